@@ -52,6 +52,10 @@ export class EscrowInstructionData {
       tokenAmount?: bigint;
       lockDuration?: bigint;
       solAmount?: bigint;
+      minTokensOut?: bigint;
+      deadline?: bigint;
+      fillToCap?: boolean;
+      whitelistProof?: Buffer[];
       investorPubkey?: PublicKey;
     }
   ) {}
@@ -67,6 +71,21 @@ export class EscrowInstructionData {
       const solAmountBuffer = Buffer.alloc(8);
       solAmountBuffer.writeBigUInt64LE(this.params.solAmount);
       buffers.push(solAmountBuffer);
+      // Add minimum tokens out (8 bytes, little endian), 0 = no slippage limit
+      const minTokensOutBuffer = Buffer.alloc(8);
+      minTokensOutBuffer.writeBigUInt64LE(this.params.minTokensOut ?? 0n);
+      buffers.push(minTokensOutBuffer);
+      // Add deadline (8 bytes, little endian unix timestamp), 0 = no deadline
+      const deadlineBuffer = Buffer.alloc(8);
+      deadlineBuffer.writeBigInt64LE(this.params.deadline ?? 0n);
+      buffers.push(deadlineBuffer);
+      // Add fill-to-cap flag (1 byte): take only what's left to the hard cap instead of failing
+      buffers.push(Buffer.from([this.params.fillToCap ? 1 : 0]));
+      // Add whitelist Merkle proof (u32 length + 32-byte nodes), empty when the sale has no root
+      const proof = this.params.whitelistProof ?? [];
+      const proofLengthBuffer = Buffer.alloc(4);
+      proofLengthBuffer.writeUInt32LE(proof.length);
+      buffers.push(proofLengthBuffer, ...proof);
     } else if (this.instruction === EscrowInstruction.NextUnlockTime && this.params?.investorPubkey) {
       // Add investor pubkey (32 bytes)
       buffers.push(this.params.investorPubkey.toBuffer());
@@ -81,15 +100,19 @@ export class EscrowInstructionData {
 }
 
 // PDA helpers
-export function findGlobalEscrowPDA(initializer: PublicKey, tokenMint: PublicKey): [PublicKey, number] {
-  return PublicKey.findProgramAddressSync(
-    [
-      Buffer.from('global_escrow'),
-      initializer.toBuffer(),
-      tokenMint.toBuffer(),
-    ],
-    PROGRAM_ID
-  );
+// Sale 0 keeps the original seeds; later sales of the same mint add the sale id
+export function findGlobalEscrowPDA(initializer: PublicKey, tokenMint: PublicKey, saleId: bigint = 0n): [PublicKey, number] {
+  const seeds = [
+    Buffer.from('global_escrow'),
+    initializer.toBuffer(),
+    tokenMint.toBuffer(),
+  ];
+  if (saleId !== 0n) {
+    const saleIdBuffer = Buffer.alloc(8);
+    saleIdBuffer.writeBigUInt64LE(saleId);
+    seeds.push(saleIdBuffer);
+  }
+  return PublicKey.findProgramAddressSync(seeds, PROGRAM_ID);
 }
 
 export function findInvestorPDA(investor: PublicKey, globalEscrow: PublicKey): [PublicKey, number] {
@@ -134,14 +157,17 @@ export async function createDepositSolInstruction(
   globalEscrow: PublicKey,
   tokenMint: PublicKey,
   recipientWallet: PublicKey,
-  solAmount: bigint
+  solAmount: bigint,
+  minTokensOut: bigint = 0n,
+  whitelistProof: Buffer[] = [],
+  deadline: bigint = 0n
 ): Promise<TransactionInstruction> {
   const [investorPDA] = findInvestorPDA(investor, globalEscrow);
   const [solVaultPDA] = findSolVaultPDA(investor, globalEscrow);
   const [tokenVaultPDA] = findTokenVaultPDA(globalEscrow);
   const investorTokenAccount = await getAssociatedTokenAddress(tokenMint, investor);
 
-  const data = new EscrowInstructionData(EscrowInstruction.DepositSol, { solAmount });
+  const data = new EscrowInstructionData(EscrowInstruction.DepositSol, { solAmount, minTokensOut, deadline, whitelistProof });
 
   return new TransactionInstruction({
     programId: PROGRAM_ID,
@@ -279,7 +305,7 @@ function parseGlobalEscrowData(data: Buffer): {
   initializationTimestamp: bigint;
 } {
   const reader = new DataView(data.buffer, data.byteOffset, data.byteLength);
-  let offset = 0;
+  let offset = 8; // account discriminator
   
   const isInitialized = data[offset]; offset += 1; // u8
   offset += 32; // initializerPubkey 
@@ -355,7 +381,7 @@ function parseInvestorData(data: Buffer): {
   status: number;
 } {
   const reader = new DataView(data.buffer, data.byteOffset, data.byteLength);
-  let offset = 0;
+  let offset = 8; // account discriminator
   
   const isInitialized = data[offset]; offset += 1; // u8
   offset += 32; // investorPubkey
@@ -0,0 +1,395 @@
+// Off-chain account loaders and instruction builders for integrators (enabled with
+// the `client` feature).
+//
+// Accounts start with an 8-byte discriminator; accounts written before discriminators
+// were introduced start directly at is_initialized. Within either form the layout is
+// identified by its length: every layout change so far only appended fields, so each
+// historical layout is a prefix of the current one. Older accounts decode with the
+// missing fields at their zero default, which is exactly what MigrateEscrow produces
+// on-chain (except token_decimals, which MigrateEscrow refreshes from the mint).
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+    sysvar,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use thiserror::Error;
+
+use crate::{
+    find_investor_pda, find_sol_vault_pda, find_token_vault_pda, EscrowInstruction, GlobalEscrow,
+    InvestorAccount, DISCRIMINATOR_LEN, GLOBAL_ESCROW_DISCRIMINATOR, INVESTOR_ACCOUNT_DISCRIMINATOR,
+};
+
+// Size of the first deployed layouts (no discriminator), the shortest data a loader accepts
+pub const GLOBAL_ESCROW_V1_LEN: usize = 1 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 32 + 32 + 8 + 8 + 8 + 8 + 8;
+pub const INVESTOR_ACCOUNT_V1_LEN: usize = 1 + 32 + 32 + 8 + 8 + 8 + 8 + 1 + 1;
+
+#[derive(Error, Debug)]
+pub enum LoadError {
+    #[error("{account} data is {len} bytes, known layouts are {min}..={max} bytes")]
+    UnknownLayout { account: &'static str, len: usize, min: usize, max: usize },
+    #[error("{0} account is not initialized")]
+    Uninitialized(&'static str),
+    #[error("Data is not a {0} account")]
+    DiscriminatorMismatch(&'static str),
+    #[error("Malformed {account} data: {source}")]
+    Malformed { account: &'static str, source: std::io::Error },
+}
+
+/// Which layout the account data was written with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Current,
+    Legacy { len: usize, tagged: bool },
+}
+
+/// Layout of `data` if it is a GlobalEscrow account
+pub fn global_escrow_layout(data: &[u8]) -> Result<Layout, LoadError> {
+    layout_of("GlobalEscrow", data, &GLOBAL_ESCROW_DISCRIMINATOR, GLOBAL_ESCROW_V1_LEN, GlobalEscrow::LEN)
+}
+
+/// Layout of `data` if it is an InvestorAccount
+pub fn investor_layout(data: &[u8]) -> Result<Layout, LoadError> {
+    layout_of("InvestorAccount", data, &INVESTOR_ACCOUNT_DISCRIMINATOR, INVESTOR_ACCOUNT_V1_LEN, InvestorAccount::LEN)
+}
+
+/// Decode GlobalEscrow account data of any known layout
+pub fn load_global_escrow(data: &[u8]) -> Result<GlobalEscrow, LoadError> {
+    load("GlobalEscrow", data, global_escrow_layout(data)?, &GLOBAL_ESCROW_DISCRIMINATOR, GlobalEscrow::LEN)
+}
+
+/// Decode InvestorAccount data of any known layout
+pub fn load_investor(data: &[u8]) -> Result<InvestorAccount, LoadError> {
+    load("InvestorAccount", data, investor_layout(data)?, &INVESTOR_ACCOUNT_DISCRIMINATOR, InvestorAccount::LEN)
+}
+
+fn layout_of(
+    account: &'static str,
+    data: &[u8],
+    discriminator: &[u8; 8],
+    min: usize,
+    max: usize,
+) -> Result<Layout, LoadError> {
+    let tagged = data.starts_with(discriminator);
+    // Untagged data starts at is_initialized, so anything but 0 or 1 is another type
+    if !tagged && data.first().is_some_and(|&byte| byte > 1) {
+        return Err(LoadError::DiscriminatorMismatch(account));
+    }
+
+    let prefix_len = if tagged { DISCRIMINATOR_LEN } else { 0 };
+    let (len, min, max) = (data.len(), min + prefix_len, max + prefix_len - DISCRIMINATOR_LEN);
+    if !(min..=max).contains(&len) {
+        return Err(LoadError::UnknownLayout { account, len, min, max });
+    }
+    // is_initialized leads every layout
+    if data[prefix_len] != 1 {
+        return Err(LoadError::Uninitialized(account));
+    }
+    Ok(if tagged && len == max { Layout::Current } else { Layout::Legacy { len, tagged } })
+}
+
+fn load<T: BorshDeserialize>(
+    account: &'static str,
+    data: &[u8],
+    layout: Layout,
+    discriminator: &[u8; 8],
+    len: usize,
+) -> Result<T, LoadError> {
+    let decoded = match layout {
+        Layout::Current => T::try_from_slice(data),
+        Layout::Legacy { tagged, .. } => {
+            let mut padded = if tagged { Vec::new() } else { discriminator.to_vec() };
+            padded.extend_from_slice(data);
+            padded.resize(len, 0);
+            T::try_from_slice(&padded)
+        }
+    };
+    decoded.map_err(|source| LoadError::Malformed { account, source })
+}
+
+/// DepositSol with the 15 fixed accounts in handler order, PDAs and the investor's
+/// associated token account derived from `escrow` (the decoded `global_escrow`).
+/// Optional accounts the sale's configuration calls for (fee wallet, oracle feeds,
+/// whitelist entry, ...) are located by key, so push them onto `accounts` afterwards.
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_sol_ix(
+    program_id: &Pubkey,
+    investor: &Pubkey,
+    global_escrow: &Pubkey,
+    escrow: &GlobalEscrow,
+    sol_amount: u64,
+    min_tokens_out: u64,
+    deadline: i64,
+    fill_to_cap: bool,
+    whitelist_proof: Vec<[u8; 32]>,
+) -> Instruction {
+    let token_program = escrow.token_program_id();
+    let (investor_pda, _) = find_investor_pda(investor, global_escrow, program_id);
+    let (sol_vault, _) = find_sol_vault_pda(investor, global_escrow, program_id);
+    let (token_vault, _) = find_token_vault_pda(global_escrow, program_id);
+    let investor_token_account = get_associated_token_address_with_program_id(
+        investor,
+        &escrow.token_mint_pubkey,
+        &token_program,
+    );
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &EscrowInstruction::DepositSol { sol_amount, min_tokens_out, deadline, fill_to_cap, whitelist_proof },
+        vec![
+            AccountMeta::new(*investor, true),
+            AccountMeta::new(*global_escrow, false),
+            AccountMeta::new(investor_pda, false),
+            AccountMeta::new(sol_vault, false),
+            AccountMeta::new(token_vault, false),
+            AccountMeta::new(investor_token_account, false),
+            AccountMeta::new(escrow.recipient_wallet, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(escrow.oracle_program_id, false),
+            AccountMeta::new_readonly(escrow.price_feed_pubkey, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(escrow.token_mint_pubkey, false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+    )
+}
+
+/// WithdrawLockedSol of `investor`'s locked SOL, signed by the escrow's recipient wallet
+pub fn withdraw_locked_sol_ix(
+    program_id: &Pubkey,
+    global_escrow: &Pubkey,
+    escrow: &GlobalEscrow,
+    investor: &Pubkey,
+) -> Instruction {
+    let (investor_pda, _) = find_investor_pda(investor, global_escrow, program_id);
+    let (sol_vault, _) = find_sol_vault_pda(investor, global_escrow, program_id);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &EscrowInstruction::WithdrawLockedSol,
+        vec![
+            AccountMeta::new(escrow.recipient_wallet, true),
+            AccountMeta::new(*global_escrow, false),
+            AccountMeta::new(investor_pda, false),
+            AccountMeta::new(sol_vault, false),
+            AccountMeta::new(escrow.recipient_wallet, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InvestorStatus;
+
+    fn blank_escrow() -> GlobalEscrow {
+        let mut data = vec![0u8; GlobalEscrow::LEN];
+        data[..DISCRIMINATOR_LEN].copy_from_slice(&GLOBAL_ESCROW_DISCRIMINATOR);
+        GlobalEscrow::try_from_slice(&data).unwrap()
+    }
+
+    fn sample_escrow() -> GlobalEscrow {
+        let mut escrow = blank_escrow();
+        escrow.is_initialized = true;
+        escrow.token_mint_pubkey = Pubkey::new_unique();
+        escrow.recipient_wallet = Pubkey::new_unique();
+        escrow.oracle_program_id = Pubkey::new_unique();
+        escrow.price_feed_pubkey = Pubkey::new_unique();
+        escrow
+    }
+
+    /// Untagged (pre-discriminator) data of `len` bytes, or tagged data `len` bytes past the tag
+    fn account_data(discriminator: &[u8; 8], len: usize, tagged: bool) -> Vec<u8> {
+        let mut data = if tagged { discriminator.to_vec() } else { Vec::new() };
+        data.push(1);
+        data.resize(data.len() + len - 1, 0);
+        data
+    }
+
+    fn metas(ix: &Instruction) -> Vec<(Pubkey, bool, bool)> {
+        ix.accounts.iter().map(|meta| (meta.pubkey, meta.is_signer, meta.is_writable)).collect()
+    }
+
+    #[test]
+    fn deposit_sol_accounts_follow_handler_order() {
+        let program_id = Pubkey::new_unique();
+        let investor = Pubkey::new_unique();
+        let global_escrow = Pubkey::new_unique();
+        let escrow = sample_escrow();
+        let ix = deposit_sol_ix(&program_id, &investor, &global_escrow, &escrow, 5, 4, 3, true, vec![[2; 32]]);
+
+        let token_program = escrow.token_program_id();
+        // process_deposit_sol's next_account_info sequence
+        let expected = vec![
+            (investor, true, true),                                             // payer
+            (global_escrow, false, true),                                       // global_escrow_account
+            (find_investor_pda(&investor, &global_escrow, &program_id).0, false, true), // investor_account
+            (find_sol_vault_pda(&investor, &global_escrow, &program_id).0, false, true), // sol_vault_account
+            (find_token_vault_pda(&global_escrow, &program_id).0, false, true), // token_vault_account
+            (
+                get_associated_token_address_with_program_id(&investor, &escrow.token_mint_pubkey, &token_program),
+                false,
+                true,
+            ),                                                                  // investor_token_account
+            (escrow.recipient_wallet, false, true),                             // recipient_wallet
+            (token_program, false, false),                                      // token_program
+            (escrow.oracle_program_id, false, false),                           // oracle_program
+            (escrow.price_feed_pubkey, false, false),                           // price_feed
+            (system_program::id(), false, false),                               // system_program
+            (sysvar::clock::id(), false, false),                                // _clock
+            (escrow.token_mint_pubkey, false, false),                           // token_mint_account
+            (spl_associated_token_account::id(), false, false),                 // associated_token_program
+            (sysvar::rent::id(), false, false),                                 // _rent_sysvar
+        ];
+        assert_eq!(ix.program_id, program_id);
+        assert_eq!(metas(&ix), expected);
+
+        match EscrowInstruction::unpack(&ix.data).unwrap() {
+            EscrowInstruction::DepositSol { sol_amount, min_tokens_out, deadline, fill_to_cap, whitelist_proof } => {
+                assert_eq!((sol_amount, min_tokens_out, deadline, fill_to_cap), (5, 4, 3, true));
+                assert_eq!(whitelist_proof, vec![[2; 32]]);
+            }
+            other => panic!("unexpected instruction {:?}", other),
+        }
+    }
+
+    #[test]
+    fn withdraw_locked_sol_accounts_follow_handler_order() {
+        let program_id = Pubkey::new_unique();
+        let investor = Pubkey::new_unique();
+        let global_escrow = Pubkey::new_unique();
+        let escrow = sample_escrow();
+        let ix = withdraw_locked_sol_ix(&program_id, &global_escrow, &escrow, &investor);
+
+        // process_withdraw_locked_sol's next_account_info sequence
+        let expected = vec![
+            (escrow.recipient_wallet, true, true),                              // withdrawer
+            (global_escrow, false, true),                                       // global_escrow_account
+            (find_investor_pda(&investor, &global_escrow, &program_id).0, false, true), // investor_account
+            (find_sol_vault_pda(&investor, &global_escrow, &program_id).0, false, true), // sol_vault_account
+            (escrow.recipient_wallet, false, true),                             // recipient_wallet
+            (system_program::id(), false, false),                               // _system_program
+            (sysvar::clock::id(), false, false),                                // _clock
+        ];
+        assert_eq!(ix.program_id, program_id);
+        assert_eq!(metas(&ix), expected);
+        assert!(matches!(EscrowInstruction::unpack(&ix.data).unwrap(), EscrowInstruction::WithdrawLockedSol));
+    }
+
+    #[test]
+    fn layout_bounds_depend_on_the_tag() {
+        let discriminator = [9; 8];
+        // Untagged data spans min..=max - DISCRIMINATOR_LEN, tagged data min + 8..=max
+        let untagged = |len| layout_of("Test", &account_data(&discriminator, len, false), &discriminator, 2, 12);
+        let tagged = |len| layout_of("Test", &account_data(&discriminator, len, true), &discriminator, 2, 12);
+
+        assert!(matches!(untagged(1), Err(LoadError::UnknownLayout { len: 1, min: 2, max: 4, .. })));
+        assert!(matches!(untagged(2), Ok(Layout::Legacy { len: 2, tagged: false })));
+        assert!(matches!(untagged(4), Ok(Layout::Legacy { len: 4, tagged: false })));
+        assert!(matches!(untagged(5), Err(LoadError::UnknownLayout { len: 5, min: 2, max: 4, .. })));
+
+        assert!(matches!(tagged(1), Err(LoadError::UnknownLayout { len: 9, min: 10, max: 12, .. })));
+        assert!(matches!(tagged(2), Ok(Layout::Legacy { len: 10, tagged: true })));
+        assert!(matches!(tagged(3), Ok(Layout::Legacy { len: 11, tagged: true })));
+        assert!(matches!(tagged(4), Ok(Layout::Current)));
+        assert!(matches!(tagged(5), Err(LoadError::UnknownLayout { len: 13, min: 10, max: 12, .. })));
+    }
+
+    #[test]
+    fn global_escrow_loads_every_layout_length() {
+        for len in GLOBAL_ESCROW_V1_LEN..=GlobalEscrow::LEN - DISCRIMINATOR_LEN {
+            for tagged in [false, true] {
+                let data = account_data(&GLOBAL_ESCROW_DISCRIMINATOR, len, tagged);
+                let layout = global_escrow_layout(&data).unwrap();
+                let current = tagged && len == GlobalEscrow::LEN - DISCRIMINATOR_LEN;
+                assert_eq!(layout == Layout::Current, current, "len {} tagged {}", len, tagged);
+
+                let escrow = load_global_escrow(&data).unwrap();
+                assert!(escrow.is_initialized);
+                assert_eq!(escrow.discriminator, GLOBAL_ESCROW_DISCRIMINATOR);
+            }
+        }
+
+        for (len, tagged) in [
+            (GLOBAL_ESCROW_V1_LEN - 1, false),
+            (GLOBAL_ESCROW_V1_LEN - 1, true),
+            (GlobalEscrow::LEN - DISCRIMINATOR_LEN + 1, false),
+            (GlobalEscrow::LEN - DISCRIMINATOR_LEN + 1, true),
+        ] {
+            let data = account_data(&GLOBAL_ESCROW_DISCRIMINATOR, len, tagged);
+            assert!(matches!(global_escrow_layout(&data), Err(LoadError::UnknownLayout { .. })), "len {}", len);
+        }
+    }
+
+    #[test]
+    fn investor_loads_every_layout_length() {
+        for len in INVESTOR_ACCOUNT_V1_LEN..=InvestorAccount::LEN - DISCRIMINATOR_LEN {
+            for tagged in [false, true] {
+                let data = account_data(&INVESTOR_ACCOUNT_DISCRIMINATOR, len, tagged);
+                let layout = investor_layout(&data).unwrap();
+                let current = tagged && len == InvestorAccount::LEN - DISCRIMINATOR_LEN;
+                assert_eq!(layout == Layout::Current, current, "len {} tagged {}", len, tagged);
+
+                let investor = load_investor(&data).unwrap();
+                assert!(investor.is_initialized);
+                assert_eq!(investor.discriminator, INVESTOR_ACCOUNT_DISCRIMINATOR);
+            }
+        }
+
+        for (len, tagged) in [
+            (INVESTOR_ACCOUNT_V1_LEN - 1, false),
+            (INVESTOR_ACCOUNT_V1_LEN - 1, true),
+            (InvestorAccount::LEN - DISCRIMINATOR_LEN + 1, false),
+            (InvestorAccount::LEN - DISCRIMINATOR_LEN + 1, true),
+        ] {
+            let data = account_data(&INVESTOR_ACCOUNT_DISCRIMINATOR, len, tagged);
+            assert!(matches!(investor_layout(&data), Err(LoadError::UnknownLayout { .. })), "len {}", len);
+        }
+    }
+
+    #[test]
+    fn legacy_investor_keeps_its_fields() {
+        let mut investor = load_investor(&account_data(&INVESTOR_ACCOUNT_DISCRIMINATOR, INVESTOR_ACCOUNT_V1_LEN, false)).unwrap();
+        investor.investor_pubkey = Pubkey::new_unique();
+        investor.sol_deposited = 7;
+        investor.status = InvestorStatus::Deposited;
+        investor.tokens_vesting = 5;
+        let current = borsh::to_vec(&investor).unwrap();
+
+        // The first layout: no tag, nothing past bump_seed
+        let v1 = &current[DISCRIMINATOR_LEN..DISCRIMINATOR_LEN + INVESTOR_ACCOUNT_V1_LEN];
+        assert_eq!(investor_layout(v1).unwrap(), Layout::Legacy { len: INVESTOR_ACCOUNT_V1_LEN, tagged: false });
+        let loaded = load_investor(v1).unwrap();
+        assert_eq!(loaded.investor_pubkey, investor.investor_pubkey);
+        assert_eq!(loaded.sol_deposited, 7);
+        assert_eq!(loaded.status, InvestorStatus::Deposited);
+        assert_eq!(loaded.tokens_vesting, 0);
+
+        assert_eq!(investor_layout(&current).unwrap(), Layout::Current);
+        assert_eq!(load_investor(&current).unwrap().tokens_vesting, 5);
+    }
+
+    #[test]
+    fn loaders_reject_other_types_and_uninitialized_data() {
+        let investor = account_data(&INVESTOR_ACCOUNT_DISCRIMINATOR, InvestorAccount::LEN - DISCRIMINATOR_LEN, true);
+        assert!(matches!(global_escrow_layout(&investor), Err(LoadError::DiscriminatorMismatch("GlobalEscrow"))));
+        let escrow = account_data(&GLOBAL_ESCROW_DISCRIMINATOR, GlobalEscrow::LEN - DISCRIMINATOR_LEN, true);
+        assert!(matches!(investor_layout(&escrow), Err(LoadError::DiscriminatorMismatch("InvestorAccount"))));
+
+        let mut untagged = account_data(&GLOBAL_ESCROW_DISCRIMINATOR, GLOBAL_ESCROW_V1_LEN, false);
+        untagged[0] = 0;
+        assert!(matches!(load_global_escrow(&untagged), Err(LoadError::Uninitialized("GlobalEscrow"))));
+        let mut tagged = account_data(&GLOBAL_ESCROW_DISCRIMINATOR, GLOBAL_ESCROW_V1_LEN, true);
+        tagged[DISCRIMINATOR_LEN] = 0;
+        assert!(matches!(load_global_escrow(&tagged), Err(LoadError::Uninitialized("GlobalEscrow"))));
+
+        assert!(matches!(load_global_escrow(&[]), Err(LoadError::UnknownLayout { len: 0, .. })));
+    }
+}
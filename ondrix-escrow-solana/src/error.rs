@@ -0,0 +1,100 @@
+// Program error codes, returned to clients as ProgramError::Custom
+
+use solana_program::{
+    program_error::ProgramError,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, Copy, Clone)]
+pub enum EscrowError {
+    #[error("Invalid instruction")]
+    InvalidInstruction,
+    #[error("Not rent exempt")]
+    NotRentExempt,
+    #[error("Expected amount mismatch")]
+    ExpectedAmountMismatch,
+    #[error("Amount overflow")]
+    AmountOverflow,
+    #[error("Invalid escrow status")]
+    InvalidEscrowStatus,
+    #[error("Unauthorized")]
+    Unauthorized,
+    #[error("SOL still locked")]
+    SolStillLocked,
+    #[error("No SOL to withdraw")]
+    NoSolToWithdraw,
+    #[error("Invalid price feed")]
+    InvalidPriceFeed,
+    #[error("Stale price data")]
+    StalePriceData,
+    #[error("Insufficient SOL deposit")]
+    InsufficientSolDeposit,
+    #[error("Not enough tokens available")]
+    NotEnoughTokens,
+    #[error("Invalid PDA")]
+    InvalidPDA,
+    #[error("Investment amount below minimum")]
+    InvestmentBelowMinimum,
+    #[error("Investment amount exceeds maximum per address")]
+    InvestmentExceedsMaximum,
+    #[error("Invalid token account")]
+    InvalidTokenAccount,
+    #[error("Audit log account missing or invalid")]
+    InvalidAuditLog,
+    #[error("Round or lifetime token sale cap exceeded")]
+    TokenCapExceeded,
+    #[error("Price feeds disagree beyond tolerance")]
+    PriceFeedDisagreement,
+    #[error("Deposit amount is not a multiple of the deposit quantum")]
+    DepositNotQuantized,
+    #[error("Account creation rent exceeds the per-investor rent budget")]
+    RentBudgetExceeded,
+    #[error("Invocation depth too high to complete the deposit safely")]
+    CpiDepthExceeded,
+    #[error("Deposit would exceed the SOL hard cap")]
+    HardCapExceeded,
+    #[error("Soft cap was not met, locked SOL is reserved for refunds")]
+    SoftCapNotMet,
+    #[error("Invalid deposit permit")]
+    InvalidPermit,
+    #[error("Deposit permit expired")]
+    PermitExpired,
+    #[error("Cancellation window has passed")]
+    CancellationWindowClosed,
+    #[error("Deposit would receive fewer tokens than min_tokens_out")]
+    SlippageExceeded,
+    #[error("Revealed secret does not match the lottery commitment")]
+    InvalidLotteryReveal,
+    #[error("Token vault is no longer controlled by the global escrow")]
+    VaultAuthorityMismatch,
+    #[error("Withdrawals are frozen")]
+    WithdrawalsFrozen,
+    #[error("Token allowance for the current drip bucket is used up")]
+    BucketLimitReached,
+    #[error("An installment of the commitment was missed")]
+    InstallmentMissed,
+    #[error("Missing or invalid sybil attestation")]
+    InvalidAttestation,
+    #[error("Deposit would exceed the attested identity's SOL cap")]
+    IdentityCapExceeded,
+    #[error("Deposits must be top-level instructions, not invoked by another program")]
+    CpiNotAllowed,
+    #[error("Deposit executed after its deadline")]
+    DeadlineExceeded,
+    #[error("Oracle price is outside the escrow's accepted band")]
+    PriceOutOfBounds,
+    #[error("Account data belongs to a different account type")]
+    AccountDiscriminatorMismatch,
+    #[error("Investor must wait out the deposit cooldown")]
+    CooldownActive,
+    #[error("Sale has already been closed")]
+    SaleAlreadyClosed,
+    #[error("Sale has ended")]
+    SaleEnded,
+}
+
+impl From<EscrowError> for ProgramError {
+    fn from(e: EscrowError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
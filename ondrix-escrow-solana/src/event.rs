@@ -0,0 +1,150 @@
+// Events logged for indexers and the payloads read-only instructions return
+
+use solana_program::{
+    entrypoint::ProgramResult,
+    log::sol_log_data,
+    pubkey::Pubkey,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::{AuditEntry, DepositProof, InvestorStatus};
+
+// Structured log for indexers, emitted with sol_log_data next to the msg! lines
+// ("Program data: <base64>" in the transaction logs). The payload is the borsh
+// encoding: one u8 discriminant (variant index in declaration order, starting at
+// Initialized = 0) followed by the variant's fields. Variants are only ever appended.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub enum EscrowEvent {
+    Initialized {
+        global_escrow: Pubkey,
+        initializer: Pubkey,
+        token_mint: Pubkey,
+        token_amount: u64,
+        token_price_cents: u64,
+        lock_duration: i64,
+        sale_end_timestamp: i64,
+        timestamp: i64,
+    },
+    Deposited {
+        global_escrow: Pubkey,
+        investor: Pubkey,
+        sol_amount: u64,
+        tokens: u64,
+        sol_usd_price: u64,           // 8 decimals
+        timestamp: i64,
+    },
+    LockedSolWithdrawn {
+        global_escrow: Pubkey,
+        investor: Pubkey,
+        recipient: Pubkey,
+        sol_amount: u64,
+        timestamp: i64,
+    },
+    SaleClosed {
+        global_escrow: Pubkey,
+        unsold_tokens: u64,
+        tokens_sold: u64,
+        total_sol_deposited: u64,
+        timestamp: i64,
+    },
+    InvestorStatus {
+        global_escrow: Pubkey,
+        investor: Pubkey,
+        sol_deposited: u64,
+        tokens_received: u64,
+        deposit_timestamp: i64,
+        status: InvestorStatus,
+        sol_usd_price: u64,           // 8 decimals
+        unlock_timestamp: i64,        // Time-lock end for this investor
+        timestamp: i64,
+    },
+    TokenDeposited {
+        global_escrow: Pubkey,
+        investor: Pubkey,
+        payment_mint: Pubkey,
+        payment_amount: u64,          // Stablecoin base units
+        tokens: u64,
+        price_cents: u64,
+        timestamp: i64,
+    },
+}
+
+impl EscrowEvent {
+    pub fn emit(&self) -> ProgramResult {
+        sol_log_data(&[&borsh::to_vec(self)?]);
+        Ok(())
+    }
+}
+
+// Return data of every deposit: this action vs the investor's running position
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct DepositReceipt {
+    pub this_deposit_sol: u64,
+    pub this_deposit_tokens: u64,
+    pub cumulative_sol: u64,          // sol_deposited after this deposit
+    pub cumulative_tokens: u64,       // tokens_received after this deposit
+    pub sol_usd_price: u64,           // Price this deposit executed at (8 decimals)
+    pub is_top_up: bool,              // Investor already had a position before this deposit
+    pub degraded_pricing: bool,       // Priced at the cached last-good price during an oracle outage
+}
+
+// Return data of GetEscrowStatus
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct GlobalEscrowStatus {
+    pub is_initialized: bool,
+    pub total_tokens_available: u64,
+    pub tokens_sold: u64,
+    pub tokens_remaining: u64,
+    pub progress_bps: u64,            // tokens_sold / total_tokens_available in basis points
+    pub total_sol_deposited: u64,
+    pub total_sol_withdrawn: u64,
+    pub lock_duration: i64,
+    pub sale_end_timestamp: i64,
+    pub time_remaining: i64,          // Seconds until sale end (0 once ended)
+    pub global_unlock_timestamp: i64,
+    pub protocol_fee_bps: u16,
+    pub total_fees_collected: u64,
+    pub sol_usd_price: Option<u64>,   // Live price (8 decimals), only when the oracle accounts are passed
+}
+
+// Return data of GetBreakeven
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct BreakevenReport {
+    pub sol_paid: u64,                // Lamports the investor deposited
+    pub tokens: u64,                  // Tokens bought (delivered or vesting)
+    pub token_price: u64,             // Current sale price, CURVE_PRICE_SCALE units per whole token
+    pub avg_sol_usd_price: u64,       // Investor's weighted entry price (8 decimals)
+    pub breakeven_sol_usd_price: u64, // SOL price at which the tokens are worth the SOL paid (8 decimals)
+}
+
+// Return data of GetAverageSalePrice
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct AverageSalePrice {
+    pub total_usd_value_cents: u64,
+    pub usd_valued_tokens: u64,
+    pub average_price: u64,           // Blended USD price, CURVE_PRICE_SCALE units per whole token (0 before any sale)
+}
+
+// One entry of the GetQuotes return data
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct AssetQuote {
+    pub quote_mint: Pubkey,           // Native mint for SOL
+    pub price: u64,                   // Asset USD price from its feed (8 decimals)
+    pub quote_amount: u64,            // Asset base units worth the USD budget, rounded down
+    pub tokens: u64,                  // Tokens quote_amount buys at the current sale price
+}
+
+// Read-only page returned by GetAuditLog (newest entry first)
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AuditLogPage {
+    pub total_entries: u64,
+    pub entries: Vec<AuditEntry>,
+}
+
+// Read-only page returned by GetInvestorHistoryPage (oldest deposit first)
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct DepositHistoryPage {
+    pub total_deposits: u64,          // Investor's deposit_count
+    pub start: u64,                   // Deposit index of the first requested entry
+    pub entries: Vec<DepositProof>,   // Proofs in the requested range (deposits without one are skipped)
+}
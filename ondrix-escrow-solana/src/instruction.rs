@@ -0,0 +1,1576 @@
+// Instruction set of the escrow, its config updates, and the instructions it sends to
+// the staking and AMM programs it integrates with
+
+use solana_program::{
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::{
+    CurveType, DepositPermit, EscrowError, GlobalEscrow, OracleKind, PriceTier, QuoteFeed,
+    UnlockMode, BPS_DENOMINATOR, CURVE_PRICE_SCALE, MAX_DEPOSIT_REVEAL_WINDOW,
+    MAX_INSTRUCTION_STACK_HEIGHT, MAX_LOCK_DURATION, MAX_ORACLE_FEEDS, MAX_PRICE_TIERS,
+    MAX_PROTOCOL_FEE_BPS, MAX_QUOTE_FEEDS, MIN_INACTIVITY_REFUND_DELAY, MIN_LOCK_DURATION,
+};
+
+// Configuration changes settable by the initializer before the first deposit
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum ConfigUpdate {
+    UnlockCondition { mode: UnlockMode, tokens_sold_target: u64 },
+    PriceTiers { tiers: Vec<PriceTier> },
+    InactivityRefundDelay { delay: i64 },
+    EmaPricing { use_ema: bool, alpha_bps: u16 },
+    DepositProofs { enabled: bool },
+    ProtocolFee { fee_wallet: Pubkey, fee_bps: u16 },
+    DelegatedDeposits { allowed: bool },
+    MaxTotalTokensSold { cap: u64 },
+    MultiOracle { use_multi_oracle: bool, feeds: Vec<Pubkey>, max_disagreement_bps: u16 },
+    DepositQuantum { quantum: u64, round_down: bool },
+    RecipientFallback { enabled: bool },
+    MaxRentPerInvestor { lamports: u64 },
+    PartialFill { allowed: bool },
+    MaxCallerStackHeight { height: u8 },
+    SolHardCap { cap: u64 },
+    ForbidSelfDealing { forbid: bool },
+    SoftCap { lamports: u64 },
+    DegradedOracleMode { enabled: bool, max_price_age: u64, max_deposit: u64 },
+    Custodian { custodian: Pubkey },
+    StakingDelivery { staking_program: Pubkey, staking_pool: Pubkey, staking_deposit_account: Pubkey },
+    RaiseVelocity { window: i64, min_raise: u64 },
+    PermitDeposits { allowed: bool },
+    QuoteFeeds { feeds: Vec<QuoteFeed> },
+    LiquidityProvision { amm_program: Pubkey, amm_pool: Pubkey, amm_token_account: Pubkey, sol_bps: u16 },
+    CancellationWindow { window: i64 },
+    BondingCurve { use_curve: bool, curve_type: CurveType, base_price: u64, slope: u64, growth_bps: u16, step: u64 },
+    RefundQueue { enabled: bool },
+    Lottery { enabled: bool, entry_lamports: u64, entry_tokens: u64, commitment: [u8; 32] },
+    DutchAuction { start_price_cents: u64, end_price_cents: u64 },
+    VaultCheckOnDeposit { enabled: bool },
+    LinearCurveCents { base_price_cents: u64, slope_cents: u64 },
+    Vesting { enabled: bool, immediate_token_bps: u16 },
+    EarlyBirdBonus { threshold_lamports: u64, bonus_bps: u16 },
+    WhitelistRequired { required: bool },
+    WhitelistRoot { root: [u8; 32] },
+    VestingMode { enabled: bool },
+    ClaimTicketMint { mint: Pubkey },
+    DripSale { tokens_per_bucket: u64, bucket_duration: i64 },
+    Installments { enabled: bool, grace_period: i64, penalty_bps: u16 },
+    SybilDampening { authority: Pubkey, max_sol_per_identity: u64 },
+    DeferredPricing { enabled: bool, reveal_window: i64 },
+    PaymentMint { mint: Pubkey },
+    DisallowCpiDeposits { disallow: bool },
+    DepositCooldown { cooldown: i64 },
+}
+
+impl ConfigUpdate {
+    /// Validate and apply the update to the escrow state
+    pub fn apply(&self, global_escrow: &mut GlobalEscrow) -> ProgramResult {
+        match *self {
+            ConfigUpdate::UnlockCondition { mode, tokens_sold_target } => {
+                if mode != UnlockMode::Time
+                    && (tokens_sold_target == 0 || tokens_sold_target > global_escrow.total_tokens_available)
+                {
+                    msg!("Invalid unlock target: {}", tokens_sold_target);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.unlock_mode = mode;
+                global_escrow.unlock_tokens_sold_target = tokens_sold_target;
+            }
+            ConfigUpdate::PriceTiers { ref tiers } => {
+                if tiers.len() > MAX_PRICE_TIERS {
+                    msg!("Too many price tiers: {} > {}", tiers.len(), MAX_PRICE_TIERS);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                // Thresholds must be strictly ascending and every tier must have a price
+                for (i, tier) in tiers.iter().enumerate() {
+                    if tier.price_cents == 0 || (i > 0 && tier.tokens_sold_threshold <= tiers[i - 1].tokens_sold_threshold) {
+                        msg!("Invalid price tier {}: {:?}", i, tier);
+                        return Err(EscrowError::InvalidInstruction.into());
+                    }
+                }
+                let mut price_tiers = [PriceTier::default(); MAX_PRICE_TIERS];
+                price_tiers[..tiers.len()].copy_from_slice(tiers);
+                global_escrow.price_tiers = price_tiers;
+                global_escrow.price_tier_count = tiers.len() as u8;
+            }
+            ConfigUpdate::InactivityRefundDelay { delay } => {
+                // The switch protects investors from a silent operator, so the operator may
+                // only push it further out, never shorten or disable it
+                let min_delay = MIN_INACTIVITY_REFUND_DELAY.max(global_escrow.inactivity_refund_delay);
+                if delay < min_delay {
+                    msg!("Inactivity refund delay too short: {} < {}", delay, min_delay);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.inactivity_refund_delay = delay;
+            }
+            ConfigUpdate::EmaPricing { use_ema, alpha_bps } => {
+                if use_ema && (alpha_bps == 0 || alpha_bps > BPS_DENOMINATOR as u16) {
+                    msg!("Invalid EMA alpha: {} bps", alpha_bps);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.use_ema = use_ema;
+                global_escrow.ema_alpha_bps = alpha_bps;
+            }
+            ConfigUpdate::DepositProofs { enabled } => {
+                global_escrow.deposit_proofs_enabled = enabled;
+            }
+            ConfigUpdate::ProtocolFee { fee_wallet, fee_bps } => {
+                if fee_bps > MAX_PROTOCOL_FEE_BPS || (fee_bps > 0 && fee_wallet == Pubkey::default()) {
+                    msg!("Invalid protocol fee: {} bps to {}", fee_bps, fee_wallet);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.fee_wallet = fee_wallet;
+                global_escrow.protocol_fee_bps = fee_bps;
+            }
+            ConfigUpdate::DelegatedDeposits { allowed } => {
+                global_escrow.allow_delegated_deposits = allowed;
+            }
+            ConfigUpdate::MaxTotalTokensSold { cap } => {
+                if cap > global_escrow.total_tokens_available {
+                    msg!("Lifetime cap {} exceeds tokens available {}", cap, global_escrow.total_tokens_available);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.max_total_tokens_sold = cap;
+            }
+            ConfigUpdate::MultiOracle { use_multi_oracle, ref feeds, max_disagreement_bps } => {
+                if use_multi_oracle && (feeds.len() < 2 || feeds.len() > MAX_ORACLE_FEEDS || max_disagreement_bps == 0) {
+                    msg!("Invalid multi-oracle config: {} feeds, {} bps", feeds.len(), max_disagreement_bps);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                if feeds.len() > MAX_ORACLE_FEEDS {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                // Feeds must be distinct or the median is meaningless
+                for (i, feed) in feeds.iter().enumerate() {
+                    if feeds[..i].contains(feed) {
+                        msg!("Duplicate price feed: {}", feed);
+                        return Err(EscrowError::InvalidInstruction.into());
+                    }
+                }
+                let mut oracle_feeds = [Pubkey::default(); MAX_ORACLE_FEEDS];
+                oracle_feeds[..feeds.len()].copy_from_slice(feeds);
+                global_escrow.use_multi_oracle = use_multi_oracle;
+                global_escrow.oracle_feeds = oracle_feeds;
+                global_escrow.oracle_feed_count = feeds.len() as u8;
+                global_escrow.max_feed_disagreement_bps = max_disagreement_bps;
+            }
+            ConfigUpdate::DepositQuantum { quantum, round_down } => {
+                // A quantum above the maximum would reject or zero out every allowed deposit
+                if quantum > global_escrow.max_sol_investment {
+                    msg!("Deposit quantum {} exceeds max investment {}", quantum, global_escrow.max_sol_investment);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.deposit_quantum = quantum;
+                global_escrow.deposit_quantum_round_down = round_down;
+            }
+            ConfigUpdate::RecipientFallback { enabled } => {
+                global_escrow.recipient_fallback_enabled = enabled;
+            }
+            ConfigUpdate::MaxRentPerInvestor { lamports } => {
+                global_escrow.max_rent_per_investor = lamports;
+            }
+            ConfigUpdate::PartialFill { allowed } => {
+                global_escrow.allow_partial_fill = allowed;
+            }
+            ConfigUpdate::MaxCallerStackHeight { height } => {
+                // 1 = top-level only; anything above the safe limit is clamped at deposit time
+                if height as usize > MAX_INSTRUCTION_STACK_HEIGHT {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.max_caller_stack_height = height;
+            }
+            ConfigUpdate::SolHardCap { cap } => {
+                if cap > 0 && cap < global_escrow.total_sol_deposited {
+                    msg!("Hard cap {} is below SOL already raised {}", cap, global_escrow.total_sol_deposited);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                // A cap below one maximum deposit would turn away a single full-size investor
+                if cap > 0 && cap < global_escrow.max_sol_investment {
+                    msg!("Hard cap {} is below max_sol_investment {}", cap, global_escrow.max_sol_investment);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.sol_hard_cap = cap;
+            }
+            ConfigUpdate::ForbidSelfDealing { forbid } => {
+                global_escrow.forbid_self_dealing = forbid;
+            }
+            ConfigUpdate::SoftCap { lamports } => {
+                if global_escrow.sol_hard_cap > 0 && lamports > global_escrow.sol_hard_cap {
+                    msg!("Soft cap {} exceeds hard cap {}", lamports, global_escrow.sol_hard_cap);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                // Lottery SOL is only counted at the draw, after the outcome is fixed
+                if lamports > 0 && global_escrow.lottery_enabled {
+                    msg!("A soft cap can't be combined with lottery mode");
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.soft_cap_lamports = lamports;
+            }
+            ConfigUpdate::DegradedOracleMode { enabled, max_price_age, max_deposit } => {
+                // The cached price must be allowed to be older than a fresh one, and capacity must be bounded
+                if enabled && (max_price_age <= global_escrow.price_staleness_threshold || max_deposit == 0) {
+                    msg!("Invalid degraded mode config: max age {}, max deposit {}", max_price_age, max_deposit);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.degraded_mode_enabled = enabled;
+                global_escrow.degraded_max_price_age = max_price_age;
+                global_escrow.degraded_max_deposit = max_deposit;
+            }
+            ConfigUpdate::Custodian { custodian } => {
+                global_escrow.custodian = custodian;
+            }
+            ConfigUpdate::StakingDelivery { staking_program, staking_pool, staking_deposit_account } => {
+                // Either fully configured or fully off
+                if staking_program != Pubkey::default()
+                    && (staking_pool == Pubkey::default() || staking_deposit_account == Pubkey::default())
+                {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.staking_program = staking_program;
+                global_escrow.staking_pool = staking_pool;
+                global_escrow.staking_deposit_account = staking_deposit_account;
+            }
+            ConfigUpdate::RaiseVelocity { window, min_raise } => {
+                if window < 0 || (window > 0 && min_raise == 0) {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.velocity_window = window;
+                global_escrow.min_raise_per_window = min_raise;
+                global_escrow.velocity_window_start = 0;
+                global_escrow.velocity_window_raised = 0;
+            }
+            ConfigUpdate::PermitDeposits { allowed } => {
+                global_escrow.allow_permit_deposits = allowed;
+            }
+            ConfigUpdate::QuoteFeeds { ref feeds } => {
+                if feeds.len() > MAX_QUOTE_FEEDS {
+                    msg!("Too many quote feeds: {} (max {})", feeds.len(), MAX_QUOTE_FEEDS);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                // One feed per asset, or the lookup would be ambiguous
+                for (i, feed) in feeds.iter().enumerate() {
+                    if feeds[..i].iter().any(|other| other.quote_mint == feed.quote_mint) {
+                        msg!("Duplicate quote mint: {}", feed.quote_mint);
+                        return Err(EscrowError::InvalidInstruction.into());
+                    }
+                }
+                let mut quote_feeds = [QuoteFeed::default(); MAX_QUOTE_FEEDS];
+                quote_feeds[..feeds.len()].copy_from_slice(feeds);
+                global_escrow.quote_feeds = quote_feeds;
+                global_escrow.quote_feed_count = feeds.len() as u8;
+            }
+            ConfigUpdate::LiquidityProvision { amm_program, amm_pool, amm_token_account, sol_bps } => {
+                // The pool is seeded once; the reserve is gone afterwards
+                if global_escrow.liquidity_added {
+                    return Err(EscrowError::InvalidEscrowStatus.into());
+                }
+                // Either fully configured or fully off
+                if amm_program != Pubkey::default()
+                    && (amm_pool == Pubkey::default()
+                        || amm_token_account == Pubkey::default()
+                        || sol_bps == 0
+                        || sol_bps > 10000)
+                {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.amm_program = amm_program;
+                global_escrow.amm_pool = amm_pool;
+                global_escrow.amm_token_account = amm_token_account;
+                global_escrow.liquidity_sol_bps = sol_bps;
+            }
+            ConfigUpdate::CancellationWindow { window } => {
+                if window < 0 {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.cancellation_window = window;
+            }
+            ConfigUpdate::BondingCurve { use_curve, curve_type, base_price, slope, growth_bps, step } => {
+                if use_curve && base_price == 0 {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                if use_curve && curve_type == CurveType::Exponential && (growth_bps == 0 || step == 0) {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.use_curve = use_curve;
+                global_escrow.curve_type = curve_type;
+                global_escrow.curve_base_price = base_price;
+                global_escrow.curve_slope = slope;
+                global_escrow.curve_growth_bps = growth_bps;
+                global_escrow.curve_step = step;
+            }
+            ConfigUpdate::RefundQueue { enabled } => {
+                // Registered claims must stay payable
+                if !enabled && global_escrow.total_refunds_owed > 0 {
+                    return Err(EscrowError::InvalidEscrowStatus.into());
+                }
+                global_escrow.refund_queue_enabled = enabled;
+            }
+            ConfigUpdate::Lottery { enabled, entry_lamports, entry_tokens, commitment } => {
+                // The sale mode and its commitment are fixed once anything has been sold or entered
+                if global_escrow.tokens_sold > 0 || global_escrow.lottery_entry_count > 0 {
+                    return Err(EscrowError::InvalidEscrowStatus.into());
+                }
+                if enabled && (entry_lamports == 0 || entry_tokens == 0 || commitment == [0u8; 32]) {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                if enabled && global_escrow.soft_cap_lamports > 0 {
+                    msg!("Lottery mode can't be combined with a soft cap");
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.lottery_enabled = enabled;
+                global_escrow.lottery_entry_lamports = entry_lamports;
+                global_escrow.lottery_entry_tokens = entry_tokens;
+                global_escrow.lottery_commitment = commitment;
+            }
+            ConfigUpdate::DutchAuction { start_price_cents, end_price_cents } => {
+                // Both zero turns the auction off; otherwise the price must fall to a non-zero floor
+                if (start_price_cents != 0 || end_price_cents != 0)
+                    && (end_price_cents == 0 || start_price_cents <= end_price_cents)
+                {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.start_price_cents = start_price_cents;
+                global_escrow.end_price_cents = end_price_cents;
+            }
+            ConfigUpdate::VaultCheckOnDeposit { enabled } => {
+                global_escrow.check_vault_on_deposit = enabled;
+            }
+            // Whole-cent shorthand for a linear BondingCurve
+            ConfigUpdate::LinearCurveCents { base_price_cents, slope_cents } => {
+                if base_price_cents == 0 {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.use_curve = true;
+                global_escrow.curve_type = CurveType::Linear;
+                global_escrow.curve_base_price = base_price_cents
+                    .checked_mul(CURVE_PRICE_SCALE)
+                    .ok_or(EscrowError::AmountOverflow)?;
+                global_escrow.curve_slope = slope_cents
+                    .checked_mul(CURVE_PRICE_SCALE)
+                    .ok_or(EscrowError::AmountOverflow)?;
+                global_escrow.curve_growth_bps = 0;
+                global_escrow.curve_step = 0;
+            }
+            ConfigUpdate::Vesting { enabled, immediate_token_bps } => {
+                if immediate_token_bps as u64 > BPS_DENOMINATOR {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.vesting_enabled = enabled;
+                global_escrow.immediate_token_bps = immediate_token_bps;
+            }
+            ConfigUpdate::EarlyBirdBonus { threshold_lamports, bonus_bps } => {
+                if bonus_bps as u64 > BPS_DENOMINATOR || (bonus_bps > 0 && threshold_lamports == 0) {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.bonus_threshold_lamports = threshold_lamports;
+                global_escrow.bonus_bps = bonus_bps;
+            }
+            ConfigUpdate::WhitelistRequired { required } => {
+                global_escrow.whitelist_required = required;
+            }
+            ConfigUpdate::WhitelistRoot { root } => {
+                global_escrow.whitelist_root = root;
+            }
+            ConfigUpdate::VestingMode { enabled } => {
+                global_escrow.vesting_mode = enabled;
+            }
+            ConfigUpdate::ClaimTicketMint { mint } => {
+                // Tickets already in circulation must stay redeemable
+                if global_escrow.claim_tickets_outstanding > 0 {
+                    msg!("{} claim tickets outstanding", global_escrow.claim_tickets_outstanding);
+                    return Err(EscrowError::InvalidEscrowStatus.into());
+                }
+                global_escrow.claim_ticket_mint = mint;
+            }
+            ConfigUpdate::DripSale { tokens_per_bucket, bucket_duration } => {
+                if tokens_per_bucket > 0 && bucket_duration <= 0 {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.tokens_per_bucket = tokens_per_bucket;
+                global_escrow.bucket_duration = bucket_duration;
+                global_escrow.bucket_start = 0;
+                global_escrow.bucket_tokens_sold = 0;
+            }
+            ConfigUpdate::Installments { enabled, grace_period, penalty_bps } => {
+                if grace_period < 0 || penalty_bps as u64 > BPS_DENOMINATOR {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.installments_enabled = enabled;
+                global_escrow.installment_grace_period = grace_period;
+                global_escrow.installment_penalty_bps = penalty_bps;
+            }
+            ConfigUpdate::SybilDampening { authority, max_sol_per_identity } => {
+                if authority != Pubkey::default() && max_sol_per_identity == 0 {
+                    msg!("Sybil dampening needs a non-zero identity cap");
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.sybil_authority = authority;
+                global_escrow.max_sol_per_identity = max_sol_per_identity;
+            }
+            ConfigUpdate::DeferredPricing { enabled, reveal_window } => {
+                if enabled && !(1..=MAX_DEPOSIT_REVEAL_WINDOW).contains(&reveal_window) {
+                    msg!("Reveal window must be 1..={} seconds", MAX_DEPOSIT_REVEAL_WINDOW);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.deferred_pricing = enabled;
+                global_escrow.reveal_window = reveal_window;
+            }
+            ConfigUpdate::PaymentMint { mint } => {
+                // The payment vault and its accounting hold a single stablecoin
+                if global_escrow.total_payment_deposited > 0 {
+                    msg!("{} stablecoin units already deposited", global_escrow.total_payment_deposited);
+                    return Err(EscrowError::InvalidEscrowStatus.into());
+                }
+                global_escrow.payment_mint = mint;
+            }
+            ConfigUpdate::DisallowCpiDeposits { disallow } => {
+                global_escrow.disallow_cpi_deposits = disallow;
+            }
+            ConfigUpdate::DepositCooldown { cooldown } => {
+                if cooldown < 0 {
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                global_escrow.deposit_cooldown = cooldown;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Whether QueueConfigUpdate may change this once deposits have started. Only limits
+    /// and safeguards on future deposits qualify; pricing, delivery, unlock and refund
+    /// terms stay as the existing investors bought under them.
+    pub fn allowed_after_deposits(&self) -> bool {
+        match self {
+            ConfigUpdate::DepositProofs { .. }
+            | ConfigUpdate::DelegatedDeposits { .. }
+            | ConfigUpdate::MaxTotalTokensSold { .. }
+            | ConfigUpdate::DepositQuantum { .. }
+            | ConfigUpdate::RecipientFallback { .. }
+            | ConfigUpdate::MaxRentPerInvestor { .. }
+            | ConfigUpdate::MaxCallerStackHeight { .. }
+            | ConfigUpdate::SolHardCap { .. }
+            | ConfigUpdate::ForbidSelfDealing { .. }
+            | ConfigUpdate::DegradedOracleMode { .. }
+            | ConfigUpdate::RaiseVelocity { .. }
+            | ConfigUpdate::PermitDeposits { .. }
+            | ConfigUpdate::RefundQueue { .. }
+            | ConfigUpdate::VaultCheckOnDeposit { .. }
+            | ConfigUpdate::WhitelistRequired { .. }
+            | ConfigUpdate::DripSale { .. }
+            | ConfigUpdate::SybilDampening { .. }
+            | ConfigUpdate::DisallowCpiDeposits { .. }
+            | ConfigUpdate::DepositCooldown { .. } => true,
+            ConfigUpdate::UnlockCondition { .. }
+            | ConfigUpdate::PriceTiers { .. }
+            | ConfigUpdate::InactivityRefundDelay { .. }
+            | ConfigUpdate::EmaPricing { .. }
+            | ConfigUpdate::ProtocolFee { .. }
+            | ConfigUpdate::MultiOracle { .. }
+            | ConfigUpdate::PartialFill { .. }
+            | ConfigUpdate::SoftCap { .. }
+            | ConfigUpdate::Custodian { .. }
+            | ConfigUpdate::StakingDelivery { .. }
+            | ConfigUpdate::QuoteFeeds { .. }
+            | ConfigUpdate::LiquidityProvision { .. }
+            | ConfigUpdate::CancellationWindow { .. }
+            | ConfigUpdate::BondingCurve { .. }
+            | ConfigUpdate::Lottery { .. }
+            | ConfigUpdate::DutchAuction { .. }
+            | ConfigUpdate::LinearCurveCents { .. }
+            | ConfigUpdate::Vesting { .. }
+            | ConfigUpdate::EarlyBirdBonus { .. }
+            | ConfigUpdate::WhitelistRoot { .. }
+            | ConfigUpdate::VestingMode { .. }
+            | ConfigUpdate::ClaimTicketMint { .. }
+            | ConfigUpdate::Installments { .. }
+            | ConfigUpdate::DeferredPricing { .. }
+            | ConfigUpdate::PaymentMint { .. } => false,
+        }
+    }
+}
+
+// Instruction the configured staking program must implement to receive deposits
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub enum StakingInstruction {
+    /// Credit `amount` tokens, already moved into the pool deposit account, to `beneficiary`.
+    /// Accounts passed by the escrow:
+    /// 0. `[writable]` Staking pool
+    /// 1. `[writable]` Pool deposit token account
+    /// 2. `[writable]` Stake position (PDA of the staking program: ["stake_position", pool, beneficiary])
+    /// 3. `[]` Beneficiary (investor identity)
+    /// 4. `[signer]` Global escrow account (proves the call comes from the escrow)
+    /// 5. `[signer, writable]` Payer, for position rent
+    /// 6. `[]` System program
+    StakeFor { amount: u64, beneficiary: Pubkey },
+}
+
+// Instruction the configured AMM program must implement to be seeded after the sale
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub enum AmmInstruction {
+    /// Create or top up the pool with `token_amount` tokens, already moved into the pool
+    /// token account, and `sol_amount` lamports taken from the funder.
+    /// Accounts passed by the escrow:
+    /// 0. `[writable]` Pool
+    /// 1. `[writable]` Pool token account
+    /// 2. `[signer, writable]` Funder (recipient wallet), pays the SOL side and pool rent
+    /// 3. `[signer]` Global escrow account (proves the call comes from the escrow)
+    /// 4. `[]` Token program
+    /// 5. `[]` System program
+    SeedPool { token_amount: u64, sol_amount: u64 },
+}
+
+// InitializeEscrow arguments
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+    pub struct InitializeEscrowParams {
+        pub token_amount: u64,            // Tokens moved into the vault (sellable + reserved)
+        pub lock_duration: i64,
+        pub sale_end_timestamp: i64,
+        pub min_sol_investment: u64,
+        pub max_sol_investment: u64,
+        pub price_staleness_threshold: u64,
+        pub reserved_tokens: u64,         // Kept in the vault but never sold, returned at CloseSale
+        pub oracle_kind: OracleKind,      // Oracle the supplied oracle_program / price_feed belong to
+        pub token_price_cents: u64,       // Flat token price in USD cents
+        pub cliff_duration: i64,          // Vesting cliff, at most lock_duration
+        pub sale_id: u64,                 // Distinguishes concurrent sales of the same mint by one initializer
+        pub min_sol_usd_price: u64,       // Lowest SOL/USD price (8 decimals) a deposit may be priced at
+        pub max_sol_usd_price: u64,       // Highest SOL/USD price (8 decimals) a deposit may be priced at
+        pub sol_hard_cap: u64,            // Max total SOL raised in lamports; 0 = no cap
+    }
+}
+
+impl InitializeEscrowParams {
+    /// Reject parameter combinations no sale could run under, `now` being the
+    /// initialization time. The reserve is checked again against the tokens the vault
+    /// actually receives, which a transfer fee can make smaller than token_amount.
+    pub fn validate(&self, now: i64) -> ProgramResult {
+        if self.token_price_cents == 0 {
+            msg!("Token price must be non-zero");
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+
+        if self.min_sol_usd_price == 0 || self.min_sol_usd_price > self.max_sol_usd_price {
+            msg!("Invalid SOL/USD price band: {}..={}", self.min_sol_usd_price, self.max_sol_usd_price);
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+
+        // Investment limits that no deposit could satisfy
+        if self.max_sol_investment == 0 {
+            msg!("Max SOL investment must be non-zero");
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+        if self.min_sol_investment > self.max_sol_investment {
+            msg!("Min SOL investment {} exceeds max {}", self.min_sol_investment, self.max_sol_investment);
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+        // Same rule as ConfigUpdate::SolHardCap: one full-size investor must fit under the cap
+        if self.sol_hard_cap > 0 && self.sol_hard_cap < self.max_sol_investment {
+            msg!("Hard cap {} is below max_sol_investment {}", self.sol_hard_cap, self.max_sol_investment);
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+
+        if self.sale_end_timestamp <= now {
+            msg!("Sale end {} must be after initialization at {}", self.sale_end_timestamp, now);
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+
+        // Reserve must leave something to sell
+        if self.reserved_tokens >= self.token_amount {
+            msg!("Reserved tokens {} must be below token amount {}", self.reserved_tokens, self.token_amount);
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+
+        // SECURITY: Validate lock duration is reasonable (1 minute to 1 year)
+        if !(MIN_LOCK_DURATION..=MAX_LOCK_DURATION).contains(&self.lock_duration) {
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+        
+        if !(0..=self.lock_duration).contains(&self.cliff_duration) {
+            msg!("Cliff {}s must be within the {}s lock", self.cliff_duration, self.lock_duration);
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+
+        Ok(())
+    }
+}
+
+// Instruction data
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub enum EscrowInstruction {
+    /// Initialize global escrow
+    /// Accounts expected:
+    /// 0. `[signer]` Initializer account
+    /// 1. `[writable]` Global escrow account (PDA)
+    /// 2. `[]` Token mint
+    /// 3. `[writable]` Token vault account (PDA)
+    /// 4. `[writable]` Initializer's token account (source)
+    /// 5. `[]` Recipient wallet
+    /// 6. `[]` Token program (SPL Token or Token-2022, stored for every later token CPI)
+    /// 7. `[]` Associated token program
+    /// 8. `[]` System program
+    /// 9. `[]` Rent sysvar
+    /// 10. `[]` Oracle program
+    /// 11. `[]` Price feed
+    ///
+    /// Token-2022 escrows move tokens with transfer_checked, so every instruction that
+    /// transfers sale tokens must also carry the token mint (located by key).
+    InitializeEscrow(InitializeEscrowParams),
+    
+    /// Deposit SOL and receive all tokens immediately
+    /// Accounts expected:
+    /// 0. `[signer]` Investor account
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Sol vault account (PDA) - stores locked SOL
+    /// 4. `[writable]` Token vault account
+    /// 5. `[writable]` Investor's token account (destination)
+    /// 6. `[writable]` Recipient wallet (receives 50% SOL)
+    /// 7. `[]` Token program
+    /// 8. `[]` Oracle program (Chainlink or Pyth, per oracle_kind)
+    /// 9. `[]` SOL/USD price feed
+    /// 10. `[]` System program
+    /// 11. `[]` Clock sysvar
+    /// 12. `[]` Token mint account
+    /// 13. `[]` Associated token program
+    /// 14. `[]` Rent sysvar
+    ///
+    /// Optional (located by key):
+    /// - `[writable]` Deposit proof account (PDA), required when deposit proofs are enabled
+    /// - `[writable]` Fee wallet, required when a protocol fee is configured
+    /// - `[]` Every configured oracle feed, required when multi-oracle pricing is enabled
+    /// - `[writable]` Recipient vault (PDA), required when the recipient can't receive lamports directly
+    /// - `[]` Staking program, `[writable]` staking pool, pool deposit account and stake position,
+    ///   required when staking delivery is configured
+    /// - `[writable]` Analytics account (PDA), required when deposit analytics are enabled
+    /// - `[]` Whitelist entry (PDA) of the investor, required when the whitelist is enforced
+    /// - `[]` Instructions sysvar and `[writable]` sybil identity (PDA), required when a
+    ///   sybil authority is set. The transaction must carry an Ed25519 verification of the
+    ///   authority's SybilAttestation for the investor before this instruction.
+    /// - `[]` Instructions sysvar, required when CPI deposits are disallowed
+    ///
+    /// Fails with SlippageExceeded, before any transfer, if fewer than `min_tokens_out`
+    /// tokens would be sent (0 = no limit), and with DeadlineExceeded if it executes
+    /// after the unix timestamp `deadline` (0 = no deadline). Every deposit variant fails
+    /// with SaleEnded from sale_end_timestamp on.
+    ///
+    /// A deposit that would take total SOL raised past sol_hard_cap fails with
+    /// HardCapExceeded, unless `fill_to_cap` is set: then only the SOL left to the cap is
+    /// taken and the rest stays with the investor.
+    ///
+    /// `whitelist_proof` is the investor's Merkle proof against whitelist_root
+    /// (empty when no root is set). The other deposit variants carry no proof,
+    /// so only DepositSol can deposit into a Merkle-whitelisted sale.
+    DepositSol { sol_amount: u64, min_tokens_out: u64, deadline: i64, fill_to_cap: bool, whitelist_proof: Vec<[u8; 32]> },
+    
+    /// Withdraw locked SOL (only by the recipient wallet after lock period)
+    /// Accounts expected:
+    /// 0. `[signer]` Recipient wallet
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Sol vault account (PDA) - contains locked SOL
+    /// 4. `[writable]` Recipient wallet (receives the locked SOL)
+    /// 5. `[]` System program
+    /// 6. `[]` Clock sysvar
+    WithdrawLockedSol,
+    
+    /// Get escrow status (read-only)
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Clock sysvar
+    /// 2. `[]` Oracle program (optional, with 3. to log the live SOL/USD price)
+    /// 3. `[]` SOL/USD price feed (optional)
+    ///
+    /// Returns a borsh GlobalEscrowStatus as return data.
+    GetEscrowStatus,
+    
+    /// Close sale and reclaim unsold tokens plus the reserved allocation
+    /// Only recipient_wallet can call after sale_end_timestamp
+    /// Accounts expected:
+    /// 0. `[signer]` Recipient wallet
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Token vault account (PDA)
+    /// 3. `[writable]` Recipient's token account (destination)
+    /// 4. `[]` Token program
+    CloseSale,
+    
+    /// Create the audit log and enable mandatory logging of privileged actions
+    /// Only initializer can call, logging cannot be disabled afterwards
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Initializer account (pays rent)
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Audit log account (PDA)
+    /// 3. `[]` System program
+    InitializeAuditLog,
+    
+    /// Get the most recent audit log entries (read-only), returned via return data
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Audit log account (PDA)
+    GetAuditLog { max_entries: u8 },
+    
+    /// Update optional escrow configuration (only initializer, before the first deposit)
+    /// Accounts expected:
+    /// 0. `[signer]` Initializer account
+    /// 1. `[writable]` Global escrow account
+    UpdateConfig { update: ConfigUpdate },
+    
+    /// Deposit SOL priced as `chunks` consecutive slices, each at the tier active
+    /// after the previous slice, so a large order gets the blended tier price.
+    /// Accounts expected: same as DepositSol
+    DepositSolChunked { sol_amount: u64, chunks: u8 },
+    
+    /// Dead-man's switch: investor reclaims their own locked SOL once the
+    /// inactivity deadline (global unlock + inactivity_refund_delay) has passed.
+    /// The investor's beneficiary (see SetBeneficiary) may sign instead and receives the refund
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor or beneficiary account (receives refund)
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Sol vault account (PDA)
+    ClaimInactivityRefund,
+    
+    /// Pre-create investor accounts (and optionally sol vaults and ATAs) for up to
+    /// MAX_BATCH_INVESTORS investors in one transaction, paid by a single funder
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Funder account (pays rent)
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[]` Token mint
+    /// 3. `[]` System program
+    /// 4. `[]` Token program
+    /// 5. `[]` Associated token program
+    ///
+    /// Then for each investor:
+    /// - `[]` Investor wallet
+    /// - `[writable]` Investor account (PDA)
+    /// - `[writable]` Sol vault account (PDA)
+    /// - `[writable]` Investor's token account (ATA), only when `create_token_accounts`
+    BatchInitInvestors { count: u8, create_token_accounts: bool },
+    
+    /// Close settled investor accounts after the sale ends, returning rent to each investor.
+    /// Accounts not in a terminal state (SolWithdrawn / Refunded) are skipped, unless they
+    /// were provisioned and never used.
+    /// Accounts expected:
+    /// 0. `[signer]` Caller
+    /// 1. `[writable]` Global escrow account
+    ///
+    /// Then for each investor:
+    /// - `[writable]` Investor account (PDA)
+    /// - `[writable]` Investor wallet (receives rent)
+    BatchCloseInvestorAccounts { count: u8 },
+    
+    /// Get a persisted deposit proof (read-only), returned via return data
+    /// Accounts expected:
+    /// 0. `[]` Deposit proof account (PDA)
+    GetDepositProof,
+    
+    /// Register (or rotate) the executor allowed to deposit on behalf of an investor identity.
+    /// Signed once by the identity itself, e.g. through a multisig transaction.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor identity (pays rent)
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Deposit delegate account (PDA)
+    /// 3. `[]` System program
+    SetDepositDelegate { executor: Pubkey },
+    
+    /// Deposit SOL for an investor identity that cannot sign directly (multisig/PDA).
+    /// The executor signs and funds the deposit; positions, ATA and vaults belong to the identity.
+    /// Accounts expected: same as DepositSol with account 0 being the executor, plus:
+    /// 15. `[]` Investor identity
+    /// 16. `[]` Deposit delegate account (PDA)
+    DepositSolDelegated { sol_amount: u64 },
+    
+    /// Start the next sale round with a fresh per-round token cap (0 = no round cap)
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer]` Initializer account
+    /// 1. `[writable]` Global escrow account
+    AdvanceRound { round_tokens_cap: u64 },
+    
+    /// Archive the current escrow state (and optionally one investor account) into a
+    /// write-once snapshot account stamped with the slot and a hash
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Creator (pays rent)
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Snapshot account (PDA)
+    /// 3. `[]` System program
+    /// 4. `[]` (optional) Investor account to capture
+    SnapshotState { snapshot_id: u64 },
+    
+    /// Get an archived snapshot (read-only), returned via return data
+    /// Accounts expected:
+    /// 0. `[]` Snapshot account (PDA)
+    GetSnapshot,
+    
+    /// Pull immediate SOL that was routed to the recipient vault because the recipient
+    /// wallet couldn't receive it directly
+    /// Only recipient wallet can call
+    /// Accounts expected:
+    /// 0. `[signer]` Recipient wallet
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Recipient vault (PDA)
+    /// 3. `[writable]` Destination account
+    ClaimImmediate,
+    
+    /// Deposit SOL exactly like DepositSol, logging remaining compute units at each
+    /// checkpoint (validation, oracle, transfers) for profiling
+    /// Accounts expected: same as DepositSol
+    DepositSolProfiled { sol_amount: u64 },
+    
+    /// Send lamports held by the global escrow above its rent-exempt minimum
+    /// (e.g. SOL transferred to it by mistake) to the recipient wallet
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer]` Initializer account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Recipient wallet
+    SweepStraySol,
+    
+    /// Queue a config change behind the timelock. Unlike UpdateConfig this is allowed
+    /// after deposits, since investors can see the change before it takes effect, but
+    /// only for the updates ConfigUpdate::allowed_after_deposits lists.
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Initializer account (pays rent)
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Pending config account (PDA)
+    /// 3. `[]` System program
+    QueueConfigUpdate { update: ConfigUpdate },
+    
+    /// Apply the queued config change once its activation time has passed. A change
+    /// that deposits have since ruled out is dropped from the queue instead.
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Initializer account (receives rent)
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Pending config account (PDA)
+    ExecuteConfigUpdate,
+    
+    /// Get the queued config change and its activation time (read-only), returned via
+    /// return data. Empty return data when nothing is queued.
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Pending config account (PDA)
+    GetPendingConfig,
+    
+    /// Deposit exactly the SOL left under the hard cap, bounded by the investor's
+    /// remaining per-address allowance
+    /// Accounts expected: same as DepositSol
+    DepositFillCap,
+    
+    /// Investor reclaims their SOL after the sale ended below its soft cap (the
+    /// failed-sale refund claim). Refundable is whatever SOL of the position is still
+    /// in its SOL vault: the locked half plus any immediate SOL held for the
+    /// cancellation window. Immediate SOL already paid to the recipient is not.
+    /// In exchange the purchase is unwound like RefundDeposit: the tokens in hand go
+    /// back to the token vault, unclaimed vesting is forfeited, and the position is
+    /// zeroed and marked Refunded. Staked deliveries can't be returned, so their tokens
+    /// stay staked. While the sale stands failed WithdrawLockedSol and ReleaseHeldSol
+    /// refuse, so the recipient can't drain the vaults first.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account (receives refund)
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Sol vault account (PDA)
+    /// 4. `[writable]` Token vault account (PDA)
+    /// 5. `[writable]` Investor's token account
+    /// 6. `[]` Token program
+    RefundFailedSale,
+    
+    /// Pause or resume deposits (also clears an automatic raise-velocity pause).
+    /// Withdrawals and CloseSale keep working while paused.
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer]` Initializer account
+    /// 1. `[writable]` Global escrow account
+    SetPaused { paused: bool },
+    
+    /// Create (if needed) and fund the investor's permit vault
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Permit vault account (PDA)
+    /// 3. `[]` System program
+    FundPermitVault { lamports: u64 },
+    
+    /// Take unspent SOL back out of the permit vault
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Permit vault account (PDA)
+    WithdrawPermitVault { lamports: u64 },
+    
+    /// Relayed deposit authorized by an Ed25519 signature of the investor over the permit,
+    /// verified by an Ed25519 program instruction placed immediately before this one.
+    /// The deposited SOL comes from the investor's permit vault; the relayer only pays rent.
+    /// Accounts expected: same as DepositSol with account 0 being the relayer, plus:
+    /// 15. `[]` Investor identity
+    /// 16. `[writable]` Permit vault account (PDA)
+    /// 17. `[]` Instructions sysvar
+    DepositWithPermit { permit: DepositPermit },
+    
+    /// Investor backs out before the sale ends: the locked 50% in the SOL vault is
+    /// returned and all tokens received go back to the token vault. The immediate 50%
+    /// already sent to the recipient wallet is not refundable.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account (receives refund)
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Sol vault account (PDA)
+    /// 4. `[writable]` Token vault account (PDA)
+    /// 5. `[writable]` Investor token account
+    /// 6. `[]` Token program
+    RefundDeposit,
+    
+    /// Seed the configured AMM pool with the reserved tokens and `liquidity_sol_bps`
+    /// of the SOL withdrawn so far, paid from the recipient wallet. Once, after the sale.
+    /// Only recipient_wallet can call
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Recipient wallet
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Token vault account (PDA)
+    /// 3. `[writable]` Pool token account
+    /// 4. `[writable]` Pool
+    /// 5. `[]` AMM program
+    /// 6. `[]` Token program
+    /// 7. `[]` System program
+    AddLiquidity,
+    
+    /// Grow a global escrow created by an older program version to the current
+    /// `GlobalEscrow::LEN`. Fields added since then (e.g. `is_paused`) are appended
+    /// to the layout, so the zero-filled tail decodes as their off/empty defaults.
+    /// Escrows from before account discriminators also get the discriminator prepended.
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Initializer account (pays the extra rent)
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[]` System program
+    /// 3. `[]` Token mint (token_decimals is read from it, not defaulted)
+    MigrateEscrow,
+    
+    /// Reverse the investor's position within `cancellation_window` of its first deposit,
+    /// and before sale end.
+    /// Tokens go back to the token vault; locked and held immediate SOL are refunded.
+    /// The protocol fee is not refundable.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account (receives refund)
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Sol vault account (PDA)
+    /// 4. `[writable]` Token vault account (PDA)
+    /// 5. `[writable]` Investor token account
+    /// 6. `[]` Token program
+    CancelRecentDeposit,
+    
+    /// Send immediate SOL held during the cancellation window on to the recipient
+    /// once the window has passed. Anyone can call
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[writable]` Investor account (PDA)
+    /// 2. `[writable]` Sol vault account (PDA)
+    /// 3. `[writable]` Recipient wallet
+    ReleaseHeldSol,
+    
+    /// Queue a refund of the immediate SOL (net of protocol fee) of a failed sale,
+    /// which the recipient already received. Claims are paid in registration order.
+    /// Accounts expected:
+    /// 0. `[signer]` Investor account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    RegisterRefund,
+    
+    /// Put SOL into the refund vault, typically by the recipient returning raised SOL
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Funder account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Refund vault account (PDA)
+    /// 3. `[]` System program
+    FundRefundQueue { lamports: u64 },
+    
+    /// Take whatever part of the investor's queued refund the vault has been funded for
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account (receives refund)
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Refund vault account (PDA)
+    ClaimRefundFromQueue,
+    
+    /// Create the analytics PDA and start recording deposits into it.
+    /// Deposits then pass the analytics account (located by key).
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Initializer account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Analytics account (PDA)
+    /// 3. `[]` System program
+    InitializeAnalytics,
+    
+    /// Read-only: returns the DepositAnalytics account data as return data
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Analytics account (PDA)
+    GetAnalytics,
+    
+    /// Set a lock duration for one investor that replaces the global lock when
+    /// withdrawing (measured from initialization, like the global lock). 0 clears it.
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer]` Initializer account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    InvestorOverride { custom_lock_duration: i64 },
+    
+    /// Enter the lottery: pays lottery_entry_lamports into the investor's SOL vault,
+    /// no tokens yet. One entry per investor. Only while the sale runs in lottery mode.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Sol vault account (PDA)
+    /// 4. `[]` System program
+    /// 5. `[]` Whitelist entry (PDA), required when the whitelist is enforced
+    EnterLottery,
+    
+    /// Draw the lottery after sale end by revealing the committed secret. The seed mixes
+    /// in the most recent slot hash, and winners are capped by the tokens left to sell.
+    /// Only initializer can call, within LOTTERY_REVEAL_WINDOW of sale end
+    /// Accounts expected:
+    /// 0. `[signer]` Initializer account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[]` SlotHashes sysvar
+    ResolveLottery { secret: [u8; 32] },
+    
+    /// Settle a lottery entry: a winner receives lottery_entry_tokens and the entry
+    /// SOL is split like a deposit (half to recipient, half locked); a loser, or any
+    /// entry once the reveal window lapsed unresolved, is refunded in full
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Sol vault account (PDA)
+    /// 4. `[writable]` Token vault account (PDA)
+    /// 5. `[writable]` Investor token account
+    /// 6. `[writable]` Recipient wallet
+    /// 7. `[]` Token program
+    ClaimLottery,
+    
+    /// Read-only self-check: fails with VaultAuthorityMismatch unless the token vault
+    /// is still the escrow's vault PDA, owned by the token program, holding the sale
+    /// mint, with the global escrow as its sole authority (no delegate, no close authority).
+    /// Anyone can call
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Token vault account (PDA)
+    SelfAudit,
+    
+    /// Claim the vesting tokens released so far (nothing before cliff_duration, then
+    /// linear until lock_duration after the investor's first deposit). Only the not-yet-claimed remainder is transferred,
+    /// so repeating the claim is harmless. With vesting_mode on this is the only way
+    /// purchased tokens leave the vault. After the inactivity deadline the investor's
+    /// beneficiary may claim instead, into a token account of their own.
+    /// Accounts expected:
+    /// 0. `[signer]` Investor or beneficiary account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Token vault account (PDA)
+    /// 4. `[writable]` Signer's token account
+    /// 5. `[]` Token program
+    ClaimVestedTokens,
+    
+    /// Create the whitelist entry PDA for `investor`
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Initializer account (pays rent)
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Whitelist entry account (PDA)
+    /// 3. `[]` System program
+    AddToWhitelist { investor: Pubkey },
+    
+    /// Close the whitelist entry PDA for `investor`, returning its rent to the initializer
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Initializer account
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Whitelist entry account (PDA)
+    RemoveFromWhitelist { investor: Pubkey },
+    
+    /// Freeze (or unfreeze) every withdrawal and claim during an investigation.
+    /// Deposits and read-only instructions keep working while frozen.
+    /// Only initializer can call
+    /// Accounts expected:
+    /// 0. `[signer]` Initializer account
+    /// 1. `[writable]` Global escrow account
+    SetWithdrawalsFrozen { frozen: bool },
+    
+    /// Read-only: returns a BreakevenReport as return data. The investor's tokens are
+    /// valued at the current sale price (curve, auction or tier, as a deposit would
+    /// price them now); above the breakeven SOL/USD price the SOL paid is worth more.
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Investor account (PDA)
+    GetBreakeven,
+    
+    /// Redeem claim tickets for sale tokens after sale end. Whoever holds the tickets
+    /// may claim, not necessarily the original depositor: `amount` tickets are burned
+    /// and the same amount of tokens is released from the vault.
+    /// Accounts expected:
+    /// 0. `[signer]` Ticket holder
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Token vault account (PDA)
+    /// 3. `[writable]` Claim ticket mint
+    /// 4. `[writable]` Holder's claim ticket account
+    /// 5. `[writable]` Holder's token account
+    /// 6. `[]` Token program
+    ClaimTokens { amount: u64 },
+    
+    /// Read-only: checks the investor's recorded sol_usd_price against what the Chainlink
+    /// feed reported for the recorded price_round_id. Returns a borsh bool as return data.
+    /// Fails when no round was recorded or it has left the feed's live window.
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Investor account (PDA)
+    /// 2. `[]` Chainlink program
+    /// 3. `[]` Chainlink SOL/USD price feed
+    VerifyInvestorPrice,
+    
+    /// Page through an investor's deposit history (read-only), returned via return data
+    /// as a DepositHistoryPage. `count` is capped to the deposits after `start` and to
+    /// what fits in the return data buffer.
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Investor account (PDA)
+    ///
+    /// Then the deposit proof accounts (PDA) for deposit indices `start` onwards
+    GetInvestorHistoryPage { start: u64, count: u8 },
+    
+    /// Hand admin rights to `new_authority` (e.g. after a key rotation or compromise) and
+    /// optionally move recipient_wallet, which authorizes WithdrawLockedSol/CloseSale, to
+    /// `new_recipient`. The old authority can no longer administer afterwards.
+    /// Only the current authority can call
+    /// Accounts expected:
+    /// 0. `[signer]` Current authority
+    /// 1. `[writable]` Global escrow account
+    TransferAuthority { new_authority: Pubkey, new_recipient: Option<Pubkey> },
+    
+    /// Commit to depositing `total_lamports` in installments of `installment_lamports`,
+    /// one every `installment_interval` seconds starting now. Posts installment_penalty_bps
+    /// of the total as collateral on the commitment account.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account (pays rent and collateral)
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Commitment account (PDA)
+    /// 3. `[]` System program
+    CreateCommitment { total_lamports: u64, installment_lamports: u64, installment_interval: i64 },
+    
+    /// Pay the next installment of the investor's commitment through the regular deposit
+    /// path, so tokens are delivered per installment at the price of the moment. Paying
+    /// the last installment returns the collateral and closes the commitment.
+    /// Accounts expected: same as DepositSol, plus (located by key):
+    /// - `[writable]` Commitment account (PDA)
+    PayInstallment,
+    
+    /// Anyone may settle a commitment whose next installment is past due plus the grace
+    /// period: the collateral goes to recipient_wallet and the rent back to the investor.
+    /// Installments already paid stay ordinary deposits.
+    /// Accounts expected:
+    /// 0. `[signer]` Caller
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Commitment account (PDA)
+    /// 3. `[writable]` Recipient wallet (receives the collateral)
+    /// 4. `[writable]` Investor wallet (receives the rent)
+    ForfeitCommitment,
+    
+    /// Read-only: the blended USD price per token the whole sale achieved, as an
+    /// AverageSalePrice in return data. Each deposit's SOL is valued at its own
+    /// oracle price; lottery awards carry no price and are left out.
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    GetAverageSalePrice,
+    
+    /// Close a fully settled escrow: the token vault (empty, e.g. after CloseSale) and the
+    /// global escrow account, with their rent going to the authority. Requires the sale to
+    /// have ended, every investor account closed through BatchCloseInvestorAccounts (so no
+    /// investor has SOL or tokens left to collect) and nothing held for later payout.
+    /// Only the authority can call
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Authority (receives the rent)
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Token vault account (PDA)
+    /// 3. `[]` Token program
+    CloseEscrow,
+    
+    /// Designate (or clear, with the default pubkey) a recovery address for the position.
+    /// The beneficiary can run ClaimInactivityRefund and ClaimVestedTokens for the investor,
+    /// but only after the inactivity deadline (global unlock + inactivity_refund_delay)
+    /// Accounts expected:
+    /// 0. `[signer]` Investor account
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    SetBeneficiary { beneficiary: Pubkey },
+    
+    /// Return the rent left in an investor's SOL vault once the locked SOL has been
+    /// withdrawn (status SolWithdrawn). Anyone can call; the lamports always go to the
+    /// investor_pubkey stored on the investor account
+    /// Accounts expected:
+    /// 0. `[signer]` Caller
+    /// 1. `[]` Investor account (PDA)
+    /// 2. `[writable]` Sol vault account (PDA)
+    /// 3. `[writable]` Investor wallet (receives the rent)
+    CloseSolVault,
+    
+    /// Quote a USD budget in every supported quote asset: native SOL and each mint in
+    /// quote_feeds, each priced by its own feed (read once). Returns a borsh
+    /// Vec<AssetQuote> as return data. Tokens are at the current spot price, ignoring
+    /// curve movement within the purchase, bonuses and caps.
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Oracle program
+    ///
+    /// Then, in any order:
+    /// - `[]` The price feed of every quote asset
+    /// - `[]` The mint of every quote asset other than SOL
+    GetQuotes { usd_budget_cents: u64 },
+    
+    /// First step of a deferred-pricing deposit: escrow `sol_amount` on the investor's
+    /// deferred deposit PDA without pricing it. One open commit per investor.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account
+    /// 1. `[]` Global escrow account
+    /// 2. `[writable]` Deferred deposit account (PDA)
+    /// 3. `[]` System program
+    CommitDeposit { sol_amount: u64 },
+    
+    /// Second step: price the committed SOL at the oracle price of this (later) slot and
+    /// deposit it exactly like DepositSol. Must land after the commit slot and before
+    /// commit_timestamp + reveal_window, or sale end if that comes first. Whatever the deposit doesn't take, and the PDA's
+    /// rent, go back to the investor and the PDA is closed.
+    /// Accounts expected: same as DepositSol, plus:
+    /// 15. `[writable]` Deferred deposit account (PDA)
+    RevealPrice,
+    
+    /// Refund a commit whose reveal window passed without a reveal. Anyone can call;
+    /// the SOL and rent always go to the committing investor.
+    /// Accounts expected:
+    /// 0. `[signer]` Caller
+    /// 1. `[writable]` Deferred deposit account (PDA)
+    /// 2. `[writable]` Investor wallet
+    ReclaimExpiredCommit,
+    
+    /// Get one investor's position (read-only), logged and emitted as an
+    /// EscrowEvent::InvestorStatus
+    /// Accounts expected:
+    /// 0. `[]` Global escrow account
+    /// 1. `[]` Investor account (PDA)
+    /// 2. `[]` Clock sysvar
+    GetInvestorStatus,
+    
+    /// Buy with the configured stablecoin instead of SOL: `token_amount` payment
+    /// units are valued 1:1 in USD at the current tier or auction price, no oracle.
+    /// Half goes to the recipient, half is locked in the payment vault until the
+    /// global unlock (WithdrawLockedPayment). Not available in lottery, curve,
+    /// claim-ticket, staking or soft-cap sales; SOL-denominated investment limits
+    /// and caps don't apply.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Investor account
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Investor account (PDA)
+    /// 3. `[writable]` Investor's payment token account (source)
+    /// 4. `[writable]` Recipient wallet's payment token account
+    /// 5. `[writable]` Payment vault (PDA), created on first use
+    /// 6. `[]` Payment mint
+    /// 7. `[]` Payment token program
+    /// 8. `[writable]` Token vault account
+    /// 9. `[writable]` Investor's token account (destination, must exist)
+    /// 10. `[]` Token mint account
+    /// 11. `[]` Token program
+    /// 12. `[]` System program
+    /// 13. `[]` Clock sysvar
+    ///
+    /// Optional (located by key):
+    /// - `[]` Whitelist entry (PDA) of the investor, required when the whitelist is enforced
+    DepositToken { token_amount: u64 },
+    
+    /// Release the locked stablecoin half to the recipient after the global unlock
+    /// Accounts expected:
+    /// 0. `[signer]` Recipient wallet
+    /// 1. `[writable]` Global escrow account
+    /// 2. `[writable]` Payment vault (PDA)
+    /// 3. `[writable]` Recipient wallet's payment token account
+    /// 4. `[]` Payment mint
+    /// 5. `[]` Payment token program
+    /// 6. `[]` Clock sysvar
+    WithdrawLockedPayment,
+    
+    /// Grow an investor account created by an older program version to the current
+    /// `InvestorAccount::LEN`, prepending the discriminator to accounts from before it.
+    /// Appended fields decode as their zero defaults. Anyone may call; the position
+    /// itself is unchanged. Legacy positions must migrate before any other instruction
+    /// can read them.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` Payer (pays the extra rent)
+    /// 1. `[writable]` Investor account (PDA)
+    /// 2. `[]` System program
+    MigrateInvestor,
+}
+
+impl EscrowInstruction {
+    /// Minimum payload length (bytes after the 1-byte discriminant) for each variant.
+    /// Variable-size payloads list their smallest valid encoding. None = unknown variant.
+    pub fn min_payload_len(discriminant: u8) -> Option<usize> {
+        let len = match discriminant {
+            0 => InitializeEscrowParams::LEN, // InitializeEscrow
+            1 => 8 + 8 + 8 + 1 + 4,           // DepositSol { sol_amount, min_tokens_out, deadline, fill_to_cap, whitelist_proof }
+            2 => 0,                           // WithdrawLockedSol
+            3 => 0,                           // GetEscrowStatus
+            4 => 0,                           // CloseSale
+            5 => 0,                           // InitializeAuditLog
+            6 => 1,                           // GetAuditLog { max_entries }
+            7 => 1,                           // UpdateConfig { update } (ConfigUpdate tag)
+            8 => 8 + 1,                       // DepositSolChunked { sol_amount, chunks }
+            9 => 0,                           // ClaimInactivityRefund
+            10 => 1 + 1,                      // BatchInitInvestors { count, create_token_accounts }
+            11 => 1,                          // BatchCloseInvestorAccounts { count }
+            12 => 0,                          // GetDepositProof
+            13 => 32,                         // SetDepositDelegate { executor }
+            14 => 8,                          // DepositSolDelegated { sol_amount }
+            15 => 8,                          // AdvanceRound { round_tokens_cap }
+            16 => 8,                          // SnapshotState { snapshot_id }
+            17 => 0,                          // GetSnapshot
+            18 => 0,                          // ClaimImmediate
+            19 => 8,                          // DepositSolProfiled { sol_amount }
+            20 => 0,                          // SweepStraySol
+            21 => 1,                          // QueueConfigUpdate { update } (ConfigUpdate tag)
+            22 => 0,                          // ExecuteConfigUpdate
+            23 => 0,                          // GetPendingConfig
+            24 => 0,                          // DepositFillCap
+            25 => 0,                          // RefundFailedSale
+            26 => 1,                          // SetPaused { paused }
+            27 => 8,                          // FundPermitVault { lamports }
+            28 => 8,                          // WithdrawPermitVault { lamports }
+            29 => DepositPermit::LEN,         // DepositWithPermit { permit }
+            30 => 0,                          // RefundDeposit
+            31 => 0,                          // AddLiquidity
+            32 => 0,                          // MigrateEscrow
+            33 => 0,                          // CancelRecentDeposit
+            34 => 0,                          // ReleaseHeldSol
+            35 => 0,                          // RegisterRefund
+            36 => 8,                          // FundRefundQueue { lamports }
+            37 => 0,                          // ClaimRefundFromQueue
+            38 => 0,                          // InitializeAnalytics
+            39 => 0,                          // GetAnalytics
+            40 => 8,                          // InvestorOverride { custom_lock_duration }
+            41 => 0,                          // EnterLottery
+            42 => 32,                         // ResolveLottery { secret }
+            43 => 0,                          // ClaimLottery
+            44 => 0,                          // SelfAudit
+            45 => 0,                          // ClaimVestedTokens
+            46 => 32,                         // AddToWhitelist { investor }
+            47 => 32,                         // RemoveFromWhitelist { investor }
+            48 => 1,                          // SetWithdrawalsFrozen { frozen }
+            49 => 0,                          // GetBreakeven
+            50 => 8,                          // ClaimTokens { amount }
+            51 => 0,                          // VerifyInvestorPrice
+            52 => 8 + 1,                      // GetInvestorHistoryPage { start, count }
+            53 => 32 + 1,                     // TransferAuthority { new_authority, new_recipient }
+            54 => 8 + 8 + 8,                  // CreateCommitment { total_lamports, installment_lamports, installment_interval }
+            55 => 0,                          // PayInstallment
+            56 => 0,                          // ForfeitCommitment
+            57 => 0,                          // GetAverageSalePrice
+            58 => 0,                          // CloseEscrow
+            59 => 32,                         // SetBeneficiary: beneficiary
+            60 => 0,                          // CloseSolVault
+            61 => 8,                          // GetQuotes: usd_budget_cents
+            62 => 8,                          // CommitDeposit: sol_amount
+            63 => 0,                          // RevealPrice
+            64 => 0,                          // ReclaimExpiredCommit
+            65 => 0,                          // GetInvestorStatus
+            66 => 8,                          // DepositToken
+            67 => 0,                          // WithdrawLockedPayment
+            68 => 0,                          // MigrateInvestor
+            _ => return None,
+        };
+        Some(len)
+    }
+
+    /// Check the payload length against the variant table, then deserialize.
+    /// Truncated, oversized and unknown payloads all surface as InvalidInstruction.
+    pub fn unpack(instruction_data: &[u8]) -> Result<Self, ProgramError> {
+        let (&discriminant, payload) = instruction_data.split_first().ok_or_else(|| {
+            msg!("Empty instruction data");
+            EscrowError::InvalidInstruction
+        })?;
+
+        let min_len = Self::min_payload_len(discriminant).ok_or_else(|| {
+            msg!("Unknown instruction discriminant: {}", discriminant);
+            EscrowError::InvalidInstruction
+        })?;
+
+        if payload.len() < min_len {
+            msg!(
+                "Truncated instruction {}: expected at least {} payload bytes, got {}",
+                discriminant,
+                min_len,
+                payload.len()
+            );
+            return Err(EscrowError::InvalidInstruction.into());
+        }
+
+        Self::try_from_slice(instruction_data).map_err(|_| {
+            msg!("Malformed payload for instruction {}", discriminant);
+            EscrowError::InvalidInstruction.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SOL_LAMPORTS;
+
+    fn valid_params() -> InitializeEscrowParams {
+        InitializeEscrowParams {
+            token_amount: 1_000_000,
+            lock_duration: 3_600,
+            sale_end_timestamp: 10_000,
+            min_sol_investment: SOL_LAMPORTS / 10,
+            max_sol_investment: 10 * SOL_LAMPORTS,
+            price_staleness_threshold: 300,
+            reserved_tokens: 0,
+            oracle_kind: OracleKind::Chainlink,
+            token_price_cents: 25,
+            cliff_duration: 0,
+            sale_id: 0,
+            min_sol_usd_price: 1,
+            max_sol_usd_price: u64::MAX,
+            sol_hard_cap: 0,
+        }
+    }
+
+    #[test]
+    fn init_params_reject_bad_combinations() {
+        let now = 1_000;
+        assert_eq!(valid_params().validate(now), Ok(()));
+
+        let bad = [
+            InitializeEscrowParams { max_sol_investment: 0, min_sol_investment: 0, ..valid_params() },
+            InitializeEscrowParams { min_sol_investment: 11 * SOL_LAMPORTS, ..valid_params() },
+            InitializeEscrowParams { sale_end_timestamp: now, ..valid_params() },
+            InitializeEscrowParams { sale_end_timestamp: now - 1, ..valid_params() },
+            InitializeEscrowParams { token_price_cents: 0, ..valid_params() },
+            InitializeEscrowParams { min_sol_usd_price: 0, ..valid_params() },
+            InitializeEscrowParams { min_sol_usd_price: 300, max_sol_usd_price: 200, ..valid_params() },
+            InitializeEscrowParams { reserved_tokens: 1_000_000, ..valid_params() },
+            InitializeEscrowParams { lock_duration: MIN_LOCK_DURATION - 1, cliff_duration: 0, ..valid_params() },
+            InitializeEscrowParams { lock_duration: MAX_LOCK_DURATION + 1, ..valid_params() },
+            InitializeEscrowParams { cliff_duration: 3_601, ..valid_params() },
+            InitializeEscrowParams { cliff_duration: -1, ..valid_params() },
+        ];
+        for params in bad {
+            assert_eq!(params.validate(now), Err(EscrowError::InvalidInstruction.into()), "{:?}", params);
+        }
+
+        // Equal limits and a cliff spanning the whole lock are allowed
+        let edge = InitializeEscrowParams {
+            min_sol_investment: SOL_LAMPORTS,
+            max_sol_investment: SOL_LAMPORTS,
+            cliff_duration: 3_600,
+            ..valid_params()
+        };
+        assert_eq!(edge.validate(now), Ok(()));
+    }
+
+    #[test]
+    fn init_hard_cap_fits_one_max_investment() {
+        let params = InitializeEscrowParams { sol_hard_cap: 10 * SOL_LAMPORTS, ..valid_params() };
+        assert_eq!(params.validate(0), Ok(()));
+
+        let params = InitializeEscrowParams { sol_hard_cap: 10 * SOL_LAMPORTS - 1, ..valid_params() };
+        assert_eq!(params.validate(0), Err(EscrowError::InvalidInstruction.into()));
+    }
+
+    #[test]
+    fn only_deposit_safeguards_change_after_deposits() {
+        let allowed = [
+            ConfigUpdate::SolHardCap { cap: 1 },
+            ConfigUpdate::DepositCooldown { cooldown: 60 },
+            ConfigUpdate::WhitelistRequired { required: true },
+        ];
+        assert!(allowed.iter().all(ConfigUpdate::allowed_after_deposits));
+
+        let blocked = [
+            ConfigUpdate::VestingMode { enabled: true },
+            ConfigUpdate::SoftCap { lamports: 1 },
+            ConfigUpdate::WhitelistRoot { root: [1; 32] },
+            ConfigUpdate::Custodian { custodian: Pubkey::new_unique() },
+            ConfigUpdate::ProtocolFee { fee_wallet: Pubkey::new_unique(), fee_bps: 100 },
+            ConfigUpdate::PartialFill { allowed: true },
+            ConfigUpdate::LinearCurveCents { base_price_cents: 25, slope_cents: 1 },
+        ];
+        assert!(!blocked.iter().any(ConfigUpdate::allowed_after_deposits));
+    }
+
+    #[test]
+    fn unpack_rejects_empty_unknown_and_truncated_data() {
+        let invalid: ProgramError = EscrowError::InvalidInstruction.into();
+        assert_eq!(EscrowInstruction::unpack(&[]).unwrap_err(), invalid);
+        assert_eq!(EscrowInstruction::unpack(&[u8::MAX]).unwrap_err(), invalid);
+        // DepositSol with fewer than 8 bytes of sol_amount
+        assert_eq!(EscrowInstruction::unpack(&[1, 0, 0, 0]).unwrap_err(), invalid);
+        assert_eq!(EscrowInstruction::unpack(&[0; InitializeEscrowParams::LEN]).unwrap_err(), invalid);
+    }
+
+    #[test]
+    fn unpack_rejects_every_truncated_payload() {
+        let last = borsh::to_vec(&EscrowInstruction::MigrateInvestor).unwrap()[0];
+        for discriminant in 0..=last {
+            let min_len = EscrowInstruction::min_payload_len(discriminant).unwrap();
+            for len in 0..min_len {
+                let mut data = vec![0; 1 + len];
+                data[0] = discriminant;
+                assert_eq!(
+                    EscrowInstruction::unpack(&data).unwrap_err(),
+                    EscrowError::InvalidInstruction.into(),
+                    "instruction {} with {} payload bytes",
+                    discriminant,
+                    len
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unpack_roundtrips_packed_instructions() {
+        let data = borsh::to_vec(&EscrowInstruction::DepositSol {
+            sol_amount: SOL_LAMPORTS,
+            min_tokens_out: 1,
+            deadline: 2,
+            fill_to_cap: true,
+            whitelist_proof: vec![[7; 32]],
+        })
+        .unwrap();
+        match EscrowInstruction::unpack(&data).unwrap() {
+            EscrowInstruction::DepositSol { sol_amount, min_tokens_out, deadline, fill_to_cap, whitelist_proof } => {
+                assert_eq!((sol_amount, min_tokens_out, deadline, fill_to_cap), (SOL_LAMPORTS, 1, 2, true));
+                assert_eq!(whitelist_proof, vec![[7; 32]]);
+            }
+            other => panic!("unexpected instruction {:?}", other),
+        }
+
+        // Trailing bytes after a complete payload are rejected too
+        let mut oversized = borsh::to_vec(&EscrowInstruction::WithdrawLockedSol).unwrap();
+        oversized.push(0);
+        assert_eq!(EscrowInstruction::unpack(&oversized).unwrap_err(), EscrowError::InvalidInstruction.into());
+    }
+
+    #[test]
+    fn every_instruction_has_a_payload_length() {
+        let last = borsh::to_vec(&EscrowInstruction::MigrateInvestor).unwrap()[0];
+        for discriminant in 0..=last {
+            assert!(EscrowInstruction::min_payload_len(discriminant).is_some(), "{}", discriminant);
+        }
+        assert!(EscrowInstruction::min_payload_len(last + 1).is_none());
+    }
+}
@@ -0,0 +1,66 @@
+// Serialized sizes of the fixed-layout accounts and arguments. `fixed_len!` wraps a
+// struct definition and sums its field sizes into `LEN`, so a field added to a layout
+// can't leave the allocation size behind.
+
+use solana_program::pubkey::Pubkey;
+
+use crate::{AuditAction, CurveType, InvestorStatus, OracleKind, UnlockMode};
+
+/// Borsh-serialized size of a type whose encoding has a fixed length
+pub trait FixedLen {
+    const LEN: usize;
+}
+
+macro_rules! impl_fixed_len {
+    ($($ty:ty => $len:expr),* $(,)?) => {
+        $(impl FixedLen for $ty {
+            const LEN: usize = $len;
+        })*
+    };
+}
+
+impl_fixed_len!(bool => 1, u8 => 1, u16 => 2, u32 => 4, u64 => 8, i64 => 8, Pubkey => 32);
+
+// Unit enums serialize as their one-byte variant index
+impl_fixed_len!(OracleKind => 1, CurveType => 1, UnlockMode => 1, InvestorStatus => 1, AuditAction => 1);
+
+impl<T: FixedLen, const N: usize> FixedLen for [T; N] {
+    const LEN: usize = T::LEN * N;
+}
+
+/// Define a struct of FixedLen fields along with `pub const LEN`, its serialized size
+macro_rules! fixed_len {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),*
+        }
+        
+        impl $name {
+            pub const LEN: usize = 0 $(+ <$ty as $crate::FixedLen>::LEN)*;
+        }
+        
+        impl $crate::FixedLen for $name {
+            const LEN: usize = $name::LEN;
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GlobalEscrow, InitializeEscrowParams, InvestorAccount, PriceTier, QuoteFeed};
+
+    #[test]
+    fn len_matches_serialized_size() {
+        assert_eq!(borsh::to_vec(&GlobalEscrow::default()).unwrap().len(), GlobalEscrow::LEN);
+        assert_eq!(borsh::to_vec(&InvestorAccount::default()).unwrap().len(), InvestorAccount::LEN);
+        assert_eq!(borsh::to_vec(&InitializeEscrowParams::default()).unwrap().len(), InitializeEscrowParams::LEN);
+        assert_eq!(borsh::to_vec(&PriceTier::default()).unwrap().len(), PriceTier::LEN);
+        assert_eq!(borsh::to_vec(&QuoteFeed::default()).unwrap().len(), QuoteFeed::LEN);
+    }
+}
@@ -0,0 +1,302 @@
+// Chainlink and Pyth price reads, decimals normalization and the multi-feed median
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+use chainlink_solana::{
+    latest_round_data,
+};
+
+use crate::{
+    checked_mul_div, find_account_by_key, EscrowError, GlobalEscrow, OracleKind, BPS_DENOMINATOR,
+    CHAINLINK_DECIMALS_OFFSET, CHAINLINK_FEED_HEADER_LEN, CHAINLINK_LATEST_ROUND_ID_OFFSET,
+    CHAINLINK_LIVE_CURSOR_OFFSET, CHAINLINK_LIVE_LENGTH_OFFSET, CHAINLINK_TRANSMISSION_LEN,
+    CHAINLINK_USD_DECIMALS, PYTH_ACCOUNT_TYPE_PRICE, PYTH_MAGIC, PYTH_PRICE_ACCOUNT_MIN_LEN,
+    PYTH_STATUS_TRADING, PYTH_VERSION,
+};
+
+/// SOL/USD price from the escrow's oracle (Chainlink or Pyth, per `oracle_kind`),
+/// normalized to `CHAINLINK_USD_DECIMALS` decimals
+pub fn get_oracle_price<'a>(
+    price_feed_account: &AccountInfo<'a>,
+    oracle_program: &AccountInfo<'a>,
+    global_escrow: &GlobalEscrow,
+) -> Result<(u64, i64), ProgramError> {
+    // Validate oracle program ID using immutable oracle config
+    if oracle_program.key != &global_escrow.oracle_program_id {
+        msg!("Invalid oracle program: {}", oracle_program.key);
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+
+    if global_escrow.oracle_kind == OracleKind::Chainlink {
+        let live_decimals = chainlink_feed_decimals(&price_feed_account.data.borrow());
+        if live_decimals != Some(global_escrow.feed_decimals()) {
+            msg!("Feed decimals {:?} differ from the {} cached at init", live_decimals, global_escrow.feed_decimals());
+            return Err(EscrowError::InvalidPriceFeed.into());
+        }
+    }
+
+    let (price, timestamp) =
+        get_quote_price(price_feed_account, oracle_program, global_escrow, &spl_token::native_mint::id())?;
+    global_escrow.check_sol_usd_price(price)?;
+    
+    Ok((price, timestamp))
+}
+
+/// USD price of `quote_mint` from its mapped feed (see `GlobalEscrow::price_feed_for`)
+pub fn get_quote_price<'a>(
+    price_feed_account: &AccountInfo<'a>,
+    oracle_program: &AccountInfo<'a>,
+    global_escrow: &GlobalEscrow,
+    quote_mint: &Pubkey,
+) -> Result<(u64, i64), ProgramError> {
+    if oracle_program.key != &global_escrow.oracle_program_id {
+        msg!("Invalid oracle program: {}", oracle_program.key);
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+
+    // Validate price feed address against the feed mapped for this asset
+    let expected_feed = global_escrow.price_feed_for(quote_mint).ok_or_else(|| {
+        msg!("No price feed configured for quote asset {}", quote_mint);
+        EscrowError::InvalidPriceFeed
+    })?;
+    if price_feed_account.key != &expected_feed {
+        msg!("Invalid price feed: {}", price_feed_account.key);
+        msg!("Expected: {}", expected_feed);
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+
+    read_oracle_price(price_feed_account, oracle_program, global_escrow)
+}
+
+/// Read the latest price from the feed per `oracle_kind` and apply the staleness and
+/// positivity checks. Callers are responsible for validating the program and feed addresses.
+pub fn read_oracle_price<'a>(
+    price_feed_account: &AccountInfo<'a>,
+    oracle_program: &AccountInfo<'a>,
+    global_escrow: &GlobalEscrow,
+) -> Result<(u64, i64), ProgramError> {
+    let (answer, timestamp) = match global_escrow.oracle_kind {
+        OracleKind::Chainlink => {
+            // Get latest round data from Chainlink
+            let round_data = latest_round_data(
+                oracle_program.clone(),
+                price_feed_account.clone(),
+            ).map_err(|_| EscrowError::InvalidPriceFeed)?;
+            let decimals = chainlink_feed_decimals(&price_feed_account.data.borrow())
+                .ok_or(EscrowError::InvalidPriceFeed)?;
+            (normalize_price_decimals(round_data.answer, decimals as i32)?, round_data.timestamp as i64)
+        }
+        OracleKind::Pyth => read_pyth_price(price_feed_account, oracle_program)?,
+    };
+    
+    // Check for stale data using immutable config threshold
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp - timestamp > global_escrow.price_staleness_threshold as i64 {
+        msg!("Stale price feed: {} > {}", current_timestamp - timestamp, global_escrow.price_staleness_threshold);
+        return Err(EscrowError::StalePriceData.into());
+    }
+    
+    // Ensure price is positive
+    if answer <= 0 {
+        msg!("Invalid price: {}", answer);
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    let price = u64::try_from(answer).map_err(|_| EscrowError::InvalidPriceFeed)?;
+    
+    Ok((price, timestamp))
+}
+
+/// Aggregate price of a Pyth v2 price account, rescaled from its exponent to
+/// `CHAINLINK_USD_DECIMALS` decimals, with the aggregate's publish timestamp
+pub fn read_pyth_price(
+    price_account: &AccountInfo,
+    oracle_program: &AccountInfo,
+) -> Result<(i128, i64), ProgramError> {
+    if price_account.owner != oracle_program.key {
+        msg!("Pyth price account not owned by the Pyth program");
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    let data = price_account.data.borrow();
+    if data.len() < PYTH_PRICE_ACCOUNT_MIN_LEN {
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let read_i64 = |offset: usize| i64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    
+    // Header: magic, version, account type
+    if read_u32(0) != PYTH_MAGIC || read_u32(4) != PYTH_VERSION || read_u32(8) != PYTH_ACCOUNT_TYPE_PRICE {
+        msg!("Not a Pyth price account");
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    let expo = read_u32(20) as i32;
+    let timestamp = read_i64(96);
+    // Aggregate price info: price, conf, status
+    let price = read_i64(208);
+    if read_u32(224) != PYTH_STATUS_TRADING {
+        msg!("Pyth price is not trading");
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    Ok((normalize_price_decimals(price as i128, -expo)?, timestamp))
+}
+
+/// Rescale a price carrying `decimals` decimals to `CHAINLINK_USD_DECIMALS`
+pub fn normalize_price_decimals(price: i128, decimals: i32) -> Result<i128, ProgramError> {
+    let shift = CHAINLINK_USD_DECIMALS as i32 - decimals;
+    let scale = 10_i128
+        .checked_pow(shift.unsigned_abs())
+        .ok_or(EscrowError::InvalidPriceFeed)?;
+    if shift >= 0 {
+        Ok(price.checked_mul(scale).ok_or(EscrowError::InvalidPriceFeed)?)
+    } else {
+        Ok(price / scale)
+    }
+}
+
+/// Decimals from a Chainlink store feed account's header
+pub fn chainlink_feed_decimals(feed_data: &[u8]) -> Option<u8> {
+    feed_data.get(CHAINLINK_DECIMALS_OFFSET).copied()
+}
+
+/// Latest round id from a Chainlink store feed account's header
+pub fn chainlink_latest_round_id(feed_data: &[u8]) -> Option<u32> {
+    let bytes = feed_data.get(CHAINLINK_LATEST_ROUND_ID_OFFSET..CHAINLINK_LATEST_ROUND_ID_OFFSET + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// (answer, timestamp) of `round_id` from a Chainlink store feed account's live ring
+/// buffer. None once the round has rotated out of the live window (or never existed).
+pub fn read_chainlink_round(feed_data: &[u8], round_id: u32) -> Option<(i128, u32)> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(feed_data.get(offset..offset + 4)?.try_into().ok()?))
+    };
+    let latest_round_id = read_u32(CHAINLINK_LATEST_ROUND_ID_OFFSET)?;
+    let live_length = read_u32(CHAINLINK_LIVE_LENGTH_OFFSET)?;
+    let live_cursor = read_u32(CHAINLINK_LIVE_CURSOR_OFFSET)?;
+    
+    if round_id == 0 || round_id > latest_round_id || live_length == 0 || live_cursor >= live_length {
+        return None;
+    }
+    // The cursor points one past the latest transmission
+    let offset = latest_round_id - round_id + 1;
+    if offset > live_length {
+        return None;
+    }
+    let index = if offset <= live_cursor { live_cursor - offset } else { live_length - (offset - live_cursor) };
+    
+    // Transmission: slot u64, timestamp u32, padding u32, answer i128, padding
+    let start = CHAINLINK_FEED_HEADER_LEN + index as usize * CHAINLINK_TRANSMISSION_LEN;
+    let transmission = feed_data.get(start..start + CHAINLINK_TRANSMISSION_LEN)?;
+    let timestamp = u32::from_le_bytes(transmission[8..12].try_into().ok()?);
+    let answer = i128::from_le_bytes(transmission[16..32].try_into().ok()?);
+    Some((answer, timestamp))
+}
+
+/// Median of the usable `prices` (sorted in place) read from `feed_count` feeds, or
+/// None unless a strict majority of the feeds produced a price
+pub fn quorum_median(prices: &mut [u64], feed_count: usize) -> Option<u64> {
+    if prices.is_empty() || prices.len() * 2 <= feed_count {
+        return None;
+    }
+    
+    prices.sort_unstable();
+    let mid = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        Some(prices[mid])
+    } else {
+        // Even count: average of the two middle prices (u128 to avoid overflow)
+        Some(((prices[mid - 1] as u128 + prices[mid] as u128) / 2) as u64)
+    }
+}
+
+/// Median of the configured oracle feeds, rejecting when the feeds disagree by more
+/// than `max_feed_disagreement_bps`. Feed accounts are located among `accounts` by key.
+/// A feed failing the staleness/positivity checks is left out as long as a strict
+/// majority of the configured feeds still passes (2 of 3), so one faulty feed can't halt
+/// deposits. Returns the median price and the oldest timestamp among the feeds used.
+pub fn get_median_price<'a>(
+    accounts: &[AccountInfo<'a>],
+    oracle_program: &AccountInfo<'a>,
+    global_escrow: &GlobalEscrow,
+) -> Result<(u64, i64), ProgramError> {
+    if oracle_program.key != &global_escrow.oracle_program_id {
+        msg!("Invalid oracle program: {}", oracle_program.key);
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    let feed_count = global_escrow.oracle_feed_count as usize;
+    let mut prices = Vec::with_capacity(feed_count);
+    let mut oldest_timestamp = i64::MAX;
+    let mut feed_error = ProgramError::from(EscrowError::InvalidPriceFeed);
+    
+    for feed_key in &global_escrow.oracle_feeds[..feed_count] {
+        let feed_account = find_account_by_key(accounts, feed_key).ok_or_else(|| {
+            msg!("Missing price feed account: {}", feed_key);
+            EscrowError::InvalidPriceFeed
+        })?;
+        
+        match read_oracle_price(feed_account, oracle_program, global_escrow) {
+            Ok((price, timestamp)) => {
+                prices.push(price);
+                oldest_timestamp = oldest_timestamp.min(timestamp);
+            }
+            Err(err) => {
+                msg!("Skipping price feed {}: {:?}", feed_key, err);
+                feed_error = err;
+            }
+        }
+    }
+    
+    // QUORUM: the last feed error surfaces so degraded mode still sees StalePriceData
+    let Some(median) = quorum_median(&mut prices, feed_count) else {
+        msg!("Only {} of {} price feeds usable", prices.len(), feed_count);
+        return Err(feed_error);
+    };
+    
+    // Agreement check: spread between the extreme feeds relative to the median
+    let spread = prices[prices.len() - 1] - prices[0];
+    let spread_bps = checked_mul_div(spread, BPS_DENOMINATOR, median)?;
+    if spread_bps > global_escrow.max_feed_disagreement_bps as u64 {
+        msg!("Price feeds disagree: spread {} bps > {} bps", spread_bps, global_escrow.max_feed_disagreement_bps);
+        return Err(EscrowError::PriceFeedDisagreement.into());
+    }
+    global_escrow.check_sol_usd_price(median)?;
+    
+    Ok((median, oldest_timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_prices_normalize_to_eight_decimals() {
+        assert_eq!(normalize_price_decimals(12_345, 6).unwrap(), 1_234_500);
+        assert_eq!(normalize_price_decimals(1_234_567_890, 10).unwrap(), 12_345_678);
+        assert_eq!(normalize_price_decimals(42, CHAINLINK_USD_DECIMALS as i32).unwrap(), 42);
+        assert_eq!(normalize_price_decimals(i128::MAX, 0), Err(EscrowError::InvalidPriceFeed.into()));
+    }
+
+
+    #[test]
+    fn median_needs_a_strict_majority_of_feeds() {
+        assert_eq!(quorum_median(&mut [300, 100, 200], 3), Some(200));
+        // Two of three feeds still form a majority, averaged between them
+        assert_eq!(quorum_median(&mut [300, 100], 3), Some(200));
+        assert_eq!(quorum_median(&mut [u64::MAX, u64::MAX], 2), Some(u64::MAX));
+
+        assert_eq!(quorum_median(&mut [100, 200], 4), None);
+        assert_eq!(quorum_median(&mut [100], 3), None);
+        assert_eq!(quorum_median(&mut [], 0), None);
+    }
+}
@@ -0,0 +1,224 @@
+// Seeds of every program-derived address the program owns or signs for
+
+use solana_program::{
+    pubkey::Pubkey,
+};
+
+// PDA helper functions with proper seeds
+/// Global escrow PDA seeds (without the bump). Sale 0 keeps the original seeds,
+/// so escrows created before sale ids still derive to the same address.
+pub fn global_escrow_seeds<'a>(
+    initializer: &'a Pubkey,
+    token_mint: &'a Pubkey,
+    sale_id_bytes: &'a [u8; 8],
+) -> Vec<&'a [u8]> {
+    let mut seeds: Vec<&[u8]> = vec![b"global_escrow", initializer.as_ref(), token_mint.as_ref()];
+    if sale_id_bytes != &[0u8; 8] {
+        seeds.push(sale_id_bytes);
+    }
+    seeds
+}
+
+pub fn find_global_escrow_pda(
+    initializer: &Pubkey,
+    token_mint: &Pubkey,
+    sale_id: u64,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    let sale_id_bytes = sale_id.to_le_bytes();
+    Pubkey::find_program_address(
+        &global_escrow_seeds(initializer, token_mint, &sale_id_bytes),
+        program_id,
+    )
+}
+
+pub fn find_investor_pda(
+    investor: &Pubkey,
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"investor", investor.as_ref(), global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_token_vault_pda(
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"token_vault", global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_payment_vault_pda(
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"payment_vault", global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_audit_log_pda(
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"audit_log", global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_analytics_pda(
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"analytics", global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_deposit_proof_pda(
+    investor_account: &Pubkey,
+    deposit_index: u64,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"deposit_proof", investor_account.as_ref(), &deposit_index.to_le_bytes()],
+        program_id,
+    )
+}
+
+pub fn find_deferred_deposit_pda(
+    investor: &Pubkey,
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"deferred_deposit", investor.as_ref(), global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_sybil_identity_pda(
+    identity: &[u8; 32],
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"sybil_identity", identity.as_ref(), global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_commitment_pda(
+    investor: &Pubkey,
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"commitment", investor.as_ref(), global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_whitelist_pda(
+    investor: &Pubkey,
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"whitelist", investor.as_ref(), global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_deposit_delegate_pda(
+    investor: &Pubkey,
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"deposit_delegate", investor.as_ref(), global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_snapshot_pda(
+    global_escrow: &Pubkey,
+    snapshot_id: u64,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"snapshot", global_escrow.as_ref(), &snapshot_id.to_le_bytes()],
+        program_id,
+    )
+}
+
+pub fn find_recipient_vault_pda(
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"recipient_vault", global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_refund_vault_pda(
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"refund_vault", global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_pending_config_pda(
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"pending_config", global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_stake_position_pda(
+    staking_pool: &Pubkey,
+    beneficiary: &Pubkey,
+    staking_program: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"stake_position", staking_pool.as_ref(), beneficiary.as_ref()],
+        staking_program,
+    )
+}
+
+pub fn find_permit_vault_pda(
+    investor: &Pubkey,
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"permit_vault", investor.as_ref(), global_escrow.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_sol_vault_pda(
+    investor: &Pubkey,
+    global_escrow: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"sol_vault", investor.as_ref(), global_escrow.as_ref()],
+        program_id,
+    )
+}
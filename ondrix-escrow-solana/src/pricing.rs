@@ -0,0 +1,548 @@
+// Token pricing: flat, tiered, stablecoin and bonding-curve math
+
+use solana_program::{
+    msg,
+    program_error::ProgramError,
+};
+
+use crate::{
+    CurveType, EscrowError, GlobalEscrow, BPS_DENOMINATOR, CHAINLINK_USD_DECIMALS,
+    CURVE_PRICE_SCALE, MAX_CURVE_STEPS_PER_DEPOSIT, MAX_DEPOSIT_CHUNKS, SOL_LAMPORTS,
+    USD_CENTS_SCALE,
+};
+
+// Safe math helpers with overflow protection
+pub fn checked_mul_div(a: u64, b: u64, c: u64) -> Result<u64, ProgramError> {
+    let result = (a as u128)
+        .checked_mul(b as u128)
+        .ok_or(EscrowError::AmountOverflow)?
+        .checked_div(c as u128)
+        .ok_or(EscrowError::AmountOverflow)?;
+        
+    u64::try_from(result).map_err(|_| EscrowError::AmountOverflow.into())
+}
+
+// Calculate tokens based on SOL amount and USD price
+/// Calculate tokens for SOL amount using clear unit conversion
+/// Input: sol_amount_lamports (u64), sol_usd_price (u64 with 8 decimals from Chainlink)  
+/// Output: token amount (u64 in base units of a mint with `token_decimals`)
+/// Formula: tokens = (lamports * sol_price_usd * 10^token_decimals * 100) / (token_price_cents * sol_lamports * 10^chainlink_decimals)
+pub fn calculate_tokens_for_sol(
+    sol_amount_lamports: u64,
+    sol_usd_price: u64, // SOL/USD price normalized to 8 decimals (e.g., 21700000000 = $217.00)
+    token_price_cents: u64, // Token price in USD cents (e.g., 25 = $0.25)
+    token_decimals: u8,
+) -> Result<u64, ProgramError> {
+    // PRECISION: carry everything in u128 and divide exactly once at the end,
+    // so sub-cent value of small deposits isn't truncated by intermediate steps
+    
+    // Numerator: lamports * sol_price_usd * 10^token_decimals * cents_per_usd
+    let numerator = (sol_amount_lamports as u128)
+        .checked_mul(sol_usd_price as u128)
+        .and_then(|v| v.checked_mul(10_u128.pow(token_decimals as u32)))
+        .and_then(|v| v.checked_mul(USD_CENTS_SCALE as u128))
+        .ok_or(EscrowError::AmountOverflow)?;
+    
+    // Denominator: lamports_per_sol * 10^chainlink_decimals * token_price_cents
+    let denominator = (SOL_LAMPORTS as u128)
+        .checked_mul(10_u128.pow(CHAINLINK_USD_DECIMALS as u32))
+        .and_then(|v| v.checked_mul(token_price_cents as u128))
+        .ok_or(EscrowError::AmountOverflow)?;
+    
+    let tokens = numerator
+        .checked_div(denominator)
+        .ok_or(EscrowError::AmountOverflow)?;
+    
+    u64::try_from(tokens).map_err(|_| EscrowError::AmountOverflow.into())
+}
+
+/// Tokens bought with `payment_amount` base units of a USD stablecoin (1 unit = 1 USD)
+/// Formula: tokens = (payment_amount * 10^token_decimals * 100) / (10^payment_decimals * token_price_cents)
+pub fn calculate_tokens_for_stablecoin(
+    payment_amount: u64,
+    payment_decimals: u8,
+    token_price_cents: u64,
+    token_decimals: u8,
+) -> Result<u64, ProgramError> {
+    let numerator = (payment_amount as u128)
+        .checked_mul(10_u128.pow(token_decimals as u32))
+        .and_then(|v| v.checked_mul(USD_CENTS_SCALE as u128))
+        .ok_or(EscrowError::AmountOverflow)?;
+    
+    let denominator = 10_u128
+        .pow(payment_decimals as u32)
+        .checked_mul(token_price_cents as u128)
+        .ok_or(EscrowError::AmountOverflow)?;
+    
+    let tokens = numerator
+        .checked_div(denominator)
+        .ok_or(EscrowError::AmountOverflow)?;
+    
+    u64::try_from(tokens).map_err(|_| EscrowError::AmountOverflow.into())
+}
+
+/// USD value in cents of `lamports` at `sol_usd_price` (8 decimals)
+pub fn usd_value_cents(lamports: u64, sol_usd_price: u64) -> Result<u64, ProgramError> {
+    let cents = (lamports as u128)
+        .checked_mul(sol_usd_price as u128)
+        .and_then(|v| v.checked_mul(USD_CENTS_SCALE as u128))
+        .ok_or(EscrowError::AmountOverflow)?
+        / (SOL_LAMPORTS as u128 * 10_u128.pow(CHAINLINK_USD_DECIMALS as u32));
+    u64::try_from(cents).map_err(|_| EscrowError::AmountOverflow.into())
+}
+
+/// Inverse of `calculate_tokens_for_sol`: lamports needed to buy `tokens`.
+/// Rounds up, so `calculate_tokens_for_sol` of the result is never below
+/// `tokens` and the buyer is never charged less than the tokens are worth.
+pub fn sol_for_tokens(
+    tokens: u64,
+    sol_usd_price: u64,
+    token_price_cents: u64,
+    token_decimals: u8,
+) -> Result<u64, ProgramError> {
+    // Numerator: tokens * lamports_per_sol * 10^chainlink_decimals * token_price_cents
+    let numerator = (tokens as u128)
+        .checked_mul(SOL_LAMPORTS as u128)
+        .and_then(|v| v.checked_mul(10_u128.pow(CHAINLINK_USD_DECIMALS as u32)))
+        .and_then(|v| v.checked_mul(token_price_cents as u128))
+        .ok_or(EscrowError::AmountOverflow)?;
+    
+    // Denominator: sol_price_usd * 10^token_decimals * cents_per_usd
+    let denominator = (sol_usd_price as u128)
+        .checked_mul(10_u128.pow(token_decimals as u32))
+        .and_then(|v| v.checked_mul(USD_CENTS_SCALE as u128))
+        .filter(|d| *d > 0)
+        .ok_or(EscrowError::AmountOverflow)?;
+    
+    let lamports = numerator
+        .checked_add(denominator - 1)
+        .ok_or(EscrowError::AmountOverflow)?
+        / denominator;
+    
+    u64::try_from(lamports).map_err(|_| EscrowError::AmountOverflow.into())
+}
+
+/// Exponential moving average step: ema * (1 - alpha) + price * alpha.
+/// A zero `previous_ema` (cold start) is seeded with the spot price.
+pub fn update_ema(previous_ema: u64, spot_price: u64, alpha_bps: u16) -> Result<u64, ProgramError> {
+    if previous_ema == 0 {
+        return Ok(spot_price);
+    }
+    
+    let alpha = alpha_bps as u128;
+    let weighted = (previous_ema as u128)
+        .checked_mul(BPS_DENOMINATOR as u128 - alpha)
+        .and_then(|old| (spot_price as u128).checked_mul(alpha).and_then(|new| old.checked_add(new)))
+        .ok_or(EscrowError::AmountOverflow)?;
+    
+    u64::try_from(weighted / BPS_DENOMINATOR as u128).map_err(|_| EscrowError::AmountOverflow.into())
+}
+
+/// Price a deposit against the escrow's tier schedule.
+/// The deposit is split into `chunks` equal slices (the last one takes the remainder);
+/// each slice is priced at the tier active after the previous slices, so with
+/// `chunks == 1` the whole amount is priced at the current tier.
+pub fn calculate_tokens_for_sol_tiered(
+    global_escrow: &GlobalEscrow,
+    sol_amount_lamports: u64,
+    sol_usd_price: u64,
+    chunks: u8,
+) -> Result<u64, ProgramError> {
+    if chunks == 0 || chunks > MAX_DEPOSIT_CHUNKS {
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+    
+    let chunk_sol = sol_amount_lamports / chunks as u64;
+    let mut sol_left = sol_amount_lamports;
+    let mut running_tokens_sold = global_escrow.tokens_sold;
+    let mut total_tokens: u64 = 0;
+    
+    for i in 0..chunks {
+        let slice = if i == chunks - 1 { sol_left } else { chunk_sol };
+        sol_left -= slice;
+        
+        let price_cents = global_escrow.token_price_cents_at(running_tokens_sold);
+        let tokens = calculate_tokens_for_sol(slice, sol_usd_price, price_cents, global_escrow.token_decimals)?;
+        
+        running_tokens_sold = running_tokens_sold
+            .checked_add(tokens)
+            .ok_or(EscrowError::AmountOverflow)?;
+        total_tokens = total_tokens
+            .checked_add(tokens)
+            .ok_or(EscrowError::AmountOverflow)?;
+    }
+    
+    Ok(total_tokens)
+}
+
+/// USD value of `lamports` in CURVE_PRICE_SCALE units, rounded down
+pub fn curve_budget_for_sol(lamports: u64, sol_usd_price: u64) -> Result<u128, ProgramError> {
+    let numerator = (lamports as u128)
+        .checked_mul(sol_usd_price as u128)
+        .and_then(|v| v.checked_mul(USD_CENTS_SCALE as u128))
+        .and_then(|v| v.checked_mul(CURVE_PRICE_SCALE as u128))
+        .ok_or(EscrowError::AmountOverflow)?;
+    let denominator = (SOL_LAMPORTS as u128) * 10_u128.pow(CHAINLINK_USD_DECIMALS as u32);
+    Ok(numerator / denominator)
+}
+
+/// Lamports worth `cost` CURVE_PRICE_SCALE units of USD, rounded up
+pub fn sol_for_curve_cost(cost: u128, sol_usd_price: u64) -> Result<u64, ProgramError> {
+    let numerator = cost
+        .checked_mul(SOL_LAMPORTS as u128)
+        .and_then(|v| v.checked_mul(10_u128.pow(CHAINLINK_USD_DECIMALS as u32)))
+        .ok_or(EscrowError::AmountOverflow)?;
+    let denominator = (sol_usd_price as u128)
+        .checked_mul(USD_CENTS_SCALE as u128)
+        .and_then(|v| v.checked_mul(CURVE_PRICE_SCALE as u128))
+        .filter(|d| *d > 0)
+        .ok_or(EscrowError::AmountOverflow)?;
+    u64::try_from(numerator.div_ceil(denominator)).map_err(|_| EscrowError::AmountOverflow.into())
+}
+
+/// One exponential curve step: price * (1 + growth_bps / 10000), rounded up
+pub fn grow_curve_price(price: u128, growth_bps: u16) -> Result<u128, ProgramError> {
+    price
+        .checked_mul(BPS_DENOMINATOR as u128 + growth_bps as u128)
+        .map(|v| v.div_ceil(BPS_DENOMINATOR as u128))
+        .ok_or_else(|| EscrowError::AmountOverflow.into())
+}
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Largest r with r * r <= n (Newton's method)
+pub fn integer_sqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = x / 2 + 1;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+/// Base units a `budget` buys on a linear curve from `spot`, both in CURVE_PRICE_SCALE
+/// units per whole token (`unit` base units), `slope` added per whole token. Rounds down.
+pub fn linear_curve_tokens_for_budget(budget: u128, spot: u128, slope: u128, unit: u128) -> Result<u128, ProgramError> {
+    if slope == 0 {
+        return Ok(budget.checked_mul(unit).ok_or(EscrowError::AmountOverflow)? / spot);
+    }
+    // Cost of t base units from spot: (spot * t + slope * t^2 / (2 * unit)) / unit.
+    // Solving cost = budget: t = (sqrt(spot^2 + 2 * slope * budget) - spot) * unit / slope
+    let discriminant = spot
+        .checked_mul(spot)
+        .and_then(|v| v.checked_add(slope.checked_mul(budget)?.checked_mul(2)?))
+        .ok_or(EscrowError::AmountOverflow)?;
+    Ok((integer_sqrt(discriminant) - spot)
+        .checked_mul(unit)
+        .ok_or(EscrowError::AmountOverflow)?
+        / slope)
+}
+
+/// Tokens `sol_amount_lamports` buys on the bonding curve starting at the current
+/// `tokens_sold`, integrating the price over the range bought. Rounds down, so the
+/// buyer always pays at least the curve's cost for what they receive.
+pub fn tokens_for_sol_on_curve(
+    global_escrow: &GlobalEscrow,
+    sol_amount_lamports: u64,
+    sol_usd_price: u64,
+) -> Result<u64, ProgramError> {
+    let unit = 10_u128.pow(global_escrow.token_decimals as u32);
+    let budget = curve_budget_for_sol(sol_amount_lamports, sol_usd_price)?;
+    let spot = global_escrow.curve_price_at(global_escrow.tokens_sold)?;
+    if spot == 0 {
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    let tokens = match global_escrow.curve_type {
+        CurveType::Linear => linear_curve_tokens_for_budget(budget, spot, global_escrow.curve_slope as u128, unit)?,
+        CurveType::Exponential => {
+            // Walk the flat steps, buying each one out until the budget runs short
+            let step = global_escrow.curve_step as u128;
+            let mut sold = global_escrow.tokens_sold as u128;
+            let mut price = spot;
+            let mut budget_left = budget;
+            let mut bought: u128 = 0;
+            let mut steps_crossed = 0;
+            loop {
+                let room = step - sold % step;
+                let step_cost = price
+                    .checked_mul(room)
+                    .ok_or(EscrowError::AmountOverflow)?
+                    .div_ceil(unit);
+                if budget_left < step_cost {
+                    break bought + budget_left * unit / price;
+                }
+                if steps_crossed == MAX_CURVE_STEPS_PER_DEPOSIT {
+                    msg!("Deposit crosses more than {} curve steps", MAX_CURVE_STEPS_PER_DEPOSIT);
+                    return Err(EscrowError::InvalidInstruction.into());
+                }
+                budget_left -= step_cost;
+                bought += room;
+                sold += room;
+                price = grow_curve_price(price, global_escrow.curve_growth_bps)?;
+                steps_crossed += 1;
+            }
+        }
+    };
+    
+    u64::try_from(tokens).map_err(|_| EscrowError::AmountOverflow.into())
+}
+
+/// Inverse of `tokens_for_sol_on_curve`: lamports the curve charges for `tokens`
+/// starting at the current `tokens_sold`, rounded up
+pub fn sol_for_tokens_on_curve(
+    global_escrow: &GlobalEscrow,
+    tokens: u64,
+    sol_usd_price: u64,
+) -> Result<u64, ProgramError> {
+    let unit = 10_u128.pow(global_escrow.token_decimals as u32);
+    let spot = global_escrow.curve_price_at(global_escrow.tokens_sold)?;
+    let tokens = tokens as u128;
+    
+    let cost = match global_escrow.curve_type {
+        CurveType::Linear => {
+            // spot * t / unit + slope * t^2 / (2 * unit^2), each term rounded up
+            let flat = spot
+                .checked_mul(tokens)
+                .ok_or(EscrowError::AmountOverflow)?
+                .div_ceil(unit);
+            let rise = (global_escrow.curve_slope as u128)
+                .checked_mul(tokens)
+                .ok_or(EscrowError::AmountOverflow)?
+                .div_ceil(unit)
+                .checked_mul(tokens)
+                .ok_or(EscrowError::AmountOverflow)?
+                .div_ceil(2 * unit);
+            flat.checked_add(rise).ok_or(EscrowError::AmountOverflow)?
+        }
+        CurveType::Exponential => {
+            let step = global_escrow.curve_step as u128;
+            let mut sold = global_escrow.tokens_sold as u128;
+            let mut price = spot;
+            let mut tokens_left = tokens;
+            let mut cost: u128 = 0;
+            while tokens_left > 0 {
+                let room = (step - sold % step).min(tokens_left);
+                cost = price
+                    .checked_mul(room)
+                    .ok_or(EscrowError::AmountOverflow)?
+                    .div_ceil(unit)
+                    .checked_add(cost)
+                    .ok_or(EscrowError::AmountOverflow)?;
+                tokens_left -= room;
+                sold += room;
+                price = grow_curve_price(price, global_escrow.curve_growth_bps)?;
+            }
+            cost
+        }
+    };
+    
+    sol_for_curve_cost(cost, sol_usd_price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{instruction::ConfigUpdate, PriceTier};
+    use proptest::prelude::*;
+
+    const SOL_USD_100: u64 = 100 * 10_u64.pow(CHAINLINK_USD_DECIMALS as u32);
+
+    /// 25 cents for the first 200 tokens of 6 decimals, 50 cents after
+    fn tiered_escrow() -> GlobalEscrow {
+        let mut global_escrow = GlobalEscrow { token_decimals: 6, token_price_cents: 25, ..GlobalEscrow::default() };
+        global_escrow.price_tiers[0] = PriceTier { tokens_sold_threshold: 200_000_000, price_cents: 25 };
+        global_escrow.price_tiers[1] = PriceTier { tokens_sold_threshold: u64::MAX, price_cents: 50 };
+        global_escrow.price_tier_count = 2;
+        global_escrow
+    }
+
+    fn linear_curve_escrow(base_price_cents: u64, slope_cents: u64) -> GlobalEscrow {
+        let mut global_escrow = GlobalEscrow { token_decimals: 6, ..GlobalEscrow::default() };
+        ConfigUpdate::LinearCurveCents { base_price_cents, slope_cents }
+            .apply(&mut global_escrow)
+            .unwrap();
+        global_escrow
+    }
+
+    #[test]
+    fn linear_curve_cents_prices_on_the_curve() {
+        // A flat curve charges the same as the fixed price
+        let flat = linear_curve_escrow(25, 0);
+        assert_eq!(
+            tokens_for_sol_on_curve(&flat, SOL_LAMPORTS, SOL_USD_100).unwrap(),
+            calculate_tokens_for_sol(SOL_LAMPORTS, SOL_USD_100, 25, 6).unwrap(),
+        );
+
+        // $100 from $0.25 rising 1 cent per token: 0.25t + 0.005t^2 = 100, t ≈ 118.6
+        let mut sloped = linear_curve_escrow(25, 1);
+        let tokens = tokens_for_sol_on_curve(&sloped, SOL_LAMPORTS, SOL_USD_100).unwrap();
+        assert!((118_000_000..119_000_000).contains(&tokens), "{}", tokens);
+
+        // The same budget buys less once the price has moved up the curve
+        sloped.tokens_sold = tokens;
+        assert!(tokens_for_sol_on_curve(&sloped, SOL_LAMPORTS, SOL_USD_100).unwrap() < tokens);
+    }
+
+    #[test]
+    fn tiered_pricing_crosses_tiers_per_chunk() {
+        let global_escrow = tiered_escrow();
+
+        // One chunk is priced entirely at the first tier
+        assert_eq!(calculate_tokens_for_sol_tiered(&global_escrow, SOL_LAMPORTS, SOL_USD_100, 1).unwrap(), 400_000_000);
+        // The second half-SOL chunk is priced at the second tier
+        assert_eq!(calculate_tokens_for_sol_tiered(&global_escrow, SOL_LAMPORTS, SOL_USD_100, 2).unwrap(), 300_000_000);
+
+        assert_eq!(
+            calculate_tokens_for_sol_tiered(&global_escrow, SOL_LAMPORTS, SOL_USD_100, 0),
+            Err(EscrowError::InvalidInstruction.into())
+        );
+        assert_eq!(
+            calculate_tokens_for_sol_tiered(&global_escrow, SOL_LAMPORTS, SOL_USD_100, MAX_DEPOSIT_CHUNKS + 1),
+            Err(EscrowError::InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn chunked_deposit_equals_the_sum_of_its_slices() {
+        // 3 SOL crosses the tier boundary mid-deposit; the last slice takes the remainder
+        let sol_amount = 3 * SOL_LAMPORTS + 2;
+        let chunks = 4;
+        let chunked = calculate_tokens_for_sol_tiered(&tiered_escrow(), sol_amount, SOL_USD_100, chunks).unwrap();
+
+        let mut global_escrow = tiered_escrow();
+        let slice = sol_amount / chunks as u64;
+        let mut separate = 0;
+        for sol in [slice, slice, slice, sol_amount - 3 * slice] {
+            let tokens = calculate_tokens_for_sol_tiered(&global_escrow, sol, SOL_USD_100, 1).unwrap();
+            global_escrow.tokens_sold += tokens;
+            separate += tokens;
+        }
+        assert_eq!(chunked, separate);
+        assert!(chunked < calculate_tokens_for_sol_tiered(&tiered_escrow(), sol_amount, SOL_USD_100, 1).unwrap());
+    }
+
+    #[test]
+    fn tokens_for_sol_at_flat_price() {
+        // 1 SOL at $100 buys 400 tokens of 6 decimals at $0.25, and 100 at $1.00
+        assert_eq!(calculate_tokens_for_sol(SOL_LAMPORTS, SOL_USD_100, 25, 6).unwrap(), 400_000_000);
+        assert_eq!(calculate_tokens_for_sol(SOL_LAMPORTS, SOL_USD_100, 100, 6).unwrap(), 100_000_000);
+        // Sub-cent deposits are not truncated to zero along the way
+        assert_eq!(calculate_tokens_for_sol(1_000, SOL_USD_100, 25, 6).unwrap(), 400);
+        assert_eq!(calculate_tokens_for_sol(0, SOL_USD_100, 25, 6).unwrap(), 0);
+        assert_eq!(
+            calculate_tokens_for_sol(SOL_LAMPORTS, SOL_USD_100, 0, 6),
+            Err(EscrowError::AmountOverflow.into())
+        );
+    }
+
+    #[test]
+    fn tokens_for_sol_overflow_is_an_error() {
+        assert_eq!(
+            calculate_tokens_for_sol(u64::MAX, u64::MAX, 1, 18),
+            Err(EscrowError::AmountOverflow.into())
+        );
+        // In range for u128, but not for the u64 token amount
+        assert_eq!(
+            calculate_tokens_for_sol(u64::MAX, SOL_USD_100, 1, 6),
+            Err(EscrowError::AmountOverflow.into())
+        );
+    }
+
+    #[test]
+    fn tokens_for_stablecoin_at_flat_price() {
+        // 100 USDC (6 decimals) buys 400 tokens of 6 decimals at $0.25
+        assert_eq!(calculate_tokens_for_stablecoin(100_000_000, 6, 25, 6).unwrap(), 400_000_000);
+        assert_eq!(calculate_tokens_for_stablecoin(100_000_000, 6, 100, 6).unwrap(), 100_000_000);
+        assert_eq!(
+            calculate_tokens_for_stablecoin(100_000_000, 6, 0, 6),
+            Err(EscrowError::AmountOverflow.into())
+        );
+    }
+
+    #[test]
+    fn sol_for_tokens_never_undercharges() {
+        let lamports = sol_for_tokens(1_234_567, SOL_USD_100, 25, 6).unwrap();
+        assert!(calculate_tokens_for_sol(lamports, SOL_USD_100, 25, 6).unwrap() >= 1_234_567);
+        assert!(calculate_tokens_for_sol(lamports - 1, SOL_USD_100, 25, 6).unwrap() < 1_234_567);
+    }
+
+    // Token decimals up to 6 keep the exact u128 products below in range
+    proptest! {
+        #[test]
+        fn tokens_for_sol_is_the_exact_floor(
+            lamports in 0..=1_000_000 * SOL_LAMPORTS,
+            sol_usd_price in 1..=10_000 * 10_u64.pow(CHAINLINK_USD_DECIMALS as u32),
+            token_price_cents in 1..=10_000u64,
+            token_decimals in 0..=6u8,
+        ) {
+            let tokens = calculate_tokens_for_sol(lamports, sol_usd_price, token_price_cents, token_decimals).unwrap();
+
+            // Reference: tokens is the largest t with t * price_per_token <= value_paid
+            let value_paid = lamports as u128 * sol_usd_price as u128 * 10_u128.pow(token_decimals as u32) * 100;
+            let price_per_token = SOL_LAMPORTS as u128 * 10_u128.pow(CHAINLINK_USD_DECIMALS as u32) * token_price_cents as u128;
+            prop_assert!(tokens as u128 * price_per_token <= value_paid);
+            prop_assert!((tokens as u128 + 1) * price_per_token > value_paid);
+        }
+
+        #[test]
+        fn sol_for_tokens_round_trips(
+            tokens in 0..=1_000_000_000u64,
+            sol_usd_price in 10_u64.pow(CHAINLINK_USD_DECIMALS as u32)..=1_000 * 10_u64.pow(CHAINLINK_USD_DECIMALS as u32),
+            token_price_cents in 1..=10_000u64,
+            token_decimals in 0..=6u8,
+        ) {
+            // Only an expensive token at a cheap SOL price costs more than u64::MAX lamports
+            let lamports = sol_for_tokens(tokens, sol_usd_price, token_price_cents, token_decimals);
+            prop_assume!(lamports.is_ok());
+            let lamports = lamports.unwrap();
+
+            // The charge buys at least the tokens, and one lamport less buys fewer
+            prop_assert!(calculate_tokens_for_sol(lamports, sol_usd_price, token_price_cents, token_decimals).unwrap() >= tokens);
+            if lamports > 0 {
+                prop_assert!(calculate_tokens_for_sol(lamports - 1, sol_usd_price, token_price_cents, token_decimals).unwrap() < tokens);
+            }
+        }
+
+        #[test]
+        fn tokens_bought_never_cost_more_than_paid(
+            lamports in 0..=1_000_000 * SOL_LAMPORTS,
+            sol_usd_price in 1..=10_000 * 10_u64.pow(CHAINLINK_USD_DECIMALS as u32),
+            token_price_cents in 1..=10_000u64,
+            token_decimals in 0..=6u8,
+        ) {
+            let tokens = calculate_tokens_for_sol(lamports, sol_usd_price, token_price_cents, token_decimals).unwrap();
+            prop_assert!(sol_for_tokens(tokens, sol_usd_price, token_price_cents, token_decimals).unwrap() <= lamports);
+        }
+
+        #[test]
+        fn partial_fill_conserves_the_deposit(
+            lamports in SOL_LAMPORTS / 10..=1_000 * SOL_LAMPORTS,
+            sol_usd_price in 1..=10_000 * 10_u64.pow(CHAINLINK_USD_DECIMALS as u32),
+            token_price_cents in 1..=10_000u64,
+            token_decimals in 0..=6u8,
+            fill_bps in 1..10_000u64,
+        ) {
+            let requested = calculate_tokens_for_sol(lamports, sol_usd_price, token_price_cents, token_decimals).unwrap();
+            let fillable = requested * fill_bps / 10_000;
+            prop_assume!(fillable > 0);
+
+            // Same split as DepositSol: the delivered part is the exact cost of what's left
+            let delivered_sol = sol_for_tokens(fillable, sol_usd_price, token_price_cents, token_decimals).unwrap().min(lamports);
+            let refunded_sol = lamports - delivered_sol;
+            prop_assert_eq!(delivered_sol + refunded_sol, lamports);
+
+            // Never over-refunded: the SOL kept still buys every token delivered
+            prop_assert!(calculate_tokens_for_sol(delivered_sol, sol_usd_price, token_price_cents, token_decimals).unwrap() >= fillable);
+        }
+    }
+}
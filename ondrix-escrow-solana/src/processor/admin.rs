@@ -0,0 +1,1188 @@
+// Initializer-only configuration and controls, and the audit log they write to
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    hash::hashv,
+    keccak,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::{
+    find_account_by_key, find_analytics_pda, find_audit_log_pda, find_investor_pda,
+    find_pending_config_pda, find_snapshot_pda, find_token_vault_pda, find_whitelist_pda,
+    read_token_account, AuditAction, AuditEntry, AuditLogHeader, ConfigUpdate, DepositAnalytics,
+    EscrowError, GlobalEscrow, InvestorAccount, PendingAction, StateSnapshot, WhitelistEntry,
+    ANALYTICS_BUCKETS, AUDIT_LOG_GROWTH_ENTRIES, AUDIT_LOG_INITIAL_ENTRIES, AUDIT_LOG_MAX_ENTRIES,
+    CONFIG_TIMELOCK_DELAY, MAX_LOCK_DURATION, MIN_LOCK_DURATION,
+};
+
+pub fn process_initialize_audit_log(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let audit_log_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer can enable the audit log
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if global_escrow.audit_log_enabled {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let (expected_audit_log, audit_bump) = find_audit_log_pda(global_escrow_account.key, program_id);
+    if audit_log_account.key != &expected_audit_log {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Create audit log with initial capacity, it grows by realloc as entries are appended
+    let log_size = AuditLogHeader::account_size(AUDIT_LOG_INITIAL_ENTRIES);
+    let rent = Rent::get()?;
+    let create_log_ix = system_instruction::create_account(
+        initializer.key,
+        audit_log_account.key,
+        rent.minimum_balance(log_size),
+        log_size as u64,
+        program_id,
+    );
+
+    invoke_signed(
+        &create_log_ix,
+        &[
+            initializer.clone(),
+            audit_log_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"audit_log",
+            global_escrow_account.key.as_ref(),
+            &[audit_bump],
+        ]],
+    )?;
+
+    let header = AuditLogHeader {
+        is_initialized: true,
+        global_escrow_pubkey: *global_escrow_account.key,
+        total_entries: 0,
+        capacity: AUDIT_LOG_INITIAL_ENTRIES as u32,
+        bump_seed: audit_bump,
+    };
+    header.serialize(&mut &mut audit_log_account.data.borrow_mut()[..AuditLogHeader::LEN])?;
+
+    global_escrow.audit_log_enabled = true;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    // First entry records who enabled the log
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        initializer,
+        AuditAction::AuditLogEnabled,
+        &[global_escrow_account.key.as_ref()],
+    )?;
+
+    msg!("Audit log initialized: {}", audit_log_account.key);
+
+    Ok(())
+}
+
+/// Append an entry to the audit log if logging is enabled for this escrow.
+/// The audit log PDA (and the system program, when the log must grow) are located
+/// among `accounts` by key, so privileged handlers only need them appended.
+/// The actor pays for any realloc; once `AUDIT_LOG_MAX_ENTRIES` is reached the
+/// oldest entry is overwritten while `total_entries` keeps counting.
+pub fn record_audit_entry<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    global_escrow_account: &AccountInfo<'a>,
+    global_escrow: &GlobalEscrow,
+    actor: &AccountInfo<'a>,
+    action: AuditAction,
+    params: &[&[u8]],
+) -> ProgramResult {
+    if !global_escrow.audit_log_enabled {
+        return Ok(());
+    }
+
+    let (expected_audit_log, _) = find_audit_log_pda(global_escrow_account.key, program_id);
+    let audit_log_account = find_account_by_key(accounts, &expected_audit_log)
+        .ok_or(EscrowError::InvalidAuditLog)?;
+
+    if audit_log_account.owner != program_id || !audit_log_account.is_writable {
+        return Err(EscrowError::InvalidAuditLog.into());
+    }
+
+    let mut header = AuditLogHeader::deserialize(&mut &audit_log_account.data.borrow()[..AuditLogHeader::LEN])?;
+    if !header.is_initialized || header.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidAuditLog.into());
+    }
+
+    // Grow the log when full and still below the cap
+    let capacity = header.capacity as usize;
+    if header.total_entries as usize >= capacity && capacity < AUDIT_LOG_MAX_ENTRIES {
+        let new_capacity = (capacity + AUDIT_LOG_GROWTH_ENTRIES).min(AUDIT_LOG_MAX_ENTRIES);
+        let new_size = AuditLogHeader::account_size(new_capacity);
+
+        let rent = Rent::get()?;
+        let lamports_needed = rent
+            .minimum_balance(new_size)
+            .saturating_sub(audit_log_account.lamports());
+
+        if lamports_needed > 0 {
+            let system_program = find_account_by_key(accounts, &solana_program::system_program::id())
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+            if !actor.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+
+            invoke(
+                &system_instruction::transfer(actor.key, audit_log_account.key, lamports_needed),
+                &[
+                    actor.clone(),
+                    audit_log_account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
+
+        audit_log_account.realloc(new_size, true)?;
+        header.capacity = new_capacity as u32;
+    }
+
+    let slot = (header.total_entries % header.capacity as u64) as usize;
+    let entry = AuditEntry {
+        action,
+        actor: *actor.key,
+        timestamp: Clock::get()?.unix_timestamp,
+        param_hash: hashv(params).to_bytes(),
+    };
+
+    let offset = AuditLogHeader::LEN + slot * AuditEntry::LEN;
+    entry.serialize(&mut &mut audit_log_account.data.borrow_mut()[offset..offset + AuditEntry::LEN])?;
+
+    header.total_entries = header
+        .total_entries
+        .checked_add(1)
+        .ok_or(EscrowError::AmountOverflow)?;
+    header.serialize(&mut &mut audit_log_account.data.borrow_mut()[..AuditLogHeader::LEN])?;
+
+    Ok(())
+}
+
+pub fn process_update_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    update: ConfigUpdate,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer can change configuration
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // CONFIG IMMUTABILITY: terms are frozen once the first investor has deposited
+    if global_escrow.total_sol_deposited > 0 {
+        msg!("Config is immutable after the first deposit");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    update.apply(&mut global_escrow)?;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        initializer,
+        AuditAction::UpdateConfig,
+        &[&borsh::to_vec(&update)?],
+    )?;
+
+    msg!("Config updated: {:?}", update);
+
+    Ok(())
+}
+
+pub fn process_advance_round(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    round_tokens_cap: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer can advance rounds
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    global_escrow.current_round = global_escrow
+        .current_round
+        .checked_add(1)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.round_tokens_cap = round_tokens_cap;
+    global_escrow.round_tokens_sold = 0;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        initializer,
+        AuditAction::AdvanceRound,
+        &[&global_escrow.current_round.to_le_bytes(), &round_tokens_cap.to_le_bytes()],
+    )?;
+
+    msg!(
+        "Advanced to round {}: round cap {}, lifetime sold {}",
+        global_escrow.current_round,
+        round_tokens_cap,
+        global_escrow.tokens_sold
+    );
+
+    Ok(())
+}
+
+pub fn process_snapshot_state(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    snapshot_id: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let creator = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let snapshot_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter).ok();
+
+    if !creator.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Optional investor: must be a live investor account of this escrow
+    let (investor_account_pubkey, investor_data) = match investor_account {
+        Some(investor_account) => {
+            if investor_account.owner != program_id {
+                return Err(EscrowError::InvalidPDA.into());
+            }
+            let investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+            if !investor_data.is_initialized || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+                return Err(EscrowError::InvalidPDA.into());
+            }
+            (*investor_account.key, investor_account.data.borrow().to_vec())
+        }
+        None => (Pubkey::default(), Vec::new()),
+    };
+
+    let (expected_snapshot, snapshot_bump) = find_snapshot_pda(global_escrow_account.key, snapshot_id, program_id);
+    if snapshot_account.key != &expected_snapshot {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Write-once: the archive must not exist yet
+    if snapshot_account.data_len() > 0 || snapshot_account.owner == program_id {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let global_escrow_data = global_escrow_account.data.borrow().to_vec();
+    let snapshot_size = StateSnapshot::account_size(global_escrow_data.len(), investor_data.len());
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            creator.key,
+            snapshot_account.key,
+            rent.minimum_balance(snapshot_size),
+            snapshot_size as u64,
+            program_id,
+        ),
+        &[
+            creator.clone(),
+            snapshot_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"snapshot",
+            global_escrow_account.key.as_ref(),
+            &snapshot_id.to_le_bytes(),
+            &[snapshot_bump],
+        ]],
+    )?;
+
+    let clock = Clock::get()?;
+    let mut snapshot = StateSnapshot {
+        is_initialized: true,
+        global_escrow_pubkey: *global_escrow_account.key,
+        snapshot_id,
+        creator: *creator.key,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+        global_escrow_data,
+        investor_account_pubkey,
+        investor_data,
+        snapshot_hash: [0; 32],
+        bump_seed: snapshot_bump,
+    };
+    snapshot.snapshot_hash = snapshot.compute_hash();
+    snapshot.serialize(&mut &mut snapshot_account.data.borrow_mut()[..])?;
+
+    msg!("Snapshot #{} written at slot {}: {}", snapshot_id, clock.slot, snapshot_account.key);
+
+    Ok(())
+}
+
+pub fn process_queue_config_update(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    update: ConfigUpdate,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let pending_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer can change configuration
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_pending, pending_bump) = find_pending_config_pda(global_escrow_account.key, program_id);
+    if pending_account.key != &expected_pending {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // One change at a time: the previous one must be executed first
+    if pending_account.data_len() > 0 || pending_account.owner == program_id {
+        msg!("A config change is already queued");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    // CONFIG IMMUTABILITY: once investors are in, only the allow-listed updates may follow
+    if global_escrow.total_sol_deposited > 0 && !update.allowed_after_deposits() {
+        msg!("{:?} can't change after the first deposit", update);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Validate now against a scratch copy so an invalid change can't sit in the queue
+    update.apply(&mut global_escrow)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let pending = PendingAction {
+        is_initialized: true,
+        global_escrow_pubkey: *global_escrow_account.key,
+        update,
+        queued_at: now,
+        activation_timestamp: now + CONFIG_TIMELOCK_DELAY,
+        bump_seed: pending_bump,
+    };
+    let pending_data = borsh::to_vec(&pending)?;
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            initializer.key,
+            pending_account.key,
+            rent.minimum_balance(pending_data.len()),
+            pending_data.len() as u64,
+            program_id,
+        ),
+        &[
+            initializer.clone(),
+            pending_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"pending_config",
+            global_escrow_account.key.as_ref(),
+            &[pending_bump],
+        ]],
+    )?;
+    pending_account.data.borrow_mut().copy_from_slice(&pending_data);
+
+    // Audit against the stored state, not the scratch copy
+    let stored_global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &stored_global_escrow,
+        initializer,
+        AuditAction::QueueConfigUpdate,
+        &[&borsh::to_vec(&pending.update)?, &pending.activation_timestamp.to_le_bytes()],
+    )?;
+
+    msg!("Config change queued: {:?}, active at {}", pending.update, pending.activation_timestamp);
+
+    Ok(())
+}
+
+pub fn process_execute_config_update(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let pending_account = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id || pending_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer can change configuration
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_pending, _) = find_pending_config_pda(global_escrow_account.key, program_id);
+    if pending_account.key != &expected_pending {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let pending = PendingAction::try_from_slice(&pending_account.data.borrow())?;
+    if !pending.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    if now < pending.activation_timestamp {
+        msg!("Config change not active yet: {} < {}", now, pending.activation_timestamp);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Deposits may have started while the change was queued. It is dropped rather than
+    // left in the queue, which would block every later change.
+    let applies = global_escrow.total_sol_deposited == 0 || pending.update.allowed_after_deposits();
+    if applies {
+        pending.update.apply(&mut global_escrow)?;
+        global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+    }
+
+    // Clear the queue: zero data and return rent to the initializer
+    let rent_lamports = pending_account.lamports();
+    pending_account.data.borrow_mut().fill(0);
+    **pending_account.try_borrow_mut_lamports()? = 0;
+    **initializer.try_borrow_mut_lamports()? += rent_lamports;
+
+    if !applies {
+        msg!("Config change dropped, {:?} can't change after the first deposit", pending.update);
+        return Ok(());
+    }
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        initializer,
+        AuditAction::UpdateConfig,
+        &[&borsh::to_vec(&pending.update)?],
+    )?;
+
+    msg!("Config updated: {:?}", pending.update);
+
+    Ok(())
+}
+
+pub fn process_set_paused(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    paused: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer can pause or resume
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    global_escrow.is_paused = paused;
+    if !paused {
+        // Start a fresh velocity window so a resumed sale isn't judged on the paused period
+        global_escrow.velocity_window_start = 0;
+        global_escrow.velocity_window_raised = 0;
+    }
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        initializer,
+        AuditAction::SetPaused,
+        &[&[paused as u8]],
+    )?;
+
+    msg!("Deposits {}", if paused { "paused" } else { "resumed" });
+
+    Ok(())
+}
+
+pub fn process_initialize_analytics(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let analytics_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer can enable analytics
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if global_escrow.analytics_enabled {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let (expected_analytics, analytics_bump) = find_analytics_pda(global_escrow_account.key, program_id);
+    if analytics_account.key != &expected_analytics {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            initializer.key,
+            analytics_account.key,
+            rent.minimum_balance(DepositAnalytics::LEN),
+            DepositAnalytics::LEN as u64,
+            program_id,
+        ),
+        &[
+            initializer.clone(),
+            analytics_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"analytics",
+            global_escrow_account.key.as_ref(),
+            &[analytics_bump],
+        ]],
+    )?;
+
+    let analytics = DepositAnalytics {
+        is_initialized: true,
+        global_escrow_pubkey: *global_escrow_account.key,
+        buckets: [0; ANALYTICS_BUCKETS],
+        deposit_count: 0,
+        total_lamports: 0,
+        min_deposit: 0,
+        max_deposit: 0,
+        bump_seed: analytics_bump,
+    };
+    analytics.serialize(&mut &mut analytics_account.data.borrow_mut()[..])?;
+
+    global_escrow.analytics_enabled = true;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    msg!("Deposit analytics initialized: {}", analytics_account.key);
+
+    Ok(())
+}
+
+pub fn process_investor_override(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    custom_lock_duration: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer can override an investor's lock
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // 0 clears the override; anything else must be a valid lock duration
+    if custom_lock_duration != 0
+        && !(MIN_LOCK_DURATION..=MAX_LOCK_DURATION).contains(&custom_lock_duration)
+    {
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+    if !investor_data.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(
+        &investor_data.investor_pubkey,
+        global_escrow_account.key,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_pda {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Nothing left to unlock on a settled position
+    if investor_data.is_terminal() {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    investor_data.custom_lock_duration = custom_lock_duration;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        initializer,
+        AuditAction::InvestorOverride,
+        &[investor_data.investor_pubkey.as_ref(), &custom_lock_duration.to_le_bytes()],
+    )?;
+
+    msg!(
+        "Lock override for {}: {}",
+        investor_data.investor_pubkey,
+        if custom_lock_duration == 0 { "cleared".to_string() } else { format!("{}s", custom_lock_duration) }
+    );
+
+    Ok(())
+}
+
+/// Assert the token vault is still the escrow's own: the vault PDA, a token-program
+/// account of the sale mint whose owner is the global escrow PDA and that nobody
+/// else can move or close. Any mismatch means the authority was tampered with.
+pub fn verify_token_vault(
+    program_id: &Pubkey,
+    global_escrow_account: &AccountInfo,
+    token_vault_account: &AccountInfo,
+    global_escrow: &GlobalEscrow,
+) -> ProgramResult {
+    let (expected_token_vault, _) = find_token_vault_pda(global_escrow_account.key, program_id);
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if *token_vault_account.owner != global_escrow.token_program_id() {
+        msg!("Token vault owned by {}, expected the token program", token_vault_account.owner);
+        return Err(EscrowError::VaultAuthorityMismatch.into());
+    }
+
+    let vault_data = read_token_account(token_vault_account)?;
+
+    if vault_data.owner != *global_escrow_account.key {
+        msg!(
+            "Token vault authority is {}, expected global escrow {}",
+            vault_data.owner,
+            global_escrow_account.key
+        );
+        return Err(EscrowError::VaultAuthorityMismatch.into());
+    }
+
+    if vault_data.mint != global_escrow.token_mint_pubkey {
+        msg!("Token vault mint is {}, expected {}", vault_data.mint, global_escrow.token_mint_pubkey);
+        return Err(EscrowError::VaultAuthorityMismatch.into());
+    }
+
+    if vault_data.delegate.is_some() {
+        msg!("Token vault has a delegate set");
+        return Err(EscrowError::VaultAuthorityMismatch.into());
+    }
+
+    if vault_data.close_authority.is_some() {
+        msg!("Token vault has a close authority set");
+        return Err(EscrowError::VaultAuthorityMismatch.into());
+    }
+
+    Ok(())
+}
+
+pub fn process_self_audit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    verify_token_vault(program_id, global_escrow_account, token_vault_account, &global_escrow)?;
+
+    msg!("Self-audit passed: token vault {} controlled by {}", token_vault_account.key, global_escrow_account.key);
+
+    Ok(())
+}
+
+/// Walk a Merkle proof from `leaf` up to `root`. Each pair is hashed with keccak in
+/// sorted order, so proofs carry no left/right flags (same scheme as OpenZeppelin's MerkleProof).
+pub fn verify_merkle_proof(leaf: [u8; 32], proof: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    computed == *root
+}
+
+/// Reject unless the investor's whitelist entry PDA is among `accounts` and live
+pub fn verify_whitelisted(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    investor: &Pubkey,
+    global_escrow_key: &Pubkey,
+) -> ProgramResult {
+    let (expected_entry, _) = find_whitelist_pda(investor, global_escrow_key, program_id);
+    let whitelisted = find_account_by_key(accounts, &expected_entry)
+        .filter(|entry| entry.owner == program_id && entry.data_len() == WhitelistEntry::LEN)
+        .map(|entry| WhitelistEntry::try_from_slice(&entry.data.borrow()))
+        .transpose()?
+        .is_some_and(|entry| entry.is_initialized);
+
+    if !whitelisted {
+        msg!("Investor {} is not whitelisted", investor);
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    Ok(())
+}
+
+pub fn process_add_to_whitelist(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    investor: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let whitelist_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer manages the whitelist
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_entry, entry_bump) = find_whitelist_pda(&investor, global_escrow_account.key, program_id);
+    if whitelist_account.key != &expected_entry {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if whitelist_account.owner == program_id {
+        msg!("Investor {} is already whitelisted", investor);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            initializer.key,
+            whitelist_account.key,
+            rent.minimum_balance(WhitelistEntry::LEN),
+            WhitelistEntry::LEN as u64,
+            program_id,
+        ),
+        &[
+            initializer.clone(),
+            whitelist_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"whitelist",
+            investor.as_ref(),
+            global_escrow_account.key.as_ref(),
+            &[entry_bump],
+        ]],
+    )?;
+
+    let entry = WhitelistEntry {
+        is_initialized: true,
+        investor_pubkey: investor,
+        global_escrow_pubkey: *global_escrow_account.key,
+        bump_seed: entry_bump,
+    };
+    entry.serialize(&mut &mut whitelist_account.data.borrow_mut()[..])?;
+
+    msg!("Investor {} whitelisted", investor);
+
+    Ok(())
+}
+
+pub fn process_remove_from_whitelist(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    investor: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let whitelist_account = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer manages the whitelist
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_entry, _) = find_whitelist_pda(&investor, global_escrow_account.key, program_id);
+    if whitelist_account.key != &expected_entry || whitelist_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Zero data and drain lamports so the runtime reclaims the account
+    let rent_lamports = whitelist_account.lamports();
+    whitelist_account.data.borrow_mut().fill(0);
+    **whitelist_account.try_borrow_mut_lamports()? = 0;
+    **initializer.try_borrow_mut_lamports()? += rent_lamports;
+
+    msg!("Investor {} removed from whitelist, {} lamports of rent returned", investor, rent_lamports);
+
+    Ok(())
+}
+
+pub fn process_set_withdrawals_frozen(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    frozen: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer can freeze or unfreeze withdrawals
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    global_escrow.withdrawals_frozen = frozen;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        initializer,
+        AuditAction::SetWithdrawalsFrozen,
+        &[&[frozen as u8]],
+    )?;
+
+    msg!("Withdrawals {}", if frozen { "frozen" } else { "unfrozen" });
+
+    Ok(())
+}
+
+pub fn process_transfer_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_authority: Pubkey,
+    new_recipient: Option<Pubkey>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let authority = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only the current authority can hand over admin rights
+    if !global_escrow.is_authority(authority.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // A zeroed authority would silently fall back to the initializer
+    if new_authority == Pubkey::default() || new_recipient == Some(Pubkey::default()) {
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    let old_authority = global_escrow.admin_authority();
+    global_escrow.authority = new_authority;
+    if let Some(recipient) = new_recipient {
+        global_escrow.recipient_wallet = recipient;
+    }
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        authority,
+        AuditAction::TransferAuthority,
+        &[new_authority.as_ref(), global_escrow.recipient_wallet.as_ref()],
+    )?;
+
+    msg!("Authority transferred: {} -> {}", old_authority, new_authority);
+    if let Some(recipient) = new_recipient {
+        msg!("Recipient wallet moved to {}", recipient);
+    }
+
+    Ok(())
+}
+
+pub fn process_set_beneficiary(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    beneficiary: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(investor.key, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    // AUTHORIZATION: Only the investor picks their beneficiary (never the beneficiary itself)
+    if investor_data.investor_pubkey != *investor.key || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if beneficiary == *investor.key {
+        msg!("Beneficiary must differ from the investor");
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    if global_escrow.inactivity_deadline().is_none() && beneficiary != Pubkey::default() {
+        msg!("Warning: inactivity refunds are disabled, the beneficiary can't claim until inactivity_refund_delay is set");
+    }
+
+    investor_data.beneficiary = beneficiary;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    if beneficiary == Pubkey::default() {
+        msg!("Beneficiary cleared for investor {}", investor.key);
+    } else {
+        msg!("Beneficiary for investor {} set to {}", investor.key, beneficiary);
+    }
+
+    Ok(())
+}
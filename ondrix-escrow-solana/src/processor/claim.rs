@@ -0,0 +1,673 @@
+// Token claims: vesting, claim tickets and the lottery
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    hash::hashv,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::{self, Sysvar},
+};
+use spl_token::instruction as spl_instruction;
+use borsh::BorshSerialize;
+
+use crate::{
+    find_investor_pda, find_sol_vault_pda, find_token_vault_pda, for_token_program,
+    is_token_program, read_token_account, record_audit_entry, transfer_escrow_tokens,
+    verify_whitelisted, AuditAction, EscrowError, GlobalEscrow, InvestorAccount, InvestorStatus,
+    INVESTOR_ACCOUNT_DISCRIMINATOR,
+};
+
+pub fn process_enter_lottery(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || !global_escrow.lottery_enabled {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    if global_escrow.is_paused {
+        msg!("Deposits are paused");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    global_escrow.check_sale_open(current_timestamp)?;
+
+    // SELF-DEALING: same rule as priced deposits
+    if global_escrow.forbid_self_dealing
+        && (investor.key == &global_escrow.initializer_pubkey
+            || global_escrow.is_authority(investor.key)
+            || investor.key == &global_escrow.recipient_wallet)
+    {
+        msg!("Self-dealing entry rejected for {}", investor.key);
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if global_escrow.whitelist_required {
+        verify_whitelisted(program_id, accounts, investor.key, global_escrow_account.key)?;
+    }
+
+    let (expected_investor_pda, investor_bump) = find_investor_pda(investor.key, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_sol_vault, sol_vault_bump) = find_sol_vault_pda(investor.key, global_escrow_account.key, program_id);
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let rent = Rent::get()?;
+
+    // Create or load investor account
+    let mut investor_data = if investor_account.owner != program_id || investor_account.data_len() != InvestorAccount::LEN {
+        invoke_signed(
+            &system_instruction::create_account(
+                investor.key,
+                investor_account.key,
+                rent.minimum_balance(InvestorAccount::LEN),
+                InvestorAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                investor.clone(),
+                investor_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"investor",
+                investor.key.as_ref(),
+                global_escrow_account.key.as_ref(),
+                &[investor_bump],
+            ]],
+        )?;
+
+        global_escrow.open_investor_accounts = global_escrow
+            .open_investor_accounts
+            .checked_add(1)
+            .ok_or(EscrowError::AmountOverflow)?;
+
+        // Nothing deposited until the entry wins: sol_deposited is set at claim time
+        InvestorAccount {
+            discriminator: INVESTOR_ACCOUNT_DISCRIMINATOR,
+            is_initialized: true,
+            investor_pubkey: *investor.key,
+            global_escrow_pubkey: *global_escrow_account.key,
+            sol_deposited: 0,
+            tokens_received: 0,
+            deposit_timestamp: current_timestamp,
+            sol_usd_price: 0,
+            status: InvestorStatus::Uninitialized,
+            bump_seed: investor_bump,
+            deposit_count: 0,
+            last_deposit_timestamp: 0,
+            held_immediate_sol: 0,
+            refund_registered: false,
+            refund_owed: 0,
+            refund_queue_position: 0,
+            custom_lock_duration: 0,
+            lottery_entry: 0,
+            lottery_claimed: false,
+            tokens_vesting: 0,
+            tokens_claimed: 0,
+            avg_sol_usd_price: 0,
+            price_round_id: 0,
+            beneficiary: Pubkey::default(),
+            payment_mint: Pubkey::default(),
+            payment_deposited: 0,
+            vesting_start: 0,
+        }
+    } else {
+        InvestorAccount::load(&investor_account.data.borrow())?
+    };
+
+    if investor_data.lottery_entry != 0 {
+        msg!("Investor {} already holds entry {}", investor.key, investor_data.lottery_entry - 1);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Create SOL vault if it doesn't exist
+    if sol_vault_account.owner != program_id {
+        invoke_signed(
+            &system_instruction::create_account(
+                investor.key,
+                sol_vault_account.key,
+                rent.minimum_balance(0),
+                0, // No data, just stores SOL
+                program_id,
+            ),
+            &[
+                investor.clone(),
+                sol_vault_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"sol_vault",
+                investor.key.as_ref(),
+                global_escrow_account.key.as_ref(),
+                &[sol_vault_bump],
+            ]],
+        )?;
+    }
+
+    // The whole entry stays in the SOL vault until the draw settles it
+    invoke(
+        &system_instruction::transfer(investor.key, sol_vault_account.key, global_escrow.lottery_entry_lamports),
+        &[
+            investor.clone(),
+            sol_vault_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+
+    let entry_index = global_escrow.lottery_entry_count;
+    global_escrow.lottery_entry_count = entry_index
+        .checked_add(1)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    investor_data.lottery_entry = entry_index + 1;
+    investor_data.last_deposit_timestamp = current_timestamp;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Lottery entry {} recorded for {}: {} lamports",
+        entry_index,
+        investor.key,
+        global_escrow.lottery_entry_lamports
+    );
+
+    Ok(())
+}
+
+pub fn process_resolve_lottery(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    secret: [u8; 32],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let slot_hashes = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if slot_hashes.key != &sysvar::slot_hashes::id() {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || !global_escrow.lottery_enabled || global_escrow.lottery_resolved {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only initializer holds the committed secret
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp < global_escrow.sale_end_timestamp {
+        msg!("Sale has not ended yet. Current: {}, Sale ends: {}", current_timestamp, global_escrow.sale_end_timestamp);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Past the window every entry is refundable instead
+    if global_escrow.lottery_reveal_expired(current_timestamp) {
+        msg!("Lottery reveal window has passed");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    if hashv(&[&secret]).to_bytes() != global_escrow.lottery_commitment {
+        return Err(EscrowError::InvalidLotteryReveal.into());
+    }
+
+    // SlotHashes data: u64 length, then (slot: u64, hash: [u8; 32]) newest first
+    let slot_hash_data = slot_hashes.data.borrow();
+    let recent_slot_hash = slot_hash_data.get(16..48).ok_or(ProgramError::InvalidAccountData)?;
+
+    // The secret was fixed before any entry, the slot hash only once entries closed,
+    // so neither the initializer nor the entrants pick the seed alone
+    let seed = hashv(&[
+        &secret,
+        recent_slot_hash,
+        &global_escrow.lottery_entry_count.to_le_bytes(),
+    ])
+    .to_bytes();
+
+    let winner_count = (global_escrow.fillable_tokens() / global_escrow.lottery_entry_tokens)
+        .min(global_escrow.lottery_entry_count);
+    let tokens_awarded = winner_count
+        .checked_mul(global_escrow.lottery_entry_tokens)
+        .ok_or(EscrowError::AmountOverflow)?;
+    let sol_raised = winner_count
+        .checked_mul(global_escrow.lottery_entry_lamports)
+        .ok_or(EscrowError::AmountOverflow)?;
+
+    // Winners' tokens count as sold from here on, so CloseSale leaves them in the vault
+    global_escrow.check_sale_caps(tokens_awarded)?;
+    global_escrow.tokens_sold = global_escrow
+        .tokens_sold
+        .checked_add(tokens_awarded)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.round_tokens_sold = global_escrow
+        .round_tokens_sold
+        .checked_add(tokens_awarded)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_sol_deposited = global_escrow
+        .total_sol_deposited
+        .checked_add(sol_raised)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.lottery_tokens_outstanding = tokens_awarded;
+    global_escrow.lottery_seed = seed;
+    global_escrow.lottery_winner_count = winner_count;
+    global_escrow.lottery_resolved = true;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        initializer,
+        AuditAction::ResolveLottery,
+        &[&seed, &winner_count.to_le_bytes()],
+    )?;
+
+    msg!(
+        "Lottery resolved: {} of {} entries win, seed {:?}",
+        winner_count,
+        global_escrow.lottery_entry_count,
+        seed
+    );
+
+    Ok(())
+}
+
+pub fn process_claim_lottery(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let investor_token_account = next_account_info(account_info_iter)?;
+    let recipient_wallet = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || !global_escrow.lottery_enabled {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    global_escrow.check_withdrawals_open()?;
+
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    // AUTHORIZATION: Only the investor can settle their own entry
+    if investor_data.investor_pubkey != *investor.key {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(investor.key, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_sol_vault, _) = find_sol_vault_pda(investor.key, global_escrow_account.key, program_id);
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if investor_data.lottery_entry == 0 || investor_data.lottery_claimed {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let reveal_expired = global_escrow.lottery_reveal_expired(current_timestamp);
+    if !global_escrow.lottery_resolved && !reveal_expired {
+        msg!("Lottery has not been drawn yet");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let entry_index = investor_data.lottery_entry - 1;
+    let entry_lamports = global_escrow.lottery_entry_lamports;
+
+    // SAFETY: Ensure SOL vault remains rent-exempt after the entry leaves it
+    let vault_balance = sol_vault_account.lamports();
+    let min_rent_balance = Rent::get()?.minimum_balance(0);
+    if vault_balance < entry_lamports || vault_balance - entry_lamports < min_rent_balance {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    if global_escrow.lottery_entry_wins(entry_index) {
+        if *token_program.key != global_escrow.token_program_id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let (expected_token_vault, _) = find_token_vault_pda(global_escrow_account.key, program_id);
+        if token_vault_account.key != &expected_token_vault {
+            return Err(EscrowError::InvalidPDA.into());
+        }
+
+        if recipient_wallet.key != &global_escrow.recipient_wallet {
+            return Err(EscrowError::Unauthorized.into());
+        }
+
+        let token_account_data = read_token_account(investor_token_account)?;
+        if token_account_data.owner != *investor.key || token_account_data.mint != global_escrow.token_mint_pubkey {
+            return Err(EscrowError::InvalidTokenAccount.into());
+        }
+
+        let tokens = global_escrow.lottery_entry_tokens;
+        transfer_escrow_tokens(
+            &global_escrow,
+            accounts,
+            token_program,
+            token_vault_account,
+            investor_token_account,
+            global_escrow_account,
+            tokens,
+            &[&global_escrow.signer_seeds(&global_escrow.sale_id.to_le_bytes(), &[global_escrow.bump_seed])],
+        )?;
+
+        // Same split as a priced deposit: half to the recipient, half stays locked
+        let sol_immediate = entry_lamports / 2;
+        **sol_vault_account.try_borrow_mut_lamports()? -= sol_immediate;
+        **recipient_wallet.try_borrow_mut_lamports()? += sol_immediate;
+
+        investor_data.sol_deposited = entry_lamports;
+        investor_data.tokens_received = tokens;
+        investor_data.deposit_timestamp = current_timestamp;
+        investor_data.status = InvestorStatus::Deposited;
+        investor_data.deposit_count = investor_data
+            .deposit_count
+            .checked_add(1)
+            .ok_or(EscrowError::AmountOverflow)?;
+
+        global_escrow.lottery_tokens_outstanding = global_escrow.lottery_tokens_outstanding.saturating_sub(tokens);
+        global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+        msg!("Lottery entry {} won: {} tokens to {}", entry_index, tokens, investor.key);
+    } else {
+        **sol_vault_account.try_borrow_mut_lamports()? -= entry_lamports;
+        **investor.try_borrow_mut_lamports()? += entry_lamports;
+
+        investor_data.status = InvestorStatus::Refunded;
+
+        msg!(
+            "Lottery entry {} {}: {} lamports refunded to {}",
+            entry_index,
+            if reveal_expired && !global_escrow.lottery_resolved { "never drawn" } else { "lost" },
+            entry_lamports,
+            investor.key
+        );
+    }
+
+    investor_data.lottery_claimed = true;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+pub fn process_claim_vested_tokens(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let investor_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    global_escrow.check_withdrawals_open()?;
+
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+    let current_timestamp = Clock::get()?.unix_timestamp;
+
+    // AUTHORIZATION: Only the investor can claim their own tokens, or their
+    // beneficiary once the inactivity deadline has passed
+    let recovery_open = global_escrow.inactivity_deadline().is_some_and(|deadline| current_timestamp >= deadline);
+    if !investor_data.is_claimant(investor.key, recovery_open) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // A refunded position handed its purchase back, vesting included
+    if !matches!(investor_data.status, InvestorStatus::Deposited | InvestorStatus::SolWithdrawn) {
+        msg!("Position status {:?} has no vesting to claim", investor_data.status);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(&investor_data.investor_pubkey, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_token_vault, _) = find_token_vault_pda(global_escrow_account.key, program_id);
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Tokens go to the signer, so a beneficiary claims into their own account
+    let token_account_data = read_token_account(investor_token_account)?;
+    if token_account_data.owner != *investor.key || token_account_data.mint != global_escrow.token_mint_pubkey {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    let vested = investor_data.vested_tokens(global_escrow.lock_duration, global_escrow.cliff_duration, current_timestamp);
+    let claimable = vested.saturating_sub(investor_data.tokens_claimed);
+    if claimable == 0 {
+        msg!(
+            "Nothing to claim: {} of {} vested, {} already claimed",
+            vested,
+            investor_data.tokens_vesting,
+            investor_data.tokens_claimed
+        );
+        return Ok(());
+    }
+
+    transfer_escrow_tokens(
+        &global_escrow,
+        accounts,
+        token_program,
+        token_vault_account,
+        investor_token_account,
+        global_escrow_account,
+        claimable,
+        &[&global_escrow.signer_seeds(&global_escrow.sale_id.to_le_bytes(), &[global_escrow.bump_seed])],
+    )?;
+
+    investor_data.tokens_claimed = investor_data
+        .tokens_claimed
+        .checked_add(claimable)
+        .ok_or(EscrowError::AmountOverflow)?;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+    global_escrow.vesting_tokens_outstanding = global_escrow.vesting_tokens_outstanding.saturating_sub(claimable);
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Vested tokens claimed: {} ({} of {} claimed)",
+        claimable,
+        investor_data.tokens_claimed,
+        investor_data.tokens_vesting
+    );
+
+    Ok(())
+}
+
+pub fn process_claim_tokens(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let holder = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let ticket_mint = next_account_info(account_info_iter)?;
+    let ticket_account = next_account_info(account_info_iter)?;
+    let holder_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !holder.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || !global_escrow.issues_claim_tickets() {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    global_escrow.check_withdrawals_open()?;
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp < global_escrow.sale_end_timestamp {
+        msg!("Sale has not ended yet. Current: {}, Sale ends: {}", current_timestamp, global_escrow.sale_end_timestamp);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    if amount == 0 || amount > global_escrow.claim_tickets_outstanding {
+        return Err(EscrowError::NotEnoughTokens.into());
+    }
+
+    if ticket_mint.key != &global_escrow.claim_ticket_mint {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    let (expected_token_vault, _) = find_token_vault_pda(global_escrow_account.key, program_id);
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let ticket_data = read_token_account(ticket_account)?;
+    if ticket_data.owner != *holder.key || ticket_data.mint != global_escrow.claim_ticket_mint {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+    if ticket_data.amount < amount {
+        msg!("Holder has {} claim tickets, {} requested", ticket_data.amount, amount);
+        return Err(EscrowError::NotEnoughTokens.into());
+    }
+
+    let token_account_data = read_token_account(holder_token_account)?;
+    if token_account_data.owner != *holder.key || token_account_data.mint != global_escrow.token_mint_pubkey {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    // Burn first: the holder signs for their own tickets
+    invoke(
+        &for_token_program(
+            spl_instruction::burn(
+                &spl_token::id(),
+                ticket_account.key,
+                ticket_mint.key,
+                holder.key,
+                &[],
+                amount,
+            )?,
+            token_program.key,
+        ),
+        &[
+            ticket_account.clone(),
+            ticket_mint.clone(),
+            holder.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    transfer_escrow_tokens(
+        &global_escrow,
+        accounts,
+        token_program,
+        token_vault_account,
+        holder_token_account,
+        global_escrow_account,
+        amount,
+        &[&global_escrow.signer_seeds(&global_escrow.sale_id.to_le_bytes(), &[global_escrow.bump_seed])],
+    )?;
+
+    global_escrow.claim_tickets_outstanding = global_escrow
+        .claim_tickets_outstanding
+        .checked_sub(amount)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Claim tickets redeemed by {}: {} tokens ({} tickets outstanding)",
+        holder.key,
+        amount,
+        global_escrow.claim_tickets_outstanding
+    );
+
+    Ok(())
+}
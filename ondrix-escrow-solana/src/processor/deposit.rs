@@ -0,0 +1,2505 @@
+// Deposit handlers (SOL, stablecoin, permit, commit-reveal, installments) and the
+// helpers they share
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    keccak,
+    ed25519_program,
+    instruction::{get_stack_height, AccountMeta, Instruction, TRANSACTION_LEVEL_STACK_HEIGHT},
+    log::sol_log_compute_units,
+    program::{get_return_data, invoke, invoke_signed, set_return_data},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::{self, Sysvar},
+};
+use spl_token::instruction as spl_instruction;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::{
+    calculate_tokens_for_sol, calculate_tokens_for_sol_tiered, calculate_tokens_for_stablecoin,
+    chainlink_latest_round_id, checked_mul_div, find_account_by_key, find_analytics_pda,
+    find_commitment_pda, find_deferred_deposit_pda, find_deposit_delegate_pda,
+    find_deposit_proof_pda, find_investor_pda, find_payment_vault_pda, find_permit_vault_pda,
+    find_recipient_vault_pda, find_sol_vault_pda, find_stake_position_pda, find_sybil_identity_pda,
+    for_token_program, get_median_price, get_oracle_price, is_token_account, is_token_program,
+    net_tokens_after_transfer_fee, read_mint_decimals, read_token_account, sol_for_tokens,
+    sol_for_tokens_on_curve, token_account_size, tokens_for_sol_on_curve, transfer_escrow_tokens,
+    transfer_tokens_checked, update_ema, usd_value_cents, verify_merkle_proof, verify_token_vault,
+    verify_whitelisted, Commitment, DeferredDeposit, DepositAnalytics, DepositDelegate, DepositMode,
+    DepositPermit, DepositProof, DepositReceipt, EscrowError, EscrowEvent, GlobalEscrow,
+    InvestorAccount, InvestorStatus, OracleKind, PermitVault, StakingInstruction, SybilAttestation,
+    SybilIdentity, BPS_DENOMINATOR, INVESTOR_ACCOUNT_DISCRIMINATOR, SYBIL_ATTESTATION_DOMAIN,
+    USD_CENTS_SCALE,
+};
+
+#[allow(clippy::too_many_arguments)]
+pub fn process_deposit_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    sol_amount: u64,
+    min_tokens_out: u64,
+    deadline: i64,
+    fill_to_cap: bool,
+    whitelist_proof: &[[u8; 32]],
+    chunks: u8,
+    mode: DepositMode,
+    profile: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let payer = next_account_info(account_info_iter)?; // investor, or executor/relayer per mode
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let investor_token_account = next_account_info(account_info_iter)?;
+    let recipient_wallet = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let oracle_program = next_account_info(account_info_iter)?;
+    let price_feed = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let _clock = next_account_info(account_info_iter)?;
+    let token_mint_account = next_account_info(account_info_iter)?;
+    let associated_token_program = next_account_info(account_info_iter)?;
+    let _rent_sysvar = next_account_info(account_info_iter)?;
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Load global escrow first
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    if global_escrow.is_paused {
+        msg!("Deposits are paused");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    global_escrow.check_sale_open(Clock::get()?.unix_timestamp)?;
+
+    // DEADLINE: the investor's quote is only good until the deadline they signed
+    if deadline > 0 {
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        if current_timestamp > deadline {
+            msg!("Deposit deadline passed: now {}, deadline {}", current_timestamp, deadline);
+            return Err(EscrowError::DeadlineExceeded.into());
+        }
+    }
+
+    // LOTTERY: oversubscribed fair launches take entries instead of priced deposits
+    if global_escrow.lottery_enabled {
+        msg!("Sale runs in lottery mode, use EnterLottery");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // CPI DEPTH GUARD: reject up front rather than failing deep inside a nested CPI
+    let stack_height = get_stack_height();
+    let stack_height_limit = global_escrow.deposit_stack_height_limit();
+    if stack_height > stack_height_limit {
+        msg!("Deposit invoked at stack height {}, limit is {}", stack_height, stack_height_limit);
+        return Err(EscrowError::CpiDepthExceeded.into());
+    }
+
+    // CPI DEPOSIT BLOCKING: sandwich bots and wrapper contracts reach the sale through CPI
+    if global_escrow.disallow_cpi_deposits {
+        verify_top_level_invocation(program_id, accounts)?;
+    }
+
+    // DEFERRED PRICING: the price must come from a reveal slot the investor couldn't pick up front
+    if global_escrow.deferred_pricing && mode != DepositMode::Deferred {
+        msg!("Sale uses deferred pricing, use CommitDeposit and RevealPrice");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // DELEGATED DEPOSITS: the on-chain investor identity (e.g. a multisig vault PDA) is
+    // decoupled from the signer, which must be the executor registered by that identity
+    let investor = match mode {
+        DepositMode::Direct | DepositMode::Deferred => payer,
+        DepositMode::Delegated => {
+            let investor_identity = next_account_info(account_info_iter)?;
+            let delegate_account = next_account_info(account_info_iter)?;
+            validate_deposit_delegate(
+                program_id,
+                &global_escrow,
+                global_escrow_account.key,
+                investor_identity,
+                delegate_account,
+                payer,
+            )?;
+            investor_identity
+        }
+        // PERMIT DEPOSITS: identity already checked against the signed permit
+        DepositMode::Permit => next_account_info(account_info_iter)?,
+    };
+
+    // SELF-DEALING: optionally keep the sale's own parties from buying into it
+    if global_escrow.forbid_self_dealing
+        && (investor.key == &global_escrow.initializer_pubkey
+            || global_escrow.is_authority(investor.key)
+            || investor.key == &global_escrow.recipient_wallet)
+    {
+        msg!("Self-dealing deposit rejected for {}", investor.key);
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // WHITELIST: permissioned sales only take deposits from listed investors
+    if global_escrow.whitelist_required {
+        verify_whitelisted(program_id, accounts, investor.key, global_escrow_account.key)?;
+    }
+    if global_escrow.whitelist_root != [0u8; 32]
+        && !verify_merkle_proof(keccak::hashv(&[investor.key.as_ref()]).to_bytes(), whitelist_proof, &global_escrow.whitelist_root)
+    {
+        msg!("Investor {} is not in the whitelist Merkle tree", investor.key);
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if *token_program.key != global_escrow.token_program_id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // ORACLE IMMUTABILITY: Use stored oracle config instead of hardcoded values
+    if oracle_program.key != &global_escrow.oracle_program_id {
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    // QUOTE ASSET FEEDS: SOL deposits are priced via the feed mapped for native SOL
+    if Some(*price_feed.key) != global_escrow.price_feed_for(&spl_token::native_mint::id()) {
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // Verify recipient wallet matches the one stored in global escrow
+    if recipient_wallet.key != &global_escrow.recipient_wallet {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // RENT BUDGET: total the rent of every account this deposit would create before creating any
+    if global_escrow.max_rent_per_investor > 0 {
+        let rent = Rent::get()?;
+        let mut rent_required: u64 = 0;
+        if !is_token_account(investor_token_account, token_program.key) {
+            rent_required += rent.minimum_balance(spl_token::state::Account::LEN);
+        }
+        if investor_account.owner != program_id || investor_account.data_len() != InvestorAccount::LEN {
+            rent_required += rent.minimum_balance(InvestorAccount::LEN);
+        }
+        if sol_vault_account.owner != program_id {
+            rent_required += rent.minimum_balance(0);
+        }
+        if global_escrow.deposit_proofs_enabled {
+            rent_required += rent.minimum_balance(DepositProof::LEN);
+        }
+        
+        if rent_required > global_escrow.max_rent_per_investor {
+            msg!(
+                "Deposit would charge {} lamports of rent, budget is {}",
+                rent_required,
+                global_escrow.max_rent_per_investor
+            );
+            return Err(EscrowError::RentBudgetExceeded.into());
+        }
+        msg!("Rent charged for new accounts: {} lamports", rent_required);
+    }
+
+    // Create investor's ATA if it doesn't exist  
+    if !is_token_account(investor_token_account, token_program.key) {
+        msg!("Creating ATA for investor");
+        
+        // Verify the expected ATA address
+        let expected_ata = spl_associated_token_account::get_associated_token_address_with_program_id(
+            investor.key,
+            token_mint_account.key,
+            token_program.key,
+        );
+        
+        if investor_token_account.key != &expected_ata {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let create_ata_ix = spl_associated_token_account::instruction::create_associated_token_account(
+            payer.key,         // payer
+            investor.key,      // owner 
+            token_mint_account.key, // mint
+            token_program.key, // token_program (validated against the escrow's above)
+        );
+
+        invoke(
+            &create_ata_ix,
+            &[
+                payer.clone(),
+                investor_token_account.clone(),
+                investor.clone(),
+                token_mint_account.clone(),
+                system_program.clone(),
+                token_program.clone(),
+                associated_token_program.clone(),
+            ],
+        )?;
+    }
+
+    // STRICT ATA VALIDATION
+    // Now validate the token account (after creation if needed)
+    let token_account_data = read_token_account(investor_token_account)?;
+    
+    // Verify token account owner is the investor
+    if token_account_data.owner != *investor.key {
+        msg!("Invalid token account owner. Expected: {}, Found: {}", investor.key, token_account_data.owner);
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+    
+    // Verify token account mint matches the escrow token mint
+    if token_account_data.mint != global_escrow.token_mint_pubkey {
+        msg!("Invalid token account mint. Expected: {}, Found: {}", global_escrow.token_mint_pubkey, token_account_data.mint);
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+    
+    // CUSTODIAN: the allow-listed custodian's accounts legitimately carry authorities,
+    // so only the owner and mint checks above apply to them
+    let is_custodian = global_escrow.custodian != Pubkey::default() && investor.key == &global_escrow.custodian;
+    if is_custodian {
+        msg!("Custodian token account, skipping delegate/close_authority checks");
+    }
+    
+    // Verify no delegate is set (security requirement)
+    if token_account_data.delegate.is_some() && !is_custodian {
+        msg!("Token account has delegate set, which is not allowed for security");
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+    
+    // Verify no close_authority is set (security requirement)
+    if token_account_data.close_authority.is_some() && !is_custodian {
+        msg!("Token account has close_authority set, which is not allowed for security");
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    // Verify investor PDA
+    let (expected_investor_account, investor_bump) = find_investor_pda(
+        investor.key,
+        global_escrow_account.key,
+        program_id,
+    );
+    
+    if investor_account.key != &expected_investor_account {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+    
+    // Verify SOL vault PDA
+    let (expected_sol_vault, sol_vault_bump) = find_sol_vault_pda(
+        investor.key,
+        global_escrow_account.key,
+        program_id,
+    );
+    
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // HARD CAP FILL: take only the SOL left to the cap, the rest never leaves the investor
+    let sol_amount = if fill_to_cap && global_escrow.sol_hard_cap > 0 {
+        let remaining_to_cap = global_escrow.sol_hard_cap.saturating_sub(global_escrow.total_sol_deposited);
+        if remaining_to_cap == 0 {
+            msg!("Hard cap already reached");
+            return Err(EscrowError::HardCapExceeded.into());
+        }
+        if sol_amount > remaining_to_cap {
+            msg!("Filling to cap: {} of {} lamports taken", remaining_to_cap, sol_amount);
+        }
+        sol_amount.min(remaining_to_cap)
+    } else {
+        sol_amount
+    };
+
+    // DEPOSIT QUANTIZATION: limits apply to the amount actually taken
+    let sol_amount = global_escrow.quantize_deposit(sol_amount)?;
+
+    // SECURITY: Validate investment limits using immutable config, the max against the
+    // investor's cumulative position whether or not their account exists yet
+    if sol_amount < global_escrow.min_sol_investment {
+        return Err(EscrowError::InvestmentBelowMinimum.into());
+    }
+    let existing_position =
+        if investor_account.owner == program_id && investor_account.data_len() == InvestorAccount::LEN {
+            Some(InvestorAccount::load(&investor_account.data.borrow())?)
+        } else {
+            None
+        };
+    let existing_sol_deposited = existing_position.as_ref().map_or(0, |position| position.sol_deposited);
+    let total_investment = existing_sol_deposited
+        .checked_add(sol_amount)
+        .ok_or(EscrowError::AmountOverflow)?;
+    if total_investment > global_escrow.max_sol_investment {
+        msg!(
+            "Investment {} + {} exceeds max {}",
+            existing_sol_deposited,
+            sol_amount,
+            global_escrow.max_sol_investment
+        );
+        return Err(EscrowError::InvestmentExceedsMaximum.into());
+    }
+
+    // DEPOSIT COOLDOWN: counted from last_deposit_timestamp, which every deposit updates.
+    // deposit_timestamp stays at the first deposit, as the cancellation window runs from it
+    if let Some(position) = existing_position.as_ref().filter(|_| global_escrow.deposit_cooldown > 0) {
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        let ready_at = position.last_deposit_timestamp.saturating_add(global_escrow.deposit_cooldown);
+        if current_timestamp < ready_at {
+            msg!("Deposit cooldown active until {}", ready_at);
+            return Err(EscrowError::CooldownActive.into());
+        }
+    }
+
+    log_compute_checkpoint(profile, "validation");
+
+    // Get SOL price from the oracle using immutable oracle config
+    // MULTI-ORACLE: median of the configured feeds instead of the single feed
+    let oracle_price = if global_escrow.use_multi_oracle {
+        get_median_price(accounts, oracle_program, &global_escrow)
+    } else {
+        get_oracle_price(price_feed, oracle_program, &global_escrow)
+    };
+    
+    // DEGRADED ORACLE MODE: on a stale feed, fall back to the cached last-good price
+    // for small deposits only, as long as the cached price itself isn't too old
+    let (spot_sol_usd_price, degraded_pricing) = match oracle_price {
+        Ok((price, timestamp)) => {
+            global_escrow.last_good_price = price;
+            global_escrow.last_good_price_timestamp = timestamp;
+            (price, false)
+        }
+        Err(err) if global_escrow.degraded_mode_enabled && err == EscrowError::StalePriceData.into() => {
+            let current_timestamp = Clock::get()?.unix_timestamp;
+            let cached_age = current_timestamp - global_escrow.last_good_price_timestamp;
+            if global_escrow.last_good_price == 0 || cached_age > global_escrow.degraded_max_price_age as i64 {
+                msg!("Cached price unusable: age {} > {}", cached_age, global_escrow.degraded_max_price_age);
+                return Err(err);
+            }
+            if sol_amount > global_escrow.degraded_max_deposit {
+                msg!("Degraded mode: deposit {} exceeds limit {}", sol_amount, global_escrow.degraded_max_deposit);
+                return Err(EscrowError::InvestmentExceedsMaximum.into());
+            }
+            msg!("Degraded pricing: cached price {} ({} seconds old)", global_escrow.last_good_price, cached_age);
+            (global_escrow.last_good_price, true)
+        }
+        Err(err) => return Err(err),
+    };
+    
+    // PRICE SMOOTHING: fold the spot price into the EMA and price against it when enabled.
+    // A cached degraded price carries no new information, so it doesn't move the EMA.
+    let sol_usd_price = if degraded_pricing {
+        spot_sol_usd_price
+    } else if global_escrow.use_ema {
+        global_escrow.ema_price = update_ema(global_escrow.ema_price, spot_sol_usd_price, global_escrow.ema_alpha_bps)?;
+        msg!("EMA price: {} (spot: {})", global_escrow.ema_price, spot_sol_usd_price);
+        global_escrow.ema_price
+    } else {
+        spot_sol_usd_price
+    };
+    
+    // PRICE VERIFICATION: remember the round when the price is exactly one Chainlink answer
+    let price_round_id = if global_escrow.oracle_kind == OracleKind::Chainlink
+        && !global_escrow.use_multi_oracle
+        && !global_escrow.use_ema
+        && !degraded_pricing
+    {
+        chainlink_latest_round_id(&price_feed.data.borrow()).unwrap_or(0)
+    } else {
+        0
+    };
+    
+    // DUTCH AUCTION: time-decayed price replaces the tier schedule (the curve still wins)
+    let auction_price_cents = global_escrow.dutch_auction_price_cents(Clock::get()?.unix_timestamp);
+    
+    // Calculate tokens for SOL amount against the bonding curve, the auction price or the tier schedule
+    let mut tokens_to_receive = if global_escrow.use_curve {
+        tokens_for_sol_on_curve(&global_escrow, sol_amount, sol_usd_price)?
+    } else if let Some(price_cents) = auction_price_cents {
+        msg!("Dutch auction price: {} cents", price_cents);
+        calculate_tokens_for_sol(sol_amount, sol_usd_price, price_cents, global_escrow.token_decimals)?
+    } else {
+        calculate_tokens_for_sol_tiered(&global_escrow, sol_amount, sol_usd_price, chunks)?
+    };
+    
+    // PARTIAL FILLS: deliver what's left and only take the SOL those tokens cost.
+    // The unfilled remainder never leaves the investor, priced at the current tier.
+    // DRIP SALE: what's left includes the current bucket's allowance.
+    let bucket_allowance = global_escrow.bucket_allowance(Clock::get()?.unix_timestamp);
+    let fillable = global_escrow.fillable_tokens().min(bucket_allowance);
+    let sol_amount = if global_escrow.allow_partial_fill && tokens_to_receive > fillable && fillable > 0 {
+        let delivered_sol = if global_escrow.use_curve {
+            sol_for_tokens_on_curve(&global_escrow, fillable, sol_usd_price)?
+        } else {
+            let price_cents = auction_price_cents
+                .unwrap_or_else(|| global_escrow.token_price_cents_at(global_escrow.tokens_sold));
+            sol_for_tokens(fillable, sol_usd_price, price_cents, global_escrow.token_decimals)?
+        }
+        .min(sol_amount);
+        let refunded_sol = sol_amount - delivered_sol;
+        
+        // Conservation: every requested lamport is either delivered or refunded
+        if delivered_sol.checked_add(refunded_sol) != Some(sol_amount) {
+            return Err(EscrowError::AmountOverflow.into());
+        }
+        
+        msg!(
+            "Partial fill: {} of {} tokens for {} lamports, {} lamports refunded",
+            fillable,
+            tokens_to_receive,
+            delivered_sol,
+            refunded_sol
+        );
+        tokens_to_receive = fillable;
+        delivered_sol
+    } else {
+        sol_amount
+    };
+    
+    // EARLY-BIRD BONUS: prorated to the part of this deposit below the threshold
+    let bonus_tokens = global_escrow.early_bird_bonus(sol_amount, tokens_to_receive, fillable)?;
+    if bonus_tokens > 0 {
+        msg!("Early-bird bonus: {} tokens", bonus_tokens);
+        tokens_to_receive = tokens_to_receive
+            .checked_add(bonus_tokens)
+            .ok_or(EscrowError::AmountOverflow)?;
+    }
+    
+    // SLIPPAGE: the price may have moved since the investor signed
+    if tokens_to_receive < min_tokens_out {
+        msg!("Slippage exceeded: {} tokens < minimum {}", tokens_to_receive, min_tokens_out);
+        return Err(EscrowError::SlippageExceeded.into());
+    }
+    
+    // HARD CAP: total raised may never pass sol_hard_cap
+    if global_escrow.sol_hard_cap > 0 {
+        let total_after = global_escrow
+            .total_sol_deposited
+            .checked_add(sol_amount)
+            .ok_or(EscrowError::AmountOverflow)?;
+        if total_after > global_escrow.sol_hard_cap {
+            msg!("Hard cap exceeded: {} > {}", total_after, global_escrow.sol_hard_cap);
+            return Err(EscrowError::HardCapExceeded.into());
+        }
+    }
+    
+    // SYBIL DAMPENING: every wallet attested to one identity shares a single SOL cap
+    let sybil_identity = if global_escrow.sybil_authority != Pubkey::default() {
+        let (identity_account, mut identity) =
+            load_attested_identity(program_id, accounts, &global_escrow, global_escrow_account.key, investor.key, payer, system_program)?;
+        identity.total_sol_deposited = identity
+            .total_sol_deposited
+            .checked_add(sol_amount)
+            .ok_or(EscrowError::AmountOverflow)?;
+        if identity.total_sol_deposited > global_escrow.max_sol_per_identity {
+            msg!("Identity cap exceeded: {} > {}", identity.total_sol_deposited, global_escrow.max_sol_per_identity);
+            return Err(EscrowError::IdentityCapExceeded.into());
+        }
+        Some((identity_account, identity))
+    } else {
+        None
+    };
+    
+    // Check if enough tokens available
+    let tokens_remaining = global_escrow
+        .total_tokens_available
+        .checked_sub(global_escrow.tokens_sold)
+        .ok_or(EscrowError::AmountOverflow)?;
+    if tokens_to_receive > tokens_remaining {
+        return Err(EscrowError::NotEnoughTokens.into());
+    }
+    
+    // Enforce per-round and lifetime sale caps
+    global_escrow.check_sale_caps(tokens_to_receive)?;
+    
+    if tokens_to_receive > bucket_allowance {
+        msg!("Drip bucket allowance exceeded: {} > {}", tokens_to_receive, bucket_allowance);
+        return Err(EscrowError::BucketLimitReached.into());
+    }
+    
+    // TRANSFER FEES: supply accounting uses what leaves the vault, the investor's
+    // position records what actually lands after the mint's transfer fee
+    if token_mint_account.key != &global_escrow.token_mint_pubkey {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+    let tokens_delivered = net_tokens_after_transfer_fee(token_mint_account, tokens_to_receive, Clock::get()?.epoch)?;
+    if tokens_delivered != tokens_to_receive {
+        msg!("Transfer fee: {} sent, {} received", tokens_to_receive, tokens_delivered);
+    }
+    
+    // VESTING: only the immediate share leaves the vault now. The rest is already counted
+    // in tokens_sold, so CloseSale leaves it in the vault for ClaimVestedTokens.
+    // CLAIM TICKETS: the whole purchase stays in the vault, owed to whoever holds the tickets
+    let ticket_tokens = if global_escrow.issues_claim_tickets() { tokens_to_receive } else { 0 };
+    let (immediate_tokens, tokens_vesting) = if ticket_tokens > 0 {
+        (0, 0)
+    } else {
+        global_escrow.split_vesting(tokens_to_receive)?
+    };
+    if tokens_vesting > 0 {
+        msg!("Vesting: {} tokens now, {} vesting", immediate_tokens, tokens_vesting);
+    }
+    
+    log_compute_checkpoint(profile, "oracle");
+
+    // Create or update investor account
+    let is_top_up = investor_account.owner == program_id && investor_account.data_len() == InvestorAccount::LEN;
+    let mut investor_data = if investor_account.owner != program_id || investor_account.data_len() != InvestorAccount::LEN {
+        // Create new investor account
+        let investor_size = InvestorAccount::LEN;
+        let rent = Rent::get()?;
+        let rent_lamports = rent.minimum_balance(investor_size);
+        
+        let create_investor_ix = system_instruction::create_account(
+            payer.key,
+            investor_account.key,
+            rent_lamports,
+            investor_size as u64,
+            program_id,
+        );
+
+        invoke_signed(
+            &create_investor_ix,
+            &[
+                payer.clone(),
+                investor_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"investor",
+                investor.key.as_ref(),
+                global_escrow_account.key.as_ref(),
+                &[investor_bump],
+            ]],
+        )?;
+
+        InvestorAccount {
+            discriminator: INVESTOR_ACCOUNT_DISCRIMINATOR,
+            is_initialized: true,
+            investor_pubkey: *investor.key,
+            global_escrow_pubkey: *global_escrow_account.key,
+            sol_deposited: sol_amount,
+            tokens_received: tokens_delivered,
+            deposit_timestamp: Clock::get()?.unix_timestamp,
+            sol_usd_price,
+            status: InvestorStatus::Deposited,
+            bump_seed: investor_bump,
+            deposit_count: 0,
+            last_deposit_timestamp: 0,
+            held_immediate_sol: 0,
+            refund_registered: false,
+            refund_owed: 0,
+            refund_queue_position: 0,
+            custom_lock_duration: 0,
+            lottery_entry: 0,
+            lottery_claimed: false,
+            tokens_vesting,
+            tokens_claimed: 0,
+            avg_sol_usd_price: sol_usd_price,
+            price_round_id,
+            beneficiary: Pubkey::default(),
+            payment_mint: Pubkey::default(),
+            payment_deposited: 0,
+            vesting_start: Clock::get()?.unix_timestamp,
+        }
+    } else {
+        // Update existing investor account
+        let mut existing_data = InvestorAccount::load(&investor_account.data.borrow())?;
+        let total_investment = existing_data
+            .sol_deposited
+            .checked_add(sol_amount)
+            .ok_or(EscrowError::AmountOverflow)?;
+        
+        // Pre-provisioned and refunded accounts start over on this deposit
+        existing_data.reopen_for_deposit(Clock::get()?.unix_timestamp)?;
+        
+        // Cost basis: lamport-weighted average of the prices paid
+        existing_data.avg_sol_usd_price = u64::try_from(
+            ((existing_data.avg_sol_usd_price as u128) * existing_data.sol_deposited as u128
+                + (sol_usd_price as u128) * sol_amount as u128)
+                / total_investment.max(1) as u128,
+        )
+        .map_err(|_| EscrowError::AmountOverflow)?;
+        existing_data.sol_deposited = existing_data
+            .sol_deposited
+            .checked_add(sol_amount)
+            .ok_or(EscrowError::AmountOverflow)?;
+        existing_data.tokens_received = existing_data
+            .tokens_received
+            .checked_add(tokens_delivered)
+            .ok_or(EscrowError::AmountOverflow)?;
+        existing_data.add_vesting(tokens_vesting, Clock::get()?.unix_timestamp)?;
+        existing_data.sol_usd_price = sol_usd_price; // Update to latest price for reference
+        existing_data.price_round_id = price_round_id;
+        existing_data
+    };
+
+    // Create SOL vault if it doesn't exist
+    if sol_vault_account.owner != program_id {
+        let rent = Rent::get()?;
+        let rent_lamports = rent.minimum_balance(0); // Empty account for storing SOL
+        
+        let create_sol_vault_ix = system_instruction::create_account(
+            payer.key,
+            sol_vault_account.key,
+            rent_lamports,
+            0, // No data, just stores SOL
+            program_id,
+        );
+
+        invoke_signed(
+            &create_sol_vault_ix,
+            &[
+                payer.clone(),
+                sol_vault_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"sol_vault",
+                investor.key.as_ref(),
+                global_escrow_account.key.as_ref(),
+                &[sol_vault_bump],
+            ]],
+        )?;
+    }
+
+    // Split SOL: 50% to recipient, 50% to SOL vault for locking
+    let sol_immediate = sol_amount / 2;
+    let sol_to_lock = sol_amount - sol_immediate; // Remaining SOL goes to vault
+    
+    // PROTOCOL FEE: carved out of the immediate half so locked SOL is untouched
+    let fee_amount = checked_mul_div(sol_amount, global_escrow.protocol_fee_bps as u64, BPS_DENOMINATOR)?;
+    let sol_to_recipient = sol_immediate - fee_amount;
+    
+    if fee_amount > 0 {
+        let fee_wallet = find_account_by_key(accounts, &global_escrow.fee_wallet).ok_or_else(|| {
+            msg!("Missing fee wallet account: {}", global_escrow.fee_wallet);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+        
+        invoke(
+            &system_instruction::transfer(payer.key, fee_wallet.key, fee_amount),
+            &[
+                payer.clone(),
+                fee_wallet.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+    
+    // CANCELLATION WINDOW: keep the immediate portion in the investor's SOL vault
+    // until the window passes so a cancellation can return it
+    let hold_immediate = investor_data.in_cancellation_window(global_escrow.cancellation_window, Clock::get()?.unix_timestamp);
+    
+    // RECIPIENT FALLBACK: pre-flight the recipient and park the immediate portion
+    // in the recipient vault if it can't take a direct system transfer
+    let route_to_recipient_vault = !hold_immediate
+        && global_escrow.recipient_fallback_enabled
+        && !can_receive_lamports(recipient_wallet);
+    let immediate_destination = if hold_immediate {
+        sol_vault_account
+    } else if route_to_recipient_vault {
+        let (expected_recipient_vault, recipient_vault_bump) = find_recipient_vault_pda(global_escrow_account.key, program_id);
+        let recipient_vault = find_account_by_key(accounts, &expected_recipient_vault).ok_or_else(|| {
+            msg!("Missing recipient vault account: {}", expected_recipient_vault);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+        
+        // Create recipient vault if it doesn't exist
+        if recipient_vault.owner != program_id {
+            let rent = Rent::get()?;
+            invoke_signed(
+                &system_instruction::create_account(
+                    payer.key,
+                    recipient_vault.key,
+                    rent.minimum_balance(0),
+                    0, // No data, just stores SOL
+                    program_id,
+                ),
+                &[
+                    payer.clone(),
+                    recipient_vault.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    b"recipient_vault",
+                    global_escrow_account.key.as_ref(),
+                    &[recipient_vault_bump],
+                ]],
+            )?;
+        }
+        
+        msg!("Recipient can't receive directly, routing {} lamports to recipient vault", sol_to_recipient);
+        recipient_vault
+    } else {
+        recipient_wallet
+    };
+    
+    // Transfer 50% SOL (minus fee) to recipient
+    let transfer_to_recipient_ix = system_instruction::transfer(
+        payer.key,
+        immediate_destination.key,
+        sol_to_recipient,
+    );
+    
+    invoke(
+        &transfer_to_recipient_ix,
+        &[
+            payer.clone(),
+            immediate_destination.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    // Transfer 50% SOL to SOL vault for locking
+    let transfer_to_vault_ix = system_instruction::transfer(
+        payer.key,
+        sol_vault_account.key,
+        sol_to_lock,
+    );
+    
+    invoke(
+        &transfer_to_vault_ix,
+        &[
+            payer.clone(),
+            sol_vault_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+
+    // VAULT SELF-CHECK: catch a vault whose authority was handed away before paying out of it
+    if global_escrow.check_vault_on_deposit {
+        verify_token_vault(program_id, global_escrow_account, token_vault_account, &global_escrow)?;
+    }
+
+    // SECURITY FIX: CEI Pattern - All external calls BEFORE state updates
+    // Transfer all tokens to investor immediately
+    // STAKING DELIVERY: or into the staking pool, credited to the investor by CPI below
+    let token_destination = if global_escrow.stakes_on_deposit() {
+        find_account_by_key(accounts, &global_escrow.staking_deposit_account).ok_or_else(|| {
+            msg!("Missing staking deposit account: {}", global_escrow.staking_deposit_account);
+            ProgramError::NotEnoughAccountKeys
+        })?
+    } else {
+        investor_token_account
+    };
+    
+    let (sale_id_bytes, bump) = (global_escrow.sale_id.to_le_bytes(), [global_escrow.bump_seed]);
+    let global_escrow_seeds: &[&[u8]] = &global_escrow.signer_seeds(&sale_id_bytes, &bump);
+
+    // VESTING MODE: nothing is delivered at deposit
+    if immediate_tokens > 0 {
+        transfer_escrow_tokens(
+            &global_escrow,
+            accounts,
+            token_program,
+            token_vault_account,
+            token_destination,
+            global_escrow_account,
+            immediate_tokens,
+            &[global_escrow_seeds],
+        )?;
+    }
+    
+    if global_escrow.stakes_on_deposit() && immediate_tokens > 0 {
+        let tokens_staked = if tokens_vesting == 0 {
+            tokens_delivered
+        } else {
+            net_tokens_after_transfer_fee(token_mint_account, immediate_tokens, Clock::get()?.epoch)?
+        };
+        stake_for_investor(
+            accounts,
+            &global_escrow,
+            global_escrow_account,
+            investor,
+            payer,
+            system_program,
+            tokens_staked,
+            global_escrow_seeds,
+        )?;
+    }
+    
+    if ticket_tokens > 0 {
+        let ticket_mint = find_account_by_key(accounts, &global_escrow.claim_ticket_mint).ok_or_else(|| {
+            msg!("Missing claim ticket mint: {}", global_escrow.claim_ticket_mint);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+        let ticket_account_key = spl_associated_token_account::get_associated_token_address_with_program_id(
+            investor.key,
+            &global_escrow.claim_ticket_mint,
+            token_program.key,
+        );
+        let ticket_account = find_account_by_key(accounts, &ticket_account_key).ok_or_else(|| {
+            msg!("Missing claim ticket account: {}", ticket_account_key);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+        
+        invoke_signed(
+            &for_token_program(
+                spl_instruction::mint_to(
+                    &spl_token::id(),
+                    ticket_mint.key,
+                    ticket_account.key,
+                    global_escrow_account.key,
+                    &[],
+                    ticket_tokens,
+                )?,
+                token_program.key,
+            ),
+            &[
+                ticket_mint.clone(),
+                ticket_account.clone(),
+                global_escrow_account.clone(),
+                token_program.clone(),
+            ],
+            &[global_escrow_seeds],
+        )?;
+        
+        global_escrow.claim_tickets_outstanding = global_escrow
+            .claim_tickets_outstanding
+            .checked_add(ticket_tokens)
+            .ok_or(EscrowError::AmountOverflow)?;
+        msg!("Claim tickets minted: {}", ticket_tokens);
+    }
+
+    log_compute_checkpoint(profile, "transfers");
+
+    // SECURITY FIX: Update state ONLY after all external calls succeed
+    global_escrow.tokens_sold = global_escrow
+        .tokens_sold
+        .checked_add(tokens_to_receive)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.round_tokens_sold = global_escrow
+        .round_tokens_sold
+        .checked_add(tokens_to_receive)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.record_bucket_sale(tokens_to_receive, Clock::get()?.unix_timestamp)?;
+    global_escrow.vesting_tokens_outstanding = global_escrow
+        .vesting_tokens_outstanding
+        .checked_add(tokens_vesting)
+        .ok_or(EscrowError::AmountOverflow)?;
+    if let Some((identity_account, identity)) = &sybil_identity {
+        identity.serialize(&mut &mut identity_account.data.borrow_mut()[..])?;
+    }
+    if !is_top_up {
+        global_escrow.open_investor_accounts = global_escrow
+            .open_investor_accounts
+            .checked_add(1)
+            .ok_or(EscrowError::AmountOverflow)?;
+    }
+    global_escrow.total_usd_value_cents = global_escrow
+        .total_usd_value_cents
+        .checked_add(usd_value_cents(sol_amount, sol_usd_price)?)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.usd_valued_tokens = global_escrow
+        .usd_valued_tokens
+        .checked_add(tokens_to_receive)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_sol_deposited = global_escrow
+        .total_sol_deposited
+        .checked_add(sol_amount)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_fees_collected = global_escrow
+        .total_fees_collected
+        .checked_add(fee_amount)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.record_raise_velocity(sol_amount, Clock::get()?.unix_timestamp)?;
+    if route_to_recipient_vault {
+        global_escrow.pending_immediate_sol = global_escrow
+            .pending_immediate_sol
+            .checked_add(sol_to_recipient)
+            .ok_or(EscrowError::AmountOverflow)?;
+    }
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    // DEPOSIT ANALYTICS: fold this deposit into the size histogram
+    if global_escrow.analytics_enabled {
+        record_deposit_analytics(program_id, accounts, global_escrow_account, sol_amount)?;
+    }
+
+    // DEPOSIT PROOFS: persist a write-once record of this deposit
+    if global_escrow.deposit_proofs_enabled {
+        write_deposit_proof(
+            program_id,
+            accounts,
+            payer,
+            investor_account,
+            system_program,
+            &investor_data,
+            sol_amount,
+            tokens_delivered,
+            sol_usd_price,
+        )?;
+    }
+    investor_data.deposit_count = investor_data
+        .deposit_count
+        .checked_add(1)
+        .ok_or(EscrowError::AmountOverflow)?;
+    investor_data.last_deposit_timestamp = Clock::get()?.unix_timestamp;
+    if hold_immediate {
+        investor_data.held_immediate_sol = investor_data
+            .held_immediate_sol
+            .checked_add(sol_to_recipient)
+            .ok_or(EscrowError::AmountOverflow)?;
+    }
+
+    // Update investor account state after successful token transfer
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "SOL deposited: {} lamports, tokens received: {}, price: {}",
+        sol_amount,
+        tokens_delivered,
+        sol_usd_price
+    );
+    EscrowEvent::Deposited {
+        global_escrow: *global_escrow_account.key,
+        investor: *investor.key,
+        sol_amount,
+        tokens: tokens_delivered,
+        sol_usd_price,
+        timestamp: investor_data.last_deposit_timestamp,
+    }
+    .emit()?;
+    
+    let receipt = DepositReceipt {
+        this_deposit_sol: sol_amount,
+        this_deposit_tokens: tokens_delivered,
+        cumulative_sol: investor_data.sol_deposited,
+        cumulative_tokens: investor_data.tokens_received,
+        sol_usd_price,
+        is_top_up,
+        degraded_pricing,
+    };
+    set_return_data(&borsh::to_vec(&receipt)?);
+    
+    Ok(())
+}
+
+/// Whether a system transfer to `account` is expected to succeed. Executable accounts
+/// can never be credited, and data-bearing accounts owned by other programs are treated
+/// as unable to receive so the deposit doesn't depend on their program's constraints.
+pub fn can_receive_lamports(account: &AccountInfo) -> bool {
+    !account.executable
+        && (account.owner == &solana_program::system_program::id() || account.data_len() == 0)
+}
+
+/// CPI into the configured staking program to credit tokens already moved into the
+/// pool deposit account to the investor. Staking accounts are located by key.
+#[allow(clippy::too_many_arguments)]
+pub fn stake_for_investor<'a>(
+    accounts: &[AccountInfo<'a>],
+    global_escrow: &GlobalEscrow,
+    global_escrow_account: &AccountInfo<'a>,
+    investor: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    amount: u64,
+    global_escrow_seeds: &[&[u8]],
+) -> ProgramResult {
+    let (stake_position_key, _) = find_stake_position_pda(&global_escrow.staking_pool, investor.key, &global_escrow.staking_program);
+    let staking_program = find_account_by_key(accounts, &global_escrow.staking_program);
+    let staking_pool = find_account_by_key(accounts, &global_escrow.staking_pool);
+    let staking_deposit_account = find_account_by_key(accounts, &global_escrow.staking_deposit_account);
+    let stake_position = find_account_by_key(accounts, &stake_position_key);
+    
+    let (Some(staking_program), Some(staking_pool), Some(staking_deposit_account), Some(stake_position)) =
+        (staking_program, staking_pool, staking_deposit_account, stake_position)
+    else {
+        msg!("Missing staking accounts");
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    
+    if !staking_program.executable {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let stake_ix = Instruction {
+        program_id: *staking_program.key,
+        accounts: vec![
+            AccountMeta::new(*staking_pool.key, false),
+            AccountMeta::new(*staking_deposit_account.key, false),
+            AccountMeta::new(*stake_position.key, false),
+            AccountMeta::new_readonly(*investor.key, false),
+            AccountMeta::new_readonly(*global_escrow_account.key, true),
+            AccountMeta::new(*payer.key, true),
+            AccountMeta::new_readonly(*system_program.key, false),
+        ],
+        data: borsh::to_vec(&StakingInstruction::StakeFor { amount, beneficiary: *investor.key })?,
+    };
+    
+    invoke_signed(
+        &stake_ix,
+        &[
+            staking_pool.clone(),
+            staking_deposit_account.clone(),
+            stake_position.clone(),
+            investor.clone(),
+            global_escrow_account.clone(),
+            payer.clone(),
+            system_program.clone(),
+            staking_program.clone(),
+        ],
+        &[global_escrow_seeds],
+    )?;
+    
+    msg!("Staked {} tokens for investor {}", amount, investor.key);
+    
+    Ok(())
+}
+
+/// Log the remaining compute budget at a named checkpoint when profiling is on
+pub fn log_compute_checkpoint(profile: bool, checkpoint: &str) {
+    if profile {
+        msg!("Profile checkpoint: {}", checkpoint);
+        sol_log_compute_units();
+    }
+}
+
+/// Fail with CpiNotAllowed unless this program is running as the transaction's
+/// current top-level instruction, i.e. no other program invoked it
+pub fn verify_top_level_invocation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let instructions_sysvar = find_account_by_key(accounts, &sysvar::instructions::id()).ok_or_else(|| {
+        msg!("Missing instructions sysvar for the CPI check");
+        ProgramError::NotEnoughAccountKeys
+    })?;
+
+    let current_index = sysvar::instructions::load_current_index_checked(instructions_sysvar)?;
+    let current_ix = sysvar::instructions::load_instruction_at_checked(current_index as usize, instructions_sysvar)?;
+    // The stack height also catches re-entry from a CPI made by our own top-level instruction
+    if current_ix.program_id != *program_id || get_stack_height() != TRANSACTION_LEVEL_STACK_HEIGHT {
+        msg!("Deposit invoked through program {}", current_ix.program_id);
+        return Err(EscrowError::CpiNotAllowed.into());
+    }
+
+    Ok(())
+}
+
+/// Create the write-once DepositProof PDA for the investor's next deposit index
+#[allow(clippy::too_many_arguments)]
+pub fn write_deposit_proof<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    investor: &AccountInfo<'a>,
+    investor_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    investor_data: &InvestorAccount,
+    sol_amount: u64,
+    tokens_received: u64,
+    sol_usd_price: u64,
+) -> ProgramResult {
+    let deposit_index = investor_data.deposit_count;
+    let (expected_proof, proof_bump) = find_deposit_proof_pda(investor_account.key, deposit_index, program_id);
+    let proof_account = find_account_by_key(accounts, &expected_proof).ok_or_else(|| {
+        msg!("Missing deposit proof account: {}", expected_proof);
+        ProgramError::NotEnoughAccountKeys
+    })?;
+
+    // Write-once: the PDA must not exist yet
+    if proof_account.data_len() > 0 || proof_account.owner == program_id {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            investor.key,
+            proof_account.key,
+            rent.minimum_balance(DepositProof::LEN),
+            DepositProof::LEN as u64,
+            program_id,
+        ),
+        &[
+            investor.clone(),
+            proof_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"deposit_proof",
+            investor_account.key.as_ref(),
+            &deposit_index.to_le_bytes(),
+            &[proof_bump],
+        ]],
+    )?;
+
+    let clock = Clock::get()?;
+    let mut proof = DepositProof {
+        is_initialized: true,
+        investor_pubkey: investor_data.investor_pubkey,
+        global_escrow_pubkey: investor_data.global_escrow_pubkey,
+        deposit_index,
+        sol_amount,
+        tokens_received,
+        sol_usd_price,
+        timestamp: clock.unix_timestamp,
+        slot: clock.slot,
+        proof_hash: [0; 32],
+    };
+    proof.proof_hash = proof.compute_hash();
+    proof.serialize(&mut &mut proof_account.data.borrow_mut()[..])?;
+
+    msg!("Deposit proof #{} written: {}", deposit_index, proof_account.key);
+
+    Ok(())
+}
+
+pub fn process_set_deposit_delegate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    executor: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor_identity = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let delegate_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    // The identity itself must approve its executor
+    if !investor_identity.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || !global_escrow.allow_delegated_deposits {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_delegate, delegate_bump) = find_deposit_delegate_pda(
+        investor_identity.key,
+        global_escrow_account.key,
+        program_id,
+    );
+    if delegate_account.key != &expected_delegate {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if delegate_account.owner != program_id {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                investor_identity.key,
+                delegate_account.key,
+                rent.minimum_balance(DepositDelegate::LEN),
+                DepositDelegate::LEN as u64,
+                program_id,
+            ),
+            &[
+                investor_identity.clone(),
+                delegate_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"deposit_delegate",
+                investor_identity.key.as_ref(),
+                global_escrow_account.key.as_ref(),
+                &[delegate_bump],
+            ]],
+        )?;
+    }
+
+    let delegate = DepositDelegate {
+        is_initialized: true,
+        investor_pubkey: *investor_identity.key,
+        global_escrow_pubkey: *global_escrow_account.key,
+        executor,
+        bump_seed: delegate_bump,
+    };
+    delegate.serialize(&mut &mut delegate_account.data.borrow_mut()[..])?;
+
+    msg!("Deposit executor for {} set to {}", investor_identity.key, executor);
+
+    Ok(())
+}
+
+/// Check that `executor` is the registered deposit executor for `investor_identity`
+pub fn validate_deposit_delegate(
+    program_id: &Pubkey,
+    global_escrow: &GlobalEscrow,
+    global_escrow_key: &Pubkey,
+    investor_identity: &AccountInfo,
+    delegate_account: &AccountInfo,
+    executor: &AccountInfo,
+) -> ProgramResult {
+    if !global_escrow.allow_delegated_deposits {
+        msg!("Delegated deposits are not enabled for this escrow");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_delegate, _) = find_deposit_delegate_pda(investor_identity.key, global_escrow_key, program_id);
+    if delegate_account.key != &expected_delegate || delegate_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let delegate = DepositDelegate::try_from_slice(&delegate_account.data.borrow())?;
+    if !delegate.is_initialized
+        || delegate.investor_pubkey != *investor_identity.key
+        || delegate.global_escrow_pubkey != *global_escrow_key
+    {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if !executor.is_signer || delegate.executor != *executor.key {
+        msg!("Unauthorized executor. Expected: {}, Found: {}", delegate.executor, executor.key);
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    Ok(())
+}
+
+pub fn process_deposit_fill_cap(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let _investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || global_escrow.sol_hard_cap == 0 {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let remaining_to_cap = global_escrow.sol_hard_cap.saturating_sub(global_escrow.total_sol_deposited);
+    if remaining_to_cap == 0 {
+        msg!("Hard cap already reached");
+        return Err(EscrowError::HardCapExceeded.into());
+    }
+
+    // Per-address allowance; the investor PDA itself is verified by process_deposit_sol
+    let already_deposited = if investor_account.owner == program_id && investor_account.data_len() == InvestorAccount::LEN {
+        InvestorAccount::load(&investor_account.data.borrow())?.sol_deposited
+    } else {
+        0
+    };
+    let remaining_allowance = global_escrow.max_sol_investment.saturating_sub(already_deposited);
+    if remaining_allowance == 0 {
+        return Err(EscrowError::InvestmentExceedsMaximum.into());
+    }
+
+    let fill_amount = remaining_to_cap.min(remaining_allowance);
+    msg!("Filling cap with {} lamports ({} left to cap)", fill_amount, remaining_to_cap);
+
+    process_deposit_sol(program_id, accounts, fill_amount, 0, 0, false, &[], 1, DepositMode::Direct, false)
+}
+
+/// Public key and message of a single-signature Ed25519 program instruction, provided
+/// all of its data is inline in that instruction
+pub fn ed25519_signed_payload(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    // Layout: [num_signatures: u8][padding: u8] then per signature 7 u16 offsets:
+    // signature, signature ix, public key, public key ix, message, message size, message ix
+    if data.len() < 2 + 14 || data[0] != 1 {
+        return None;
+    }
+    let offset = |i: usize| u16::from_le_bytes([data[2 + i * 2], data[3 + i * 2]]);
+    let (public_key_offset, message_offset, message_size) = (offset(2) as usize, offset(4) as usize, offset(5) as usize);
+
+    // Offsets must point into the Ed25519 instruction itself, not some other instruction
+    if offset(1) != u16::MAX || offset(3) != u16::MAX || offset(6) != u16::MAX {
+        return None;
+    }
+
+    let signed_key = data.get(public_key_offset..public_key_offset + 32)?;
+    let signed_message = data.get(message_offset..message_offset + message_size)?;
+    Some((signed_key, signed_message))
+}
+
+/// Find the sybil authority's attestation for `wallet` among the Ed25519 verifications
+/// earlier in the transaction, then load (creating it on first use, paid by `payer`)
+/// the identity PDA it names. The PDA and the instructions sysvar are located by key.
+#[allow(clippy::too_many_arguments)]
+pub fn load_attested_identity<'a, 'b>(
+    program_id: &Pubkey,
+    accounts: &'b [AccountInfo<'a>],
+    global_escrow: &GlobalEscrow,
+    global_escrow_key: &Pubkey,
+    wallet: &Pubkey,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+) -> Result<(&'b AccountInfo<'a>, SybilIdentity), ProgramError> {
+    let instructions_sysvar = find_account_by_key(accounts, &sysvar::instructions::id()).ok_or_else(|| {
+        msg!("Missing instructions sysvar for the sybil attestation");
+        EscrowError::InvalidAttestation
+    })?;
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let current_index = sysvar::instructions::load_current_index_checked(instructions_sysvar)?;
+    let mut attestation = None;
+    for index in 0..current_index as usize {
+        let ix = sysvar::instructions::load_instruction_at_checked(index, instructions_sysvar)?;
+        if ix.program_id != ed25519_program::id() {
+            continue;
+        }
+        let Some((signed_key, signed_message)) = ed25519_signed_payload(&ix.data) else {
+            continue;
+        };
+        if signed_key != global_escrow.sybil_authority.as_ref() {
+            continue;
+        }
+        let Some(payload) = signed_message.strip_prefix(SYBIL_ATTESTATION_DOMAIN) else {
+            continue;
+        };
+        let Ok(candidate) = SybilAttestation::try_from_slice(payload) else {
+            continue;
+        };
+        if candidate.global_escrow == *global_escrow_key && candidate.wallet == *wallet && candidate.expiry >= current_timestamp {
+            attestation = Some(candidate);
+            break;
+        }
+    }
+    let attestation = attestation.ok_or_else(|| {
+        msg!("No live sybil attestation for {}", wallet);
+        EscrowError::InvalidAttestation
+    })?;
+
+    let (expected_identity, identity_bump) = find_sybil_identity_pda(&attestation.identity, global_escrow_key, program_id);
+    let identity_account = find_account_by_key(accounts, &expected_identity).ok_or_else(|| {
+        msg!("Missing sybil identity account: {}", expected_identity);
+        ProgramError::NotEnoughAccountKeys
+    })?;
+
+    if identity_account.owner == program_id {
+        let identity = SybilIdentity::try_from_slice(&identity_account.data.borrow())?;
+        return Ok((identity_account, identity));
+    }
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            identity_account.key,
+            rent.minimum_balance(SybilIdentity::LEN),
+            SybilIdentity::LEN as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            identity_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"sybil_identity",
+            attestation.identity.as_ref(),
+            global_escrow_key.as_ref(),
+            &[identity_bump],
+        ]],
+    )?;
+
+    let identity = SybilIdentity {
+        is_initialized: true,
+        global_escrow_pubkey: *global_escrow_key,
+        identity: attestation.identity,
+        total_sol_deposited: 0,
+        bump_seed: identity_bump,
+    };
+    Ok((identity_account, identity))
+}
+
+/// Check that the instruction right before the current one is an Ed25519 program
+/// verification of `message` signed by `signer`, with all data inline in that instruction.
+pub fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> ProgramResult {
+    if instructions_sysvar.key != &sysvar::instructions::id() {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let current_index = sysvar::instructions::load_current_index_checked(instructions_sysvar)?;
+    if current_index == 0 {
+        msg!("Missing Ed25519 verification instruction");
+        return Err(EscrowError::InvalidPermit.into());
+    }
+
+    let ed25519_ix = sysvar::instructions::load_instruction_at_checked(current_index as usize - 1, instructions_sysvar)?;
+    if ed25519_ix.program_id != ed25519_program::id() {
+        msg!("Previous instruction is not an Ed25519 verification");
+        return Err(EscrowError::InvalidPermit.into());
+    }
+
+    let (signed_key, signed_message) = ed25519_signed_payload(&ed25519_ix.data).ok_or(EscrowError::InvalidPermit)?;
+    if signed_key != signer.as_ref() || signed_message != message {
+        msg!("Ed25519 signature does not match the expected signer or message");
+        return Err(EscrowError::InvalidPermit.into());
+    }
+
+    Ok(())
+}
+
+pub fn process_fund_permit_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    lamports: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let permit_vault_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || !global_escrow.allow_permit_deposits {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_vault, vault_bump) = find_permit_vault_pda(investor.key, global_escrow_account.key, program_id);
+    if permit_vault_account.key != &expected_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Create permit vault if it doesn't exist
+    if permit_vault_account.owner != program_id {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                investor.key,
+                permit_vault_account.key,
+                rent.minimum_balance(PermitVault::LEN),
+                PermitVault::LEN as u64,
+                program_id,
+            ),
+            &[
+                investor.clone(),
+                permit_vault_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"permit_vault",
+                investor.key.as_ref(),
+                global_escrow_account.key.as_ref(),
+                &[vault_bump],
+            ]],
+        )?;
+
+        let vault = PermitVault {
+            is_initialized: true,
+            investor_pubkey: *investor.key,
+            global_escrow_pubkey: *global_escrow_account.key,
+            next_nonce: 0,
+            bump_seed: vault_bump,
+        };
+        vault.serialize(&mut &mut permit_vault_account.data.borrow_mut()[..])?;
+    }
+
+    invoke(
+        &system_instruction::transfer(investor.key, permit_vault_account.key, lamports),
+        &[
+            investor.clone(),
+            permit_vault_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+
+    msg!("Permit vault funded with {} lamports", lamports);
+
+    Ok(())
+}
+
+pub fn process_withdraw_permit_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    lamports: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let permit_vault_account = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (expected_vault, _) = find_permit_vault_pda(investor.key, global_escrow_account.key, program_id);
+    if permit_vault_account.key != &expected_vault || permit_vault_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // SAFETY: Ensure permit vault remains rent-exempt after withdrawal
+    let min_rent_balance = Rent::get()?.minimum_balance(PermitVault::LEN);
+    let available = permit_vault_account.lamports().saturating_sub(min_rent_balance);
+    if lamports == 0 || lamports > available {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    **permit_vault_account.try_borrow_mut_lamports()? -= lamports;
+    **investor.try_borrow_mut_lamports()? += lamports;
+
+    msg!("Withdrew {} lamports from permit vault", lamports);
+
+    Ok(())
+}
+
+pub fn process_deposit_with_permit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    permit: DepositPermit,
+) -> ProgramResult {
+    let relayer = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let global_escrow_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let system_program = accounts.get(10).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let investor = accounts.get(15).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let permit_vault_account = accounts.get(16).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let instructions_sysvar = accounts.get(17).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    if !relayer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || !global_escrow.allow_permit_deposits {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // The permit must be for this escrow and this identity, and still live
+    if permit.global_escrow != *global_escrow_account.key || permit.investor != *investor.key {
+        return Err(EscrowError::InvalidPermit.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp > permit.expiry {
+        msg!("Permit expired at {}, now {}", permit.expiry, current_timestamp);
+        return Err(EscrowError::PermitExpired.into());
+    }
+
+    let (expected_vault, _) = find_permit_vault_pda(investor.key, global_escrow_account.key, program_id);
+    if permit_vault_account.key != &expected_vault || permit_vault_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut vault = PermitVault::try_from_slice(&permit_vault_account.data.borrow())?;
+    if permit.nonce != vault.next_nonce {
+        msg!("Permit nonce {} already used or out of order (next: {})", permit.nonce, vault.next_nonce);
+        return Err(EscrowError::InvalidPermit.into());
+    }
+
+    verify_ed25519_signature(instructions_sysvar, investor.key, &permit.signed_message()?)?;
+
+    // Consume the nonce before any funds move
+    vault.next_nonce = vault.next_nonce.checked_add(1).ok_or(EscrowError::AmountOverflow)?;
+    vault.serialize(&mut &mut permit_vault_account.data.borrow_mut()[..])?;
+
+    // FUNDING: the investor's vault fronts the SOL to the relayer for the duration of the
+    // deposit; whatever the deposit doesn't take goes straight back to the vault
+    let min_rent_balance = Rent::get()?.minimum_balance(PermitVault::LEN);
+    if permit_vault_account.lamports().saturating_sub(min_rent_balance) < permit.sol_amount {
+        msg!("Permit vault can't cover {} lamports", permit.sol_amount);
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+    **permit_vault_account.try_borrow_mut_lamports()? -= permit.sol_amount;
+    **relayer.try_borrow_mut_lamports()? += permit.sol_amount;
+
+    process_deposit_sol(program_id, accounts, permit.sol_amount, 0, 0, false, &[], 1, DepositMode::Permit, false)?;
+
+    let (_, receipt_data) = get_return_data().ok_or(EscrowError::InvalidInstruction)?;
+    let receipt = DepositReceipt::try_from_slice(&receipt_data)?;
+    let unspent = permit
+        .sol_amount
+        .checked_sub(receipt.this_deposit_sol)
+        .ok_or(EscrowError::AmountOverflow)?;
+    if unspent > 0 {
+        invoke(
+            &system_instruction::transfer(relayer.key, permit_vault_account.key, unspent),
+            &[
+                relayer.clone(),
+                permit_vault_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    msg!("Permit #{} deposit for {}: {} lamports", permit.nonce, investor.key, receipt.this_deposit_sol);
+
+    Ok(())
+}
+
+/// Add a deposit to the analytics histogram. The analytics account is located by key.
+pub fn record_deposit_analytics(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    global_escrow_account: &AccountInfo,
+    sol_amount: u64,
+) -> ProgramResult {
+    let (expected_analytics, _) = find_analytics_pda(global_escrow_account.key, program_id);
+    let analytics_account = find_account_by_key(accounts, &expected_analytics).ok_or_else(|| {
+        msg!("Missing analytics account: {}", expected_analytics);
+        ProgramError::NotEnoughAccountKeys
+    })?;
+
+    if analytics_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut analytics = DepositAnalytics::try_from_slice(&analytics_account.data.borrow())?;
+    analytics.record(sol_amount)?;
+    analytics.serialize(&mut &mut analytics_account.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+pub fn process_create_commitment(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    total_lamports: u64,
+    installment_lamports: u64,
+    installment_interval: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let commitment_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || !global_escrow.installments_enabled {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Whole installments only, each a valid deposit, the total within the per-investor limit
+    if installment_interval <= 0 || installment_lamports == 0 || installment_lamports < global_escrow.min_sol_investment {
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+    let installment_count = total_lamports / installment_lamports;
+    if installment_count == 0 || installment_count * installment_lamports != total_lamports {
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+    if total_lamports > global_escrow.max_sol_investment {
+        return Err(EscrowError::InvestmentExceedsMaximum.into());
+    }
+
+    // The last installment must fall due before the sale ends
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let last_due = installment_interval
+        .checked_mul(installment_count as i64 - 1)
+        .and_then(|offset| current_timestamp.checked_add(offset))
+        .ok_or(EscrowError::AmountOverflow)?;
+    if last_due >= global_escrow.sale_end_timestamp {
+        msg!("Last installment due at {} is after sale end {}", last_due, global_escrow.sale_end_timestamp);
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    let (expected_commitment, commitment_bump) = find_commitment_pda(investor.key, global_escrow_account.key, program_id);
+    if commitment_account.key != &expected_commitment {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if commitment_account.owner == program_id {
+        msg!("Investor {} already has an open commitment", investor.key);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let penalty_lamports = checked_mul_div(total_lamports, global_escrow.installment_penalty_bps as u64, BPS_DENOMINATOR)?;
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            investor.key,
+            commitment_account.key,
+            rent.minimum_balance(Commitment::LEN)
+                .checked_add(penalty_lamports)
+                .ok_or(EscrowError::AmountOverflow)?,
+            Commitment::LEN as u64,
+            program_id,
+        ),
+        &[
+            investor.clone(),
+            commitment_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"commitment",
+            investor.key.as_ref(),
+            global_escrow_account.key.as_ref(),
+            &[commitment_bump],
+        ]],
+    )?;
+
+    let commitment = Commitment {
+        is_initialized: true,
+        investor_pubkey: *investor.key,
+        global_escrow_pubkey: *global_escrow_account.key,
+        total_lamports,
+        installment_lamports,
+        installment_interval,
+        start_timestamp: current_timestamp,
+        paid_lamports: 0,
+        installments_paid: 0,
+        penalty_lamports,
+        bump_seed: commitment_bump,
+    };
+    commitment.serialize(&mut &mut commitment_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Commitment opened: {} lamports in {} installments every {}s, {} lamports collateral",
+        total_lamports,
+        installment_count,
+        installment_interval,
+        penalty_lamports
+    );
+
+    Ok(())
+}
+
+pub fn process_pay_installment(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+
+    let (expected_commitment, _) = find_commitment_pda(investor.key, global_escrow_account.key, program_id);
+    let commitment_account = find_account_by_key(accounts, &expected_commitment).ok_or_else(|| {
+        msg!("Missing commitment account: {}", expected_commitment);
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    if commitment_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut commitment = Commitment::try_from_slice(&commitment_account.data.borrow())?;
+    if !commitment.is_initialized || commitment.is_complete() {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if commitment.is_missed(global_escrow.installment_grace_period, current_timestamp) {
+        msg!(
+            "Installment {} was due at {} (grace {}s)",
+            commitment.installments_paid + 1,
+            commitment.next_due_timestamp(),
+            global_escrow.installment_grace_period
+        );
+        return Err(EscrowError::InstallmentMissed.into());
+    }
+
+    let deposited_before = if investor_account.owner == program_id && investor_account.data_len() == InvestorAccount::LEN {
+        InvestorAccount::load(&investor_account.data.borrow())?.sol_deposited
+    } else {
+        0
+    };
+
+    // The deposit verifies the investor's signature and every deposit account
+    let installment = commitment.installment_lamports.min(commitment.total_lamports - commitment.paid_lamports);
+    process_deposit_sol(program_id, accounts, installment, 0, 0, false, &[], 1, DepositMode::Direct, false)?;
+
+    // Count what the deposit actually took (quantization or a partial fill may take less)
+    let deposited_after = InvestorAccount::load(&investor_account.data.borrow())?.sol_deposited;
+    commitment.paid_lamports = commitment
+        .paid_lamports
+        .checked_add(deposited_after - deposited_before)
+        .ok_or(EscrowError::AmountOverflow)?;
+    commitment.installments_paid = commitment
+        .installments_paid
+        .checked_add(1)
+        .ok_or(EscrowError::AmountOverflow)?;
+
+    if commitment.is_complete() {
+        // Commitment fulfilled: collateral and rent go back to the investor
+        let lamports = commitment_account.lamports();
+        commitment_account.data.borrow_mut().fill(0);
+        **commitment_account.try_borrow_mut_lamports()? = 0;
+        **investor.try_borrow_mut_lamports()? += lamports;
+        msg!("Commitment complete: {} lamports paid, {} lamports returned", commitment.paid_lamports, lamports);
+    } else {
+        commitment.serialize(&mut &mut commitment_account.data.borrow_mut()[..])?;
+        msg!(
+            "Installment {} paid: {} of {} lamports, next due at {}",
+            commitment.installments_paid,
+            commitment.paid_lamports,
+            commitment.total_lamports,
+            commitment.next_due_timestamp()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn process_forfeit_commitment(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let caller = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let commitment_account = next_account_info(account_info_iter)?;
+    let recipient_wallet = next_account_info(account_info_iter)?;
+    let investor_wallet = next_account_info(account_info_iter)?;
+
+    if !caller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id || commitment_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if recipient_wallet.key != &global_escrow.recipient_wallet {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let commitment = Commitment::try_from_slice(&commitment_account.data.borrow())?;
+    if !commitment.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_commitment, _) = find_commitment_pda(&commitment.investor_pubkey, global_escrow_account.key, program_id);
+    if commitment_account.key != &expected_commitment || commitment.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+    if investor_wallet.key != &commitment.investor_pubkey {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if !commitment.is_missed(global_escrow.installment_grace_period, current_timestamp) {
+        msg!(
+            "Commitment in good standing: next installment due at {} (grace {}s)",
+            commitment.next_due_timestamp(),
+            global_escrow.installment_grace_period
+        );
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let lamports = commitment_account.lamports();
+    let penalty = commitment.penalty_lamports.min(lamports);
+    commitment_account.data.borrow_mut().fill(0);
+    **commitment_account.try_borrow_mut_lamports()? = 0;
+    **recipient_wallet.try_borrow_mut_lamports()? += penalty;
+    **investor_wallet.try_borrow_mut_lamports()? += lamports - penalty;
+
+    msg!(
+        "Commitment of {} forfeited after {} of {} lamports: {} lamports penalty to recipient",
+        commitment.investor_pubkey,
+        commitment.paid_lamports,
+        commitment.total_lamports,
+        penalty
+    );
+
+    Ok(())
+}
+
+pub fn process_commit_deposit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    sol_amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let deferred_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized || !global_escrow.deferred_pricing || global_escrow.is_paused {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let clock = Clock::get()?;
+    global_escrow.check_sale_open(clock.unix_timestamp)?;
+
+    // Amount limits are enforced again at reveal, against the position at that time
+    if sol_amount < global_escrow.min_sol_investment {
+        return Err(EscrowError::InsufficientSolDeposit.into());
+    }
+    if sol_amount > global_escrow.max_sol_investment {
+        return Err(EscrowError::InvestmentExceedsMaximum.into());
+    }
+
+    let (expected_deferred, deferred_bump) = find_deferred_deposit_pda(investor.key, global_escrow_account.key, program_id);
+    if deferred_account.key != &expected_deferred {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if deferred_account.owner == program_id {
+        msg!("Investor {} already has an unrevealed commit", investor.key);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            investor.key,
+            deferred_account.key,
+            rent.minimum_balance(DeferredDeposit::LEN)
+                .checked_add(sol_amount)
+                .ok_or(EscrowError::AmountOverflow)?,
+            DeferredDeposit::LEN as u64,
+            program_id,
+        ),
+        &[
+            investor.clone(),
+            deferred_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"deferred_deposit",
+            investor.key.as_ref(),
+            global_escrow_account.key.as_ref(),
+            &[deferred_bump],
+        ]],
+    )?;
+
+    // A reveal after sale end can't deposit, so the commit becomes reclaimable by then
+    let reveal_deadline = clock
+        .unix_timestamp
+        .checked_add(global_escrow.reveal_window)
+        .ok_or(EscrowError::AmountOverflow)?
+        .min(global_escrow.sale_end_timestamp.saturating_sub(1));
+    let deferred = DeferredDeposit {
+        is_initialized: true,
+        investor_pubkey: *investor.key,
+        global_escrow_pubkey: *global_escrow_account.key,
+        sol_amount,
+        commit_slot: clock.slot,
+        commit_timestamp: clock.unix_timestamp,
+        reveal_deadline,
+        bump_seed: deferred_bump,
+    };
+    deferred.serialize(&mut &mut deferred_account.data.borrow_mut()[..])?;
+
+    msg!("Committed {} lamports at slot {}, reveal by {}", sol_amount, clock.slot, reveal_deadline);
+
+    Ok(())
+}
+
+pub fn process_reveal_price(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let investor = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let global_escrow_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let deferred_account = accounts.get(15).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (expected_deferred, _) = find_deferred_deposit_pda(investor.key, global_escrow_account.key, program_id);
+    if deferred_account.key != &expected_deferred || deferred_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let deferred = DeferredDeposit::try_from_slice(&deferred_account.data.borrow())?;
+    if !deferred.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // The price must come from a slot that didn't exist yet when the SOL was committed
+    let clock = Clock::get()?;
+    if clock.slot <= deferred.commit_slot {
+        msg!("Reveal must land after commit slot {}", deferred.commit_slot);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+    if clock.unix_timestamp > deferred.reveal_deadline {
+        msg!("Reveal window closed at {}, use ReclaimExpiredCommit", deferred.reveal_deadline);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Close the commit first: the SOL is handed to the investor, who pays the deposit with it
+    let lamports = deferred_account.lamports();
+    deferred_account.data.borrow_mut().fill(0);
+    **deferred_account.try_borrow_mut_lamports()? = 0;
+    **investor.try_borrow_mut_lamports()? += lamports;
+
+    process_deposit_sol(program_id, accounts, deferred.sol_amount, 0, 0, false, &[], 1, DepositMode::Deferred, false)?;
+
+    msg!(
+        "Revealed commit of {} lamports from slot {} at slot {}",
+        deferred.sol_amount,
+        deferred.commit_slot,
+        clock.slot
+    );
+
+    Ok(())
+}
+
+pub fn process_reclaim_expired_commit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let caller = next_account_info(account_info_iter)?;
+    let deferred_account = next_account_info(account_info_iter)?;
+    let investor_wallet = next_account_info(account_info_iter)?;
+
+    if !caller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if deferred_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let deferred = DeferredDeposit::try_from_slice(&deferred_account.data.borrow())?;
+    if !deferred.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_deferred, _) = find_deferred_deposit_pda(&deferred.investor_pubkey, &deferred.global_escrow_pubkey, program_id);
+    if deferred_account.key != &expected_deferred {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // SECURITY: The committed SOL only ever goes back to the investor who committed it
+    if investor_wallet.key != &deferred.investor_pubkey {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp <= deferred.reveal_deadline {
+        msg!("Commit can still be revealed until {}", deferred.reveal_deadline);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let lamports = deferred_account.lamports();
+    deferred_account.data.borrow_mut().fill(0);
+    **deferred_account.try_borrow_mut_lamports()? = 0;
+    **investor_wallet.try_borrow_mut_lamports()? += lamports;
+
+    msg!("Expired commit of {} lamports refunded to {}", deferred.sol_amount, investor_wallet.key);
+
+    Ok(())
+}
+
+pub fn process_deposit_token(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    token_amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let investor_payment_account = next_account_info(account_info_iter)?;
+    let recipient_payment_account = next_account_info(account_info_iter)?;
+    let payment_vault_account = next_account_info(account_info_iter)?;
+    let payment_mint = next_account_info(account_info_iter)?;
+    let payment_token_program = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let investor_token_account = next_account_info(account_info_iter)?;
+    let token_mint_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let _clock = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    if global_escrow.payment_mint == Pubkey::default() || payment_mint.key != &global_escrow.payment_mint {
+        msg!("Stablecoin deposits are not enabled for mint {}", payment_mint.key);
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    if global_escrow.is_paused {
+        msg!("Deposits are paused");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    global_escrow.check_sale_open(Clock::get()?.unix_timestamp)?;
+
+    // Modes priced in SOL, delivered outside the vault or refunded in SOL have no stablecoin path
+    if global_escrow.lottery_enabled
+        || global_escrow.use_curve
+        || global_escrow.issues_claim_tickets()
+        || global_escrow.stakes_on_deposit()
+        || global_escrow.soft_cap_lamports > 0
+    {
+        msg!("Stablecoin deposits are not supported in this sale mode");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    if global_escrow.forbid_self_dealing
+        && (investor.key == &global_escrow.initializer_pubkey
+            || global_escrow.is_authority(investor.key)
+            || investor.key == &global_escrow.recipient_wallet)
+    {
+        msg!("Self-dealing deposit rejected for {}", investor.key);
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if global_escrow.whitelist_required {
+        verify_whitelisted(program_id, accounts, investor.key, global_escrow_account.key)?;
+    }
+    if global_escrow.whitelist_root != [0u8; 32] {
+        msg!("Merkle-whitelisted sales only take DepositSol");
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if *token_program.key != global_escrow.token_program_id()
+        || !is_token_program(payment_token_program.key)
+        || system_program.key != &solana_program::system_program::id()
+    {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if payment_mint.owner != payment_token_program.key || token_mint_account.key != &global_escrow.token_mint_pubkey {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    // The recipient's half lands directly in its own stablecoin account
+    let recipient_payment_data = read_token_account(recipient_payment_account)?;
+    if recipient_payment_data.owner != global_escrow.recipient_wallet || recipient_payment_data.mint != *payment_mint.key {
+        msg!("Recipient payment account must be a {} account of the recipient wallet", payment_mint.key);
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    let token_account_data = read_token_account(investor_token_account)?;
+    if token_account_data.owner != *investor.key || token_account_data.mint != global_escrow.token_mint_pubkey {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    let (expected_investor_account, investor_bump) = find_investor_pda(
+        investor.key,
+        global_escrow_account.key,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_account {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_payment_vault, payment_vault_bump) = find_payment_vault_pda(global_escrow_account.key, program_id);
+    if payment_vault_account.key != &expected_payment_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if token_amount == 0 {
+        return Err(EscrowError::InvestmentBelowMinimum.into());
+    }
+
+    // 1 stablecoin unit = 1 USD, so the token price applies directly
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let price_cents = global_escrow
+        .dutch_auction_price_cents(current_timestamp)
+        .unwrap_or_else(|| global_escrow.token_price_cents_at(global_escrow.tokens_sold));
+    let payment_decimals = read_mint_decimals(payment_mint)?;
+    let tokens_to_receive = calculate_tokens_for_stablecoin(
+        token_amount,
+        payment_decimals,
+        price_cents,
+        global_escrow.token_decimals,
+    )?;
+    if tokens_to_receive == 0 {
+        return Err(EscrowError::InvestmentBelowMinimum.into());
+    }
+
+    let tokens_remaining = global_escrow
+        .total_tokens_available
+        .checked_sub(global_escrow.tokens_sold)
+        .ok_or(EscrowError::AmountOverflow)?;
+    if tokens_to_receive > tokens_remaining {
+        return Err(EscrowError::NotEnoughTokens.into());
+    }
+
+    global_escrow.check_sale_caps(tokens_to_receive)?;
+
+    let bucket_allowance = global_escrow.bucket_allowance(current_timestamp);
+    if tokens_to_receive > bucket_allowance {
+        msg!("Drip bucket allowance exceeded: {} > {}", tokens_to_receive, bucket_allowance);
+        return Err(EscrowError::BucketLimitReached.into());
+    }
+
+    let tokens_delivered = net_tokens_after_transfer_fee(token_mint_account, tokens_to_receive, Clock::get()?.epoch)?;
+    let (immediate_tokens, tokens_vesting) = global_escrow.split_vesting(tokens_to_receive)?;
+
+    // Create or update investor account
+    let is_top_up = investor_account.owner == program_id && investor_account.data_len() == InvestorAccount::LEN;
+    let mut investor_data = if !is_top_up {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                investor.key,
+                investor_account.key,
+                rent.minimum_balance(InvestorAccount::LEN),
+                InvestorAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                investor.clone(),
+                investor_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"investor",
+                investor.key.as_ref(),
+                global_escrow_account.key.as_ref(),
+                &[investor_bump],
+            ]],
+        )?;
+
+        InvestorAccount {
+            discriminator: INVESTOR_ACCOUNT_DISCRIMINATOR,
+            is_initialized: true,
+            investor_pubkey: *investor.key,
+            global_escrow_pubkey: *global_escrow_account.key,
+            sol_deposited: 0,
+            tokens_received: tokens_delivered,
+            deposit_timestamp: current_timestamp,
+            sol_usd_price: 0,
+            status: InvestorStatus::Deposited,
+            bump_seed: investor_bump,
+            deposit_count: 0,
+            last_deposit_timestamp: 0,
+            held_immediate_sol: 0,
+            refund_registered: false,
+            refund_owed: 0,
+            refund_queue_position: 0,
+            custom_lock_duration: 0,
+            lottery_entry: 0,
+            lottery_claimed: false,
+            tokens_vesting,
+            tokens_claimed: 0,
+            avg_sol_usd_price: 0,
+            price_round_id: 0,
+            beneficiary: Pubkey::default(),
+            payment_mint: *payment_mint.key,
+            payment_deposited: token_amount,
+            vesting_start: current_timestamp,
+        }
+    } else {
+        let mut existing_data = InvestorAccount::load(&investor_account.data.borrow())?;
+        
+        // Pre-provisioned and refunded accounts start over on this deposit
+        existing_data.reopen_for_deposit(current_timestamp)?;
+        
+        existing_data.payment_mint = *payment_mint.key;
+        existing_data.payment_deposited = existing_data
+            .payment_deposited
+            .checked_add(token_amount)
+            .ok_or(EscrowError::AmountOverflow)?;
+        existing_data.tokens_received = existing_data
+            .tokens_received
+            .checked_add(tokens_delivered)
+            .ok_or(EscrowError::AmountOverflow)?;
+        existing_data.add_vesting(tokens_vesting, current_timestamp)?;
+        existing_data
+    };
+
+    // Create the payment vault on first use (authority: global escrow PDA)
+    if !is_token_account(payment_vault_account, payment_token_program.key) {
+        let vault_size = token_account_size(payment_token_program, payment_mint)?;
+        invoke_signed(
+            &system_instruction::create_account(
+                investor.key,
+                payment_vault_account.key,
+                Rent::get()?.minimum_balance(vault_size),
+                vault_size as u64,
+                payment_token_program.key,
+            ),
+            &[
+                investor.clone(),
+                payment_vault_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"payment_vault",
+                global_escrow_account.key.as_ref(),
+                &[payment_vault_bump],
+            ]],
+        )?;
+        
+        invoke(
+            &for_token_program(
+                spl_instruction::initialize_account3(
+                    &spl_token::id(),
+                    payment_vault_account.key,
+                    payment_mint.key,
+                    global_escrow_account.key,
+                )?,
+                payment_token_program.key,
+            ),
+            &[
+                payment_vault_account.clone(),
+                payment_mint.clone(),
+                global_escrow_account.clone(),
+                payment_token_program.clone(),
+            ],
+        )?;
+    }
+
+    // Split the payment: 50% to recipient, 50% to the payment vault for locking
+    let payment_immediate = token_amount / 2;
+    let payment_to_lock = token_amount - payment_immediate;
+
+    transfer_tokens_checked(
+        payment_token_program,
+        investor_payment_account,
+        payment_mint,
+        recipient_payment_account,
+        investor,
+        payment_immediate,
+        payment_decimals,
+        &[],
+    )?;
+    transfer_tokens_checked(
+        payment_token_program,
+        investor_payment_account,
+        payment_mint,
+        payment_vault_account,
+        investor,
+        payment_to_lock,
+        payment_decimals,
+        &[],
+    )?;
+
+    if global_escrow.check_vault_on_deposit {
+        verify_token_vault(program_id, global_escrow_account, token_vault_account, &global_escrow)?;
+    }
+
+    if immediate_tokens > 0 {
+        transfer_escrow_tokens(
+            &global_escrow,
+            accounts,
+            token_program,
+            token_vault_account,
+            investor_token_account,
+            global_escrow_account,
+            immediate_tokens,
+            &[&global_escrow.signer_seeds(&global_escrow.sale_id.to_le_bytes(), &[global_escrow.bump_seed])],
+        )?;
+    }
+
+    // Update state only after all transfers succeed
+    let usd_cents = checked_mul_div(token_amount, USD_CENTS_SCALE, 10_u64.pow(payment_decimals as u32))?;
+    global_escrow.tokens_sold = global_escrow
+        .tokens_sold
+        .checked_add(tokens_to_receive)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.round_tokens_sold = global_escrow
+        .round_tokens_sold
+        .checked_add(tokens_to_receive)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.record_bucket_sale(tokens_to_receive, current_timestamp)?;
+    global_escrow.vesting_tokens_outstanding = global_escrow
+        .vesting_tokens_outstanding
+        .checked_add(tokens_vesting)
+        .ok_or(EscrowError::AmountOverflow)?;
+    if !is_top_up {
+        global_escrow.open_investor_accounts = global_escrow
+            .open_investor_accounts
+            .checked_add(1)
+            .ok_or(EscrowError::AmountOverflow)?;
+    }
+    global_escrow.total_usd_value_cents = global_escrow
+        .total_usd_value_cents
+        .checked_add(usd_cents)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.usd_valued_tokens = global_escrow
+        .usd_valued_tokens
+        .checked_add(tokens_to_receive)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_payment_deposited = global_escrow
+        .total_payment_deposited
+        .checked_add(token_amount)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_payment_locked = global_escrow
+        .total_payment_locked
+        .checked_add(payment_to_lock)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    investor_data.deposit_count = investor_data
+        .deposit_count
+        .checked_add(1)
+        .ok_or(EscrowError::AmountOverflow)?;
+    investor_data.last_deposit_timestamp = current_timestamp;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Stablecoin deposited: {} units of {}, tokens received: {}, price: {} cents",
+        token_amount,
+        payment_mint.key,
+        tokens_delivered,
+        price_cents
+    );
+    EscrowEvent::TokenDeposited {
+        global_escrow: *global_escrow_account.key,
+        investor: *investor.key,
+        payment_mint: *payment_mint.key,
+        payment_amount: token_amount,
+        tokens: tokens_delivered,
+        price_cents,
+        timestamp: current_timestamp,
+    }
+    .emit()?;
+
+    Ok(())
+}
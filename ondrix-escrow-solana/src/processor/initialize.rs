@@ -0,0 +1,1217 @@
+// Escrow and investor account lifecycle: initialize, migrate, batch create and close
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_token::instruction as spl_instruction;
+use borsh::BorshSerialize;
+use chainlink_solana::{
+    decimals as chainlink_decimals,
+};
+
+use crate::{
+    find_global_escrow_pda, find_investor_pda, find_sol_vault_pda, find_token_vault_pda,
+    for_token_program, global_escrow_seeds, is_token_account, is_token_program, read_mint_decimals,
+    read_token_account, token_account_size, transfer_tokens_checked, CurveType, EscrowError,
+    EscrowEvent, GlobalEscrow, InitializeEscrowParams, InvestorAccount, InvestorStatus, OracleKind,
+    PriceTier, QuoteFeed, UnlockMode, BPS_DENOMINATOR, CHAINLINK_PROGRAM_ID, CHAINLINK_USD_DECIMALS,
+    DEFAULT_INACTIVITY_REFUND_DELAY, DISCRIMINATOR_LEN, GLOBAL_ESCROW_DISCRIMINATOR,
+    INVESTOR_ACCOUNT_DISCRIMINATOR, MAX_BATCH_CLOSE_ACCOUNTS, MAX_BATCH_INVESTORS, MAX_ORACLE_FEEDS,
+    MAX_PRICE_TIERS, MAX_QUOTE_FEEDS, PYTH_PROGRAM_ID, PYTH_SOL_USD_FEED, SOL_USD_FEED,
+};
+
+pub fn process_initialize_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    params: InitializeEscrowParams,
+) -> ProgramResult {
+    let InitializeEscrowParams {
+        token_amount,
+        lock_duration,
+        sale_end_timestamp,
+        min_sol_investment,
+        max_sol_investment,
+        price_staleness_threshold,
+        reserved_tokens,
+        oracle_kind,
+        token_price_cents,
+        cliff_duration,
+        sale_id,
+        min_sol_usd_price,
+        max_sol_usd_price,
+        sol_hard_cap,
+    } = params;
+
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let token_source_account = next_account_info(account_info_iter)?;
+    let recipient_wallet = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let _associated_token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let rent = &Rent::get()?;
+    let oracle_program = next_account_info(account_info_iter)?;
+    let price_feed = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // ORACLE IMMUTABILITY
+    // Validate oracle program and feed match expected values before storing immutably
+    let (expected_oracle_program, expected_price_feed) = match oracle_kind {
+        OracleKind::Chainlink => (CHAINLINK_PROGRAM_ID, SOL_USD_FEED),
+        OracleKind::Pyth => (PYTH_PROGRAM_ID, PYTH_SOL_USD_FEED),
+    };
+    if oracle_program.key != &expected_oracle_program {
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    if price_feed.key != &expected_price_feed {
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    // Pyth price accounts are plain data accounts, so also check who wrote them
+    if oracle_kind == OracleKind::Pyth && price_feed.owner != oracle_program.key {
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+    
+    // FEED DECIMALS: Chainlink feeds report in their own decimals, cached here so every
+    // later read can check the feed hasn't changed them. Pyth is rescaled from its exponent.
+    let price_feed_decimals = match oracle_kind {
+        OracleKind::Chainlink => chainlink_decimals(oracle_program.clone(), price_feed.clone())
+            .map_err(|_| EscrowError::InvalidPriceFeed)?,
+        OracleKind::Pyth => CHAINLINK_USD_DECIMALS,
+    };
+    
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Verify PDA addresses
+    let (expected_global_escrow, bump_seed) = find_global_escrow_pda(
+        initializer.key,
+        token_mint.key,
+        sale_id,
+        program_id,
+    );
+    
+    if global_escrow_account.key != &expected_global_escrow {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_token_vault, vault_bump) = find_token_vault_pda(
+        &expected_global_escrow,
+        program_id,
+    );
+    
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Prices are per whole token, so token amounts scale with the mint's decimals
+    if token_mint.owner != token_program.key {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+    let token_decimals = read_mint_decimals(token_mint)?;
+
+    // SECURITY: Verify the source can fund the vault BEFORE creating any accounts,
+    // so an under-funded init fails without leaving a half-initialized escrow
+    let source_data = read_token_account(token_source_account)?;
+    if source_data.mint != *token_mint.key {
+        msg!("Invalid source token mint. Expected: {}, Found: {}", token_mint.key, source_data.mint);
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+    
+    if source_data.amount < token_amount {
+        msg!("Insufficient source token balance. Required: {}, Available: {}", token_amount, source_data.amount);
+        return Err(EscrowError::NotEnoughTokens.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    params.validate(current_timestamp)?;
+
+    // Check if already initialized
+    if global_escrow_account.data_len() > 0 {
+        let escrow_data = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+        if escrow_data.is_initialized {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+    }
+
+    // Create global escrow account
+    let escrow_size = GlobalEscrow::LEN;
+    let rent_lamports = rent.minimum_balance(escrow_size);
+    
+    let create_escrow_ix = system_instruction::create_account(
+        initializer.key,
+        global_escrow_account.key,
+        rent_lamports,
+        escrow_size as u64,
+        program_id,
+    );
+
+    let sale_id_bytes = sale_id.to_le_bytes();
+    let bump = [bump_seed];
+    let mut escrow_signer_seeds = global_escrow_seeds(initializer.key, token_mint.key, &sale_id_bytes);
+    escrow_signer_seeds.push(&bump);
+    invoke_signed(
+        &create_escrow_ix,
+        &[
+            initializer.clone(),
+            global_escrow_account.clone(),
+            system_program.clone(),
+        ],
+        &[&escrow_signer_seeds],
+    )?;
+
+    // Create token vault if it doesn't exist (owned by global escrow PDA)
+    if !is_token_account(token_vault_account, token_program.key) {
+        let rent = Rent::get()?;
+        let token_account_size = token_account_size(token_program, token_mint)?;
+        let rent_lamports = rent.minimum_balance(token_account_size);
+        
+        // Create token account owned by global escrow PDA
+        let create_vault_ix = system_instruction::create_account(
+            initializer.key,
+            token_vault_account.key,
+            rent_lamports,
+            token_account_size as u64,
+            token_program.key,
+        );
+        
+        invoke_signed(
+            &create_vault_ix,
+            &[
+                initializer.clone(),
+                token_vault_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"token_vault",
+                expected_global_escrow.as_ref(),
+                &[vault_bump],
+            ]],
+        )?;
+        
+        // Initialize token account with global escrow as authority
+        let init_vault_ix = for_token_program(
+            spl_instruction::initialize_account3(
+                &spl_token::id(),
+                token_vault_account.key,
+                token_mint.key,
+                global_escrow_account.key, // Global escrow PDA as authority
+            )?,
+            token_program.key,
+        );
+        
+        invoke(
+            &init_vault_ix,
+            &[
+                token_vault_account.clone(),
+                token_mint.clone(),
+                global_escrow_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+    } else {
+        // SECURITY: a vault pre-created at the derived address must already be ours
+        let vault_data = read_token_account(token_vault_account)?;
+        if vault_data.mint != *token_mint.key || vault_data.owner != *global_escrow_account.key {
+            msg!(
+                "Existing token vault has mint {} and owner {}, expected {} and {}",
+                vault_data.mint,
+                vault_data.owner,
+                token_mint.key,
+                global_escrow_account.key
+            );
+            return Err(EscrowError::InvalidTokenAccount.into());
+        }
+    }
+
+    // TRANSFER FEES: a Token-2022 fee is withheld in the vault, so the sellable pool is
+    // sized from what the vault was actually credited rather than from token_amount
+    let vault_balance_before = read_token_account(token_vault_account)?.amount;
+
+    // Transfer tokens from initializer to token vault (checked, so Token-2022 mints work too)
+    transfer_tokens_checked(
+        token_program,
+        token_source_account,
+        token_mint,
+        token_vault_account,
+        initializer,
+        token_amount,
+        token_decimals,
+        &[],
+    )?;
+
+    let tokens_credited = read_token_account(token_vault_account)?
+        .amount
+        .checked_sub(vault_balance_before)
+        .ok_or(EscrowError::AmountOverflow)?;
+    if tokens_credited != token_amount {
+        msg!("Transfer fee: {} sent, {} credited to the vault", token_amount, tokens_credited);
+    }
+    if reserved_tokens >= tokens_credited {
+        msg!("Reserved tokens {} must be below the {} tokens credited", reserved_tokens, tokens_credited);
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    // Initialize global escrow data
+    let global_escrow = GlobalEscrow {
+        discriminator: GLOBAL_ESCROW_DISCRIMINATOR,
+        is_initialized: true,
+        initializer_pubkey: *initializer.key,
+        token_mint_pubkey: *token_mint.key,
+        recipient_wallet: *recipient_wallet.key,
+        total_tokens_available: tokens_credited - reserved_tokens, // Sellable pool only, net of any transfer fee
+        tokens_sold: 0,
+        total_sol_deposited: 0,
+        total_sol_withdrawn: 0,
+        lock_duration,
+        bump_seed,
+        
+        // IMMUTABLE ORACLE CONFIG
+        oracle_program_id: *oracle_program.key,
+        price_feed_pubkey: *price_feed.key,
+        
+        // IMMUTABLE CONFIG VALUES
+        min_sol_investment,
+        max_sol_investment,
+        price_staleness_threshold,
+        
+        // SALE MANAGEMENT
+        sale_end_timestamp,
+        
+        // GLOBAL TIMING
+        initialization_timestamp: current_timestamp,
+        
+        // TRANSPARENCY
+        audit_log_enabled: false,
+        
+        // UNLOCK CONDITIONS
+        unlock_mode: UnlockMode::Time,
+        unlock_tokens_sold_target: 0,
+        
+        // TIERED PRICING
+        price_tiers: [PriceTier::default(); MAX_PRICE_TIERS],
+        price_tier_count: 0,
+        
+        // INVESTOR PROTECTION
+        inactivity_refund_delay: DEFAULT_INACTIVITY_REFUND_DELAY,
+        
+        // PRICE SMOOTHING
+        use_ema: false,
+        ema_alpha_bps: 0,
+        ema_price: 0,
+        
+        // DEPOSIT PROOFS
+        deposit_proofs_enabled: false,
+        
+        // PROTOCOL FEE
+        fee_wallet: Pubkey::default(),
+        protocol_fee_bps: 0,
+        total_fees_collected: 0,
+        
+        // DELEGATED DEPOSITS
+        allow_delegated_deposits: false,
+        
+        // SALE ROUNDS
+        current_round: 0,
+        round_tokens_cap: 0,
+        round_tokens_sold: 0,
+        max_total_tokens_sold: 0,
+        
+        // RESERVED ALLOCATION
+        reserved_tokens,
+        
+        // MULTI-ORACLE
+        use_multi_oracle: false,
+        oracle_feeds: [Pubkey::default(); MAX_ORACLE_FEEDS],
+        oracle_feed_count: 0,
+        max_feed_disagreement_bps: 0,
+        
+        // DEPOSIT QUANTIZATION
+        deposit_quantum: 0,
+        deposit_quantum_round_down: false,
+        
+        // RECIPIENT FALLBACK
+        recipient_fallback_enabled: false,
+        pending_immediate_sol: 0,
+        
+        // RENT BUDGET
+        max_rent_per_investor: 0,
+        
+        // PARTIAL FILLS
+        allow_partial_fill: false,
+        
+        // CPI DEPTH GUARD
+        max_caller_stack_height: 0,
+        
+        // HARD CAP
+        sol_hard_cap,
+        
+        // SELF-DEALING
+        forbid_self_dealing: false,
+        
+        // SOFT CAP
+        soft_cap_lamports: 0,
+        
+        // DEGRADED ORACLE MODE
+        degraded_mode_enabled: false,
+        degraded_max_price_age: 0,
+        degraded_max_deposit: 0,
+        last_good_price: 0,
+        last_good_price_timestamp: 0,
+        
+        // CUSTODIAN
+        custodian: Pubkey::default(),
+        
+        // STAKING DELIVERY
+        staking_program: Pubkey::default(),
+        staking_pool: Pubkey::default(),
+        staking_deposit_account: Pubkey::default(),
+        
+        // PAUSE / RAISE VELOCITY
+        is_paused: false,
+        velocity_window: 0,
+        min_raise_per_window: 0,
+        velocity_window_start: 0,
+        velocity_window_raised: 0,
+        
+        // PERMIT DEPOSITS
+        allow_permit_deposits: false,
+        
+        // QUOTE ASSET FEEDS
+        quote_feeds: [QuoteFeed::default(); MAX_QUOTE_FEEDS],
+        quote_feed_count: 0,
+        
+        // LIQUIDITY PROVISION
+        amm_program: Pubkey::default(),
+        amm_pool: Pubkey::default(),
+        amm_token_account: Pubkey::default(),
+        liquidity_sol_bps: 0,
+        liquidity_added: false,
+        
+        // CANCELLATION WINDOW
+        cancellation_window: 0,
+        
+        // BONDING CURVE
+        use_curve: false,
+        curve_type: CurveType::Linear,
+        curve_base_price: 0,
+        curve_slope: 0,
+        curve_growth_bps: 0,
+        curve_step: 0,
+        
+        // ORACLE KIND
+        oracle_kind,
+        
+        // REFUND QUEUE
+        refund_queue_enabled: false,
+        total_refunds_owed: 0,
+        refund_queue_tail: 0,
+        refund_queue_funded: 0,
+        
+        // TOKEN DECIMALS
+        token_decimals,
+        
+        // DEPOSIT ANALYTICS
+        analytics_enabled: false,
+        
+        // TOKEN PRICE
+        token_price_cents,
+        
+        // LOTTERY
+        lottery_enabled: false,
+        lottery_entry_lamports: 0,
+        lottery_entry_tokens: 0,
+        lottery_commitment: [0u8; 32],
+        lottery_entry_count: 0,
+        lottery_resolved: false,
+        lottery_seed: [0u8; 32],
+        lottery_winner_count: 0,
+        
+        // DUTCH AUCTION
+        start_price_cents: 0,
+        end_price_cents: 0,
+        
+        // VAULT SELF-CHECK
+        check_vault_on_deposit: false,
+        
+        // VESTING
+        vesting_enabled: false,
+        immediate_token_bps: BPS_DENOMINATOR as u16,
+        
+        // EARLY-BIRD BONUS
+        bonus_threshold_lamports: 0,
+        bonus_bps: 0,
+        
+        // WHITELIST
+        whitelist_required: false,
+        
+        // EMERGENCY FREEZE
+        withdrawals_frozen: false,
+        
+        // MERKLE WHITELIST
+        whitelist_root: [0u8; 32],
+        
+        // VESTING MODE
+        vesting_mode: false,
+        
+        // CLAIM TICKETS
+        claim_ticket_mint: Pubkey::default(),
+        claim_tickets_outstanding: 0,
+        
+        // VESTING CLIFF
+        cliff_duration,
+        
+        // DRIP SALE
+        tokens_per_bucket: 0,
+        bucket_duration: 0,
+        bucket_start: 0,
+        bucket_tokens_sold: 0,
+        
+        // ADMIN AUTHORITY
+        authority: *initializer.key,
+        
+        // INSTALLMENTS
+        installments_enabled: false,
+        installment_grace_period: 0,
+        installment_penalty_bps: 0,
+        
+        // SALE ID
+        sale_id,
+        
+        // SALE ECONOMICS
+        total_usd_value_cents: 0,
+        usd_valued_tokens: 0,
+        
+        // ESCROW CLOSURE
+        investor_accounts_tracked: true,
+        open_investor_accounts: 0,
+        
+        // SYBIL DAMPENING
+        sybil_authority: Pubkey::default(),
+        max_sol_per_identity: 0,
+        
+        // DEFERRED PRICING
+        deferred_pricing: false,
+        reveal_window: 0,
+        
+        // TOKEN PROGRAM
+        token_program_id: *token_program.key,
+        
+        // STABLECOIN DEPOSITS
+        payment_mint: Pubkey::default(),
+        total_payment_deposited: 0,
+        total_payment_locked: 0,
+        total_payment_withdrawn: 0,
+        
+        // CPI DEPOSIT BLOCKING
+        disallow_cpi_deposits: false,
+        
+        // PRICE BAND
+        min_sol_usd_price,
+        max_sol_usd_price,
+        
+        // FEED DECIMALS
+        price_feed_decimals,
+        
+        // DEPOSIT COOLDOWN
+        deposit_cooldown: 0,
+        
+        // SALE CLOSE
+        sale_closed: false,
+        vesting_tokens_outstanding: 0,
+        lottery_tokens_outstanding: 0,
+    };
+
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Global escrow initialized: {} tokens ({} reserved) at {} cents, {}s lock, recipient: {}",
+        token_amount,
+        reserved_tokens,
+        token_price_cents,
+        lock_duration,
+        recipient_wallet.key
+    );
+    
+    EscrowEvent::Initialized {
+        global_escrow: *global_escrow_account.key,
+        initializer: global_escrow.initializer_pubkey,
+        token_mint: global_escrow.token_mint_pubkey,
+        token_amount,
+        token_price_cents,
+        lock_duration,
+        sale_end_timestamp: global_escrow.sale_end_timestamp,
+        timestamp: global_escrow.initialization_timestamp,
+    }
+    .emit()?;
+    
+    Ok(())
+}
+
+pub fn process_batch_init_investors(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    count: u8,
+    create_token_accounts: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let funder = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let associated_token_program = next_account_info(account_info_iter)?;
+
+    if !funder.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if count == 0 || count > MAX_BATCH_INVESTORS {
+        msg!("Invalid batch size: {} (max {})", count, MAX_BATCH_INVESTORS);
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if associated_token_program.key != &spl_associated_token_account::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    if token_mint.key != &global_escrow.token_mint_pubkey {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    // ATAs are derived per token program, so it must be the escrow's
+    if *token_program.key != global_escrow.token_program_id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let rent = Rent::get()?;
+    let mut created = 0u8;
+
+    for _ in 0..count {
+        let investor = next_account_info(account_info_iter)?;
+        let investor_account = next_account_info(account_info_iter)?;
+        let sol_vault_account = next_account_info(account_info_iter)?;
+
+        let (expected_investor_account, investor_bump) = find_investor_pda(
+            investor.key,
+            global_escrow_account.key,
+            program_id,
+        );
+        if investor_account.key != &expected_investor_account {
+            return Err(EscrowError::InvalidPDA.into());
+        }
+
+        let (expected_sol_vault, sol_vault_bump) = find_sol_vault_pda(
+            investor.key,
+            global_escrow_account.key,
+            program_id,
+        );
+        if sol_vault_account.key != &expected_sol_vault {
+            return Err(EscrowError::InvalidPDA.into());
+        }
+
+        // Skip investors that already have an account (idempotent)
+        if investor_account.owner != program_id {
+            let investor_size = InvestorAccount::LEN;
+            invoke_signed(
+                &system_instruction::create_account(
+                    funder.key,
+                    investor_account.key,
+                    rent.minimum_balance(investor_size),
+                    investor_size as u64,
+                    program_id,
+                ),
+                &[
+                    funder.clone(),
+                    investor_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    b"investor",
+                    investor.key.as_ref(),
+                    global_escrow_account.key.as_ref(),
+                    &[investor_bump],
+                ]],
+            )?;
+
+            // Empty position - the first DepositSol sets timestamp and status
+            let investor_data = InvestorAccount {
+                discriminator: INVESTOR_ACCOUNT_DISCRIMINATOR,
+                is_initialized: true,
+                investor_pubkey: *investor.key,
+                global_escrow_pubkey: *global_escrow_account.key,
+                sol_deposited: 0,
+                tokens_received: 0,
+                deposit_timestamp: 0,
+                sol_usd_price: 0,
+                status: InvestorStatus::Uninitialized,
+                bump_seed: investor_bump,
+                deposit_count: 0,
+                last_deposit_timestamp: 0,
+                held_immediate_sol: 0,
+                refund_registered: false,
+                refund_owed: 0,
+                refund_queue_position: 0,
+                custom_lock_duration: 0,
+                lottery_entry: 0,
+                lottery_claimed: false,
+                tokens_vesting: 0,
+                tokens_claimed: 0,
+                avg_sol_usd_price: 0,
+                price_round_id: 0,
+                beneficiary: Pubkey::default(),
+                payment_mint: Pubkey::default(),
+                payment_deposited: 0,
+                vesting_start: 0,
+            };
+            investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+            created += 1;
+        }
+
+        if sol_vault_account.owner != program_id {
+            invoke_signed(
+                &system_instruction::create_account(
+                    funder.key,
+                    sol_vault_account.key,
+                    rent.minimum_balance(0),
+                    0,
+                    program_id,
+                ),
+                &[
+                    funder.clone(),
+                    sol_vault_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    b"sol_vault",
+                    investor.key.as_ref(),
+                    global_escrow_account.key.as_ref(),
+                    &[sol_vault_bump],
+                ]],
+            )?;
+        }
+
+        if create_token_accounts {
+            let investor_token_account = next_account_info(account_info_iter)?;
+            let expected_ata = spl_associated_token_account::get_associated_token_address_with_program_id(
+                investor.key,
+                token_mint.key,
+                token_program.key,
+            );
+            if investor_token_account.key != &expected_ata {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            if investor_token_account.owner != token_program.key {
+                let create_ata_ix = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                    funder.key,        // payer
+                    investor.key,      // owner
+                    token_mint.key,    // mint
+                    token_program.key,
+                );
+
+                invoke(
+                    &create_ata_ix,
+                    &[
+                        funder.clone(),
+                        investor_token_account.clone(),
+                        investor.clone(),
+                        token_mint.clone(),
+                        system_program.clone(),
+                        token_program.clone(),
+                        associated_token_program.clone(),
+                    ],
+                )?;
+            }
+        }
+    }
+
+    if created > 0 {
+        global_escrow.open_investor_accounts = global_escrow
+            .open_investor_accounts
+            .checked_add(created as u64)
+            .ok_or(EscrowError::AmountOverflow)?;
+        global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+    }
+
+    msg!("Batch provisioned {} new investor accounts ({} requested)", created, count);
+
+    Ok(())
+}
+
+pub fn process_batch_close_investor_accounts(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    count: u8,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let caller = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+
+    if !caller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if count == 0 || count > MAX_BATCH_CLOSE_ACCOUNTS {
+        msg!("Invalid batch size: {} (max {})", count, MAX_BATCH_CLOSE_ACCOUNTS);
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Only after the sale ends, otherwise a closed investor could redeposit with a fresh per-address limit
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp < global_escrow.sale_end_timestamp {
+        msg!("Sale has not ended yet. Current: {}, Sale ends: {}", current_timestamp, global_escrow.sale_end_timestamp);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let mut closed = 0u8;
+    let mut reclaimed_lamports: u64 = 0;
+
+    for _ in 0..count {
+        let investor_account = next_account_info(account_info_iter)?;
+        let investor_wallet = next_account_info(account_info_iter)?;
+
+        // Already closed or never created
+        if investor_account.owner != program_id || investor_account.data_len() != InvestorAccount::LEN {
+            continue;
+        }
+
+        let investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+        // SECURITY: Rent can only go back to the investor the account belongs to
+        if investor_data.global_escrow_pubkey != *global_escrow_account.key
+            || investor_data.investor_pubkey != *investor_wallet.key
+        {
+            return Err(EscrowError::InvalidPDA.into());
+        }
+
+        let (expected_investor_pda, _) = find_investor_pda(investor_wallet.key, global_escrow_account.key, program_id);
+        if investor_account.key != &expected_investor_pda {
+            return Err(EscrowError::InvalidPDA.into());
+        }
+
+        if !investor_data.is_terminal() && !investor_data.is_unused() {
+            msg!("Skipping non-terminal investor account: {}", investor_account.key);
+            continue;
+        }
+
+        // Zero data and drain lamports so the runtime reclaims the account
+        let rent_lamports = investor_account.lamports();
+        investor_account.data.borrow_mut().fill(0);
+        **investor_account.try_borrow_mut_lamports()? = 0;
+        **investor_wallet.try_borrow_mut_lamports()? += rent_lamports;
+
+        reclaimed_lamports = reclaimed_lamports
+            .checked_add(rent_lamports)
+            .ok_or(EscrowError::AmountOverflow)?;
+        closed += 1;
+    }
+
+    if closed > 0 {
+        global_escrow.open_investor_accounts = global_escrow.open_investor_accounts.saturating_sub(closed as u64);
+        global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+    }
+
+    msg!("Closed {} of {} investor accounts, {} lamports of rent returned", closed, count, reclaimed_lamports);
+
+    Ok(())
+}
+
+pub fn process_migrate_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let token_mint = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Escrows created before discriminators were introduced start at is_initialized
+    // (never 169, the discriminator's first byte)
+    let old_size = global_escrow_account.data_len();
+    let tagged = global_escrow_account.data.borrow().starts_with(&GLOBAL_ESCROW_DISCRIMINATOR);
+    let max_old_size = if tagged { GlobalEscrow::LEN - 1 } else { GlobalEscrow::LEN - DISCRIMINATOR_LEN };
+    if old_size > max_old_size {
+        msg!("Global escrow at {} bytes has no older layout to migrate from", old_size);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // The old layout can't be decoded as the current struct yet, but its prefix is
+    // unchanged: is_initialized (1 byte) followed by initializer_pubkey
+    let prefix_len = if tagged { DISCRIMINATOR_LEN } else { 0 };
+    {
+        let data = global_escrow_account.data.borrow();
+        if data.len() < prefix_len + 1 + 32 || data[prefix_len] != 1 {
+            return Err(EscrowError::InvalidEscrowStatus.into());
+        }
+    }
+
+    let rent = Rent::get()?;
+    let lamports_needed = rent
+        .minimum_balance(GlobalEscrow::LEN)
+        .saturating_sub(global_escrow_account.lamports());
+
+    if lamports_needed > 0 {
+        invoke(
+            &system_instruction::transfer(initializer.key, global_escrow_account.key, lamports_needed),
+            &[
+                initializer.clone(),
+                global_escrow_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    global_escrow_account.realloc(GlobalEscrow::LEN, true)?;
+
+    // Untagged data moves up behind the discriminator; the zeroed tail has room for it
+    if !tagged {
+        let mut data = global_escrow_account.data.borrow_mut();
+        data.copy_within(..old_size, DISCRIMINATOR_LEN);
+        data[..DISCRIMINATOR_LEN].copy_from_slice(&GLOBAL_ESCROW_DISCRIMINATOR);
+    }
+
+    // Fail the migration rather than leave an account the program can't read
+    // AUTHORIZATION: Only the authority can migrate. The authority may sit in the
+    // resized tail (zeroed for layouts without it), so this runs after the realloc;
+    // a failed check reverts the realloc and the rent transfer with it.
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // Untagged data carries no type, so only the escrow PDA itself may be tagged as one
+    let (expected_escrow_pda, _) = find_global_escrow_pda(
+        &global_escrow.initializer_pubkey,
+        &global_escrow.token_mint_pubkey,
+        global_escrow.sale_id,
+        program_id,
+    );
+    if global_escrow_account.key != &expected_escrow_pda {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // A zero default would misprice every deposit; mint decimals never change
+    if token_mint.key != &global_escrow.token_mint_pubkey {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+    global_escrow.token_decimals = read_mint_decimals(token_mint)?;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    msg!("Global escrow migrated: {} -> {} bytes", old_size, GlobalEscrow::LEN);
+
+    Ok(())
+}
+
+pub fn process_close_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let authority = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only the authority can close the escrow
+    if !global_escrow.is_authority(authority.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp < global_escrow.sale_end_timestamp {
+        msg!("Sale has not ended yet. Current: {}, Sale ends: {}", current_timestamp, global_escrow.sale_end_timestamp);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Every investor PDA reads this account to withdraw or refund, so all of them must be gone
+    if !global_escrow.investor_accounts_tracked {
+        msg!("Investor accounts were not counted for this escrow, it can't be closed safely");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+    if global_escrow.open_investor_accounts > 0 {
+        msg!("{} investor accounts still open", global_escrow.open_investor_accounts);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+    if global_escrow.pending_immediate_sol > 0
+        || global_escrow.total_refunds_owed > 0
+        || global_escrow.claim_tickets_outstanding > 0
+    {
+        msg!(
+            "Outstanding obligations: {} lamports held, {} lamports of refunds owed, {} claim tickets",
+            global_escrow.pending_immediate_sol,
+            global_escrow.total_refunds_owed,
+            global_escrow.claim_tickets_outstanding
+        );
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_token_vault, _) = find_token_vault_pda(global_escrow_account.key, program_id);
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let vault_data = read_token_account(token_vault_account)?;
+    if vault_data.amount > 0 {
+        msg!("Token vault still holds {} tokens, run CloseSale first", vault_data.amount);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    invoke_signed(
+        &for_token_program(
+            spl_instruction::close_account(
+                &spl_token::id(),
+                token_vault_account.key,
+                authority.key,
+                global_escrow_account.key,
+                &[],
+            )?,
+            token_program.key,
+        ),
+        &[
+            token_vault_account.clone(),
+            authority.clone(),
+            global_escrow_account.clone(),
+            token_program.clone(),
+        ],
+        &[&global_escrow.signer_seeds(&global_escrow.sale_id.to_le_bytes(), &[global_escrow.bump_seed])],
+    )?;
+
+    // Zero data and drain lamports so the runtime reclaims the account
+    let rent_lamports = global_escrow_account.lamports();
+    global_escrow_account.data.borrow_mut().fill(0);
+    **global_escrow_account.try_borrow_mut_lamports()? = 0;
+    **authority.try_borrow_mut_lamports()? += rent_lamports;
+
+    msg!("Global escrow closed, {} lamports of escrow rent returned to {}", rent_lamports, authority.key);
+
+    Ok(())
+}
+
+pub fn process_close_sol_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let caller = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+    let investor_wallet = next_account_info(account_info_iter)?;
+
+    if !caller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    let (expected_investor_pda, _) = find_investor_pda(
+        &investor_data.investor_pubkey,
+        &investor_data.global_escrow_pubkey,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_pda {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_sol_vault, _) = find_sol_vault_pda(
+        &investor_data.investor_pubkey,
+        &investor_data.global_escrow_pubkey,
+        program_id,
+    );
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // SECURITY: Rent can only go back to the investor the vault belongs to
+    if investor_wallet.key != &investor_data.investor_pubkey {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // Already closed (or never created)
+    if sol_vault_account.owner != program_id {
+        msg!("SOL vault already closed");
+        return Ok(());
+    }
+
+    if investor_data.status != InvestorStatus::SolWithdrawn {
+        msg!("Locked SOL has not been withdrawn yet");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Held immediate SOL still belongs to the recipient (ReleaseHeldSol)
+    if investor_data.held_immediate_sol > 0 {
+        msg!("SOL vault still holds {} lamports of immediate SOL", investor_data.held_immediate_sol);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Drain lamports so the runtime reclaims the account
+    let rent_lamports = sol_vault_account.lamports();
+    **sol_vault_account.try_borrow_mut_lamports()? = 0;
+    **investor_wallet.try_borrow_mut_lamports()? += rent_lamports;
+
+    msg!("SOL vault closed, {} lamports of rent returned to investor {}", rent_lamports, investor_wallet.key);
+
+    Ok(())
+}
+
+pub fn process_migrate_investor(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let payer = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Same two legacy forms as MigrateEscrow: untagged (starting at is_initialized,
+    // never 170) or tagged with fields missing from the tail
+    let old_size = investor_account.data_len();
+    let tagged = investor_account.data.borrow().starts_with(&INVESTOR_ACCOUNT_DISCRIMINATOR);
+    let max_old_size = if tagged { InvestorAccount::LEN - 1 } else { InvestorAccount::LEN - DISCRIMINATOR_LEN };
+    if old_size > max_old_size {
+        msg!("Investor account at {} bytes has no older layout to migrate from", old_size);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Every layout starts with is_initialized, investor_pubkey and global_escrow_pubkey
+    let prefix_len = if tagged { DISCRIMINATOR_LEN } else { 0 };
+    {
+        let data = investor_account.data.borrow();
+        if data.len() < prefix_len + 1 + 32 + 32 || data[prefix_len] != 1 {
+            return Err(EscrowError::InvalidEscrowStatus.into());
+        }
+    }
+
+    let lamports_needed = Rent::get()?
+        .minimum_balance(InvestorAccount::LEN)
+        .saturating_sub(investor_account.lamports());
+
+    if lamports_needed > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, investor_account.key, lamports_needed),
+            &[
+                payer.clone(),
+                investor_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    investor_account.realloc(InvestorAccount::LEN, true)?;
+
+    if !tagged {
+        let mut data = investor_account.data.borrow_mut();
+        data.copy_within(..old_size, DISCRIMINATOR_LEN);
+        data[..DISCRIMINATOR_LEN].copy_from_slice(&INVESTOR_ACCOUNT_DISCRIMINATOR);
+    }
+
+    // Fail the migration rather than leave an account the program can't read, and make
+    // sure it really is the investor PDA it claims to be
+    let investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+    let (expected_investor_pda, _) = find_investor_pda(
+        &investor_data.investor_pubkey,
+        &investor_data.global_escrow_pubkey,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_pda {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    msg!("Investor account migrated: {} -> {} bytes", old_size, InvestorAccount::LEN);
+
+    Ok(())
+}
@@ -0,0 +1,321 @@
+// Instruction dispatch. Handlers live in the submodules, grouped by flow.
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+};
+
+use crate::{DepositMode, EscrowInstruction};
+
+mod admin;
+mod claim;
+mod deposit;
+mod initialize;
+mod refund;
+mod view;
+mod withdraw;
+
+pub use admin::*;
+pub use claim::*;
+pub use deposit::*;
+pub use initialize::*;
+pub use refund::*;
+pub use view::*;
+pub use withdraw::*;
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let instruction = EscrowInstruction::unpack(instruction_data)?;
+
+    match instruction {
+        EscrowInstruction::InitializeEscrow(params) => {
+            msg!("Instruction: InitializeEscrow");
+            process_initialize_escrow(program_id, accounts, params)
+        }
+        EscrowInstruction::DepositSol { sol_amount, min_tokens_out, deadline, fill_to_cap, whitelist_proof } => {
+            msg!("Instruction: DepositSol");
+            process_deposit_sol(program_id, accounts, sol_amount, min_tokens_out, deadline, fill_to_cap, &whitelist_proof, 1, DepositMode::Direct, false)
+        }
+        EscrowInstruction::WithdrawLockedSol => {
+            msg!("Instruction: WithdrawLockedSol");
+            process_withdraw_locked_sol(program_id, accounts)
+        }
+        EscrowInstruction::GetEscrowStatus => {
+            msg!("Instruction: GetEscrowStatus");
+            process_get_escrow_status(accounts)
+        }
+        EscrowInstruction::CloseSale => {
+            msg!("Instruction: CloseSale");
+            process_close_sale(program_id, accounts)
+        }
+        EscrowInstruction::InitializeAuditLog => {
+            msg!("Instruction: InitializeAuditLog");
+            process_initialize_audit_log(program_id, accounts)
+        }
+        EscrowInstruction::GetAuditLog { max_entries } => {
+            msg!("Instruction: GetAuditLog");
+            process_get_audit_log(program_id, accounts, max_entries)
+        }
+        EscrowInstruction::UpdateConfig { update } => {
+            msg!("Instruction: UpdateConfig");
+            process_update_config(program_id, accounts, update)
+        }
+        EscrowInstruction::DepositSolChunked { sol_amount, chunks } => {
+            msg!("Instruction: DepositSolChunked");
+            process_deposit_sol(program_id, accounts, sol_amount, 0, 0, false, &[], chunks, DepositMode::Direct, false)
+        }
+        EscrowInstruction::ClaimInactivityRefund => {
+            msg!("Instruction: ClaimInactivityRefund");
+            process_claim_inactivity_refund(program_id, accounts)
+        }
+        EscrowInstruction::BatchInitInvestors { count, create_token_accounts } => {
+            msg!("Instruction: BatchInitInvestors");
+            process_batch_init_investors(program_id, accounts, count, create_token_accounts)
+        }
+        EscrowInstruction::BatchCloseInvestorAccounts { count } => {
+            msg!("Instruction: BatchCloseInvestorAccounts");
+            process_batch_close_investor_accounts(program_id, accounts, count)
+        }
+        EscrowInstruction::GetDepositProof => {
+            msg!("Instruction: GetDepositProof");
+            process_get_deposit_proof(program_id, accounts)
+        }
+        EscrowInstruction::SetDepositDelegate { executor } => {
+            msg!("Instruction: SetDepositDelegate");
+            process_set_deposit_delegate(program_id, accounts, executor)
+        }
+        EscrowInstruction::DepositSolDelegated { sol_amount } => {
+            msg!("Instruction: DepositSolDelegated");
+            process_deposit_sol(program_id, accounts, sol_amount, 0, 0, false, &[], 1, DepositMode::Delegated, false)
+        }
+        EscrowInstruction::AdvanceRound { round_tokens_cap } => {
+            msg!("Instruction: AdvanceRound");
+            process_advance_round(program_id, accounts, round_tokens_cap)
+        }
+        EscrowInstruction::SnapshotState { snapshot_id } => {
+            msg!("Instruction: SnapshotState");
+            process_snapshot_state(program_id, accounts, snapshot_id)
+        }
+        EscrowInstruction::GetSnapshot => {
+            msg!("Instruction: GetSnapshot");
+            process_get_snapshot(program_id, accounts)
+        }
+        EscrowInstruction::ClaimImmediate => {
+            msg!("Instruction: ClaimImmediate");
+            process_claim_immediate(program_id, accounts)
+        }
+        EscrowInstruction::DepositSolProfiled { sol_amount } => {
+            msg!("Instruction: DepositSolProfiled");
+            process_deposit_sol(program_id, accounts, sol_amount, 0, 0, false, &[], 1, DepositMode::Direct, true)
+        }
+        EscrowInstruction::SweepStraySol => {
+            msg!("Instruction: SweepStraySol");
+            process_sweep_stray_sol(program_id, accounts)
+        }
+        EscrowInstruction::QueueConfigUpdate { update } => {
+            msg!("Instruction: QueueConfigUpdate");
+            process_queue_config_update(program_id, accounts, update)
+        }
+        EscrowInstruction::ExecuteConfigUpdate => {
+            msg!("Instruction: ExecuteConfigUpdate");
+            process_execute_config_update(program_id, accounts)
+        }
+        EscrowInstruction::GetPendingConfig => {
+            msg!("Instruction: GetPendingConfig");
+            process_get_pending_config(program_id, accounts)
+        }
+        EscrowInstruction::DepositFillCap => {
+            msg!("Instruction: DepositFillCap");
+            process_deposit_fill_cap(program_id, accounts)
+        }
+        EscrowInstruction::RefundFailedSale => {
+            msg!("Instruction: RefundFailedSale");
+            process_refund_failed_sale(program_id, accounts)
+        }
+        EscrowInstruction::SetPaused { paused } => {
+            msg!("Instruction: SetPaused");
+            process_set_paused(program_id, accounts, paused)
+        }
+        EscrowInstruction::FundPermitVault { lamports } => {
+            msg!("Instruction: FundPermitVault");
+            process_fund_permit_vault(program_id, accounts, lamports)
+        }
+        EscrowInstruction::WithdrawPermitVault { lamports } => {
+            msg!("Instruction: WithdrawPermitVault");
+            process_withdraw_permit_vault(program_id, accounts, lamports)
+        }
+        EscrowInstruction::DepositWithPermit { permit } => {
+            msg!("Instruction: DepositWithPermit");
+            process_deposit_with_permit(program_id, accounts, permit)
+        }
+        EscrowInstruction::RefundDeposit => {
+            msg!("Instruction: RefundDeposit");
+            process_refund_deposit(program_id, accounts)
+        }
+        EscrowInstruction::AddLiquidity => {
+            msg!("Instruction: AddLiquidity");
+            process_add_liquidity(program_id, accounts)
+        }
+        EscrowInstruction::MigrateEscrow => {
+            msg!("Instruction: MigrateEscrow");
+            process_migrate_escrow(program_id, accounts)
+        }
+        EscrowInstruction::CancelRecentDeposit => {
+            msg!("Instruction: CancelRecentDeposit");
+            process_cancel_recent_deposit(program_id, accounts)
+        }
+        EscrowInstruction::ReleaseHeldSol => {
+            msg!("Instruction: ReleaseHeldSol");
+            process_release_held_sol(program_id, accounts)
+        }
+        EscrowInstruction::RegisterRefund => {
+            msg!("Instruction: RegisterRefund");
+            process_register_refund(program_id, accounts)
+        }
+        EscrowInstruction::FundRefundQueue { lamports } => {
+            msg!("Instruction: FundRefundQueue");
+            process_fund_refund_queue(program_id, accounts, lamports)
+        }
+        EscrowInstruction::ClaimRefundFromQueue => {
+            msg!("Instruction: ClaimRefundFromQueue");
+            process_claim_refund_from_queue(program_id, accounts)
+        }
+        EscrowInstruction::InitializeAnalytics => {
+            msg!("Instruction: InitializeAnalytics");
+            process_initialize_analytics(program_id, accounts)
+        }
+        EscrowInstruction::GetAnalytics => {
+            msg!("Instruction: GetAnalytics");
+            process_get_analytics(program_id, accounts)
+        }
+        EscrowInstruction::InvestorOverride { custom_lock_duration } => {
+            msg!("Instruction: InvestorOverride");
+            process_investor_override(program_id, accounts, custom_lock_duration)
+        }
+        EscrowInstruction::EnterLottery => {
+            msg!("Instruction: EnterLottery");
+            process_enter_lottery(program_id, accounts)
+        }
+        EscrowInstruction::ResolveLottery { secret } => {
+            msg!("Instruction: ResolveLottery");
+            process_resolve_lottery(program_id, accounts, secret)
+        }
+        EscrowInstruction::ClaimLottery => {
+            msg!("Instruction: ClaimLottery");
+            process_claim_lottery(program_id, accounts)
+        }
+        EscrowInstruction::SelfAudit => {
+            msg!("Instruction: SelfAudit");
+            process_self_audit(program_id, accounts)
+        }
+        EscrowInstruction::ClaimVestedTokens => {
+            msg!("Instruction: ClaimVestedTokens");
+            process_claim_vested_tokens(program_id, accounts)
+        }
+        EscrowInstruction::AddToWhitelist { investor } => {
+            msg!("Instruction: AddToWhitelist");
+            process_add_to_whitelist(program_id, accounts, investor)
+        }
+        EscrowInstruction::RemoveFromWhitelist { investor } => {
+            msg!("Instruction: RemoveFromWhitelist");
+            process_remove_from_whitelist(program_id, accounts, investor)
+        }
+        EscrowInstruction::SetWithdrawalsFrozen { frozen } => {
+            msg!("Instruction: SetWithdrawalsFrozen");
+            process_set_withdrawals_frozen(program_id, accounts, frozen)
+        }
+        EscrowInstruction::GetBreakeven => {
+            msg!("Instruction: GetBreakeven");
+            process_get_breakeven(program_id, accounts)
+        }
+        EscrowInstruction::ClaimTokens { amount } => {
+            msg!("Instruction: ClaimTokens");
+            process_claim_tokens(program_id, accounts, amount)
+        }
+        EscrowInstruction::VerifyInvestorPrice => {
+            msg!("Instruction: VerifyInvestorPrice");
+            process_verify_investor_price(program_id, accounts)
+        }
+        EscrowInstruction::GetInvestorHistoryPage { start, count } => {
+            msg!("Instruction: GetInvestorHistoryPage");
+            process_get_investor_history_page(program_id, accounts, start, count)
+        }
+        EscrowInstruction::TransferAuthority { new_authority, new_recipient } => {
+            msg!("Instruction: TransferAuthority");
+            process_transfer_authority(program_id, accounts, new_authority, new_recipient)
+        }
+        EscrowInstruction::CreateCommitment { total_lamports, installment_lamports, installment_interval } => {
+            msg!("Instruction: CreateCommitment");
+            process_create_commitment(program_id, accounts, total_lamports, installment_lamports, installment_interval)
+        }
+        EscrowInstruction::PayInstallment => {
+            msg!("Instruction: PayInstallment");
+            process_pay_installment(program_id, accounts)
+        }
+        EscrowInstruction::ForfeitCommitment => {
+            msg!("Instruction: ForfeitCommitment");
+            process_forfeit_commitment(program_id, accounts)
+        }
+        EscrowInstruction::GetAverageSalePrice => {
+            msg!("Instruction: GetAverageSalePrice");
+            process_get_average_sale_price(program_id, accounts)
+        }
+        EscrowInstruction::CloseEscrow => {
+            msg!("Instruction: CloseEscrow");
+            process_close_escrow(program_id, accounts)
+        }
+        EscrowInstruction::SetBeneficiary { beneficiary } => {
+            msg!("Instruction: SetBeneficiary");
+            process_set_beneficiary(program_id, accounts, beneficiary)
+        }
+        EscrowInstruction::CloseSolVault => {
+            msg!("Instruction: CloseSolVault");
+            process_close_sol_vault(program_id, accounts)
+        }
+        EscrowInstruction::GetQuotes { usd_budget_cents } => {
+            msg!("Instruction: GetQuotes");
+            process_get_quotes(program_id, accounts, usd_budget_cents)
+        }
+        EscrowInstruction::CommitDeposit { sol_amount } => {
+            msg!("Instruction: CommitDeposit");
+            process_commit_deposit(program_id, accounts, sol_amount)
+        }
+        EscrowInstruction::RevealPrice => {
+            msg!("Instruction: RevealPrice");
+            process_reveal_price(program_id, accounts)
+        }
+        EscrowInstruction::ReclaimExpiredCommit => {
+            msg!("Instruction: ReclaimExpiredCommit");
+            process_reclaim_expired_commit(program_id, accounts)
+        }
+        EscrowInstruction::GetInvestorStatus => {
+            msg!("Instruction: GetInvestorStatus");
+            process_get_investor_status(program_id, accounts)
+        }
+        EscrowInstruction::DepositToken { token_amount } => {
+            msg!("Instruction: DepositToken");
+            process_deposit_token(program_id, accounts, token_amount)
+        }
+        EscrowInstruction::WithdrawLockedPayment => {
+            msg!("Instruction: WithdrawLockedPayment");
+            process_withdraw_locked_payment(program_id, accounts)
+        }
+        EscrowInstruction::MigrateInvestor => {
+            msg!("Instruction: MigrateInvestor");
+            process_migrate_investor(program_id, accounts)
+        }
+    }
+}
+
+/// Locate an optional account among the instruction accounts by its expected key
+pub fn find_account_by_key<'a, 'b>(
+    accounts: &'b [AccountInfo<'a>],
+    key: &Pubkey,
+) -> Option<&'b AccountInfo<'a>> {
+    accounts.iter().find(|account| account.key == key)
+}
@@ -0,0 +1,775 @@
+// Investor refunds: early refunds, cancellations, failed sales and the refund queue
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use borsh::BorshSerialize;
+
+use crate::{
+    checked_mul_div, find_investor_pda, find_refund_vault_pda, find_sol_vault_pda,
+    find_token_vault_pda, is_token_program, transfer_escrow_tokens, usd_value_cents, EscrowError,
+    GlobalEscrow, InvestorAccount, InvestorStatus, BPS_DENOMINATOR,
+};
+
+pub fn process_claim_inactivity_refund(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    global_escrow.check_withdrawals_open()?;
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    // Dead-man's switch only fires after the (very long) inactivity deadline
+    let deadline = global_escrow.inactivity_deadline().ok_or(EscrowError::InvalidEscrowStatus)?;
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp < deadline {
+        msg!("Inactivity refund not yet available. Current: {}, Available at: {}", current_timestamp, deadline);
+        return Err(EscrowError::SolStillLocked.into());
+    }
+
+    // AUTHORIZATION: Only the investor (or their beneficiary, past the deadline) can reclaim the SOL
+    if !investor_data.is_claimant(investor.key, true) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if investor_data.status != InvestorStatus::Deposited {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(&investor_data.investor_pubkey, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_sol_vault, _) = find_sol_vault_pda(&investor_data.investor_pubkey, global_escrow_account.key, program_id);
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let sol_to_refund = investor_data.get_locked_sol_amount();
+
+    // SAFETY: Ensure SOL vault remains rent-exempt after refund
+    let vault_balance = sol_vault_account.lamports();
+    let min_rent_balance = Rent::get()?.minimum_balance(0);
+    if vault_balance < sol_to_refund || vault_balance - sol_to_refund < min_rent_balance {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    **sol_vault_account.try_borrow_mut_lamports()? -= sol_to_refund;
+    **investor.try_borrow_mut_lamports()? += sol_to_refund;
+
+    investor_data.status = InvestorStatus::Refunded;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    if *investor.key == investor_data.investor_pubkey {
+        msg!("Inactivity refund: {} lamports returned to investor {}", sol_to_refund, investor.key);
+    } else {
+        msg!(
+            "Inactivity refund: {} lamports of investor {} recovered by beneficiary {}",
+            sol_to_refund,
+            investor_data.investor_pubkey,
+            investor.key
+        );
+    }
+
+    Ok(())
+}
+
+pub fn process_refund_failed_sale(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let investor_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    global_escrow.check_withdrawals_open()?;
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    // AUTHORIZATION: Only the investor can reclaim their own SOL
+    if investor_data.investor_pubkey != *investor.key {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if investor_data.status != InvestorStatus::Deposited {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(investor.key, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_sol_vault, _) = find_sol_vault_pda(investor.key, global_escrow_account.key, program_id);
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Successful sales (or ones still running) can't be refunded through this path
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if !global_escrow.soft_cap_failed(current_timestamp) {
+        msg!(
+            "Sale has not failed: raised {} of soft cap {}, sale ends at {}",
+            global_escrow.total_sol_deposited,
+            global_escrow.soft_cap_lamports,
+            global_escrow.sale_end_timestamp
+        );
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_token_vault, _) = find_token_vault_pda(global_escrow_account.key, program_id);
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Everything of the position still in its SOL vault: locked half and held immediate SOL
+    let sol_to_refund = investor_data
+        .get_locked_sol_amount()
+        .checked_add(investor_data.held_immediate_sol)
+        .ok_or(EscrowError::AmountOverflow)?;
+    // Tokens in hand come back, unclaimed vesting is forfeited; staked tokens can't be recalled
+    let tokens_to_return = if global_escrow.stakes_on_deposit() { 0 } else { investor_data.tokens_in_hand() };
+    let tokens_unsold = if global_escrow.stakes_on_deposit() { 0 } else { investor_data.tokens_received };
+
+    // SAFETY: Ensure SOL vault remains rent-exempt after refund
+    let vault_balance = sol_vault_account.lamports();
+    let min_rent_balance = Rent::get()?.minimum_balance(0);
+    if vault_balance < sol_to_refund || vault_balance - sol_to_refund < min_rent_balance {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    // The investor signs for their own token account
+    if tokens_to_return > 0 {
+        transfer_escrow_tokens(
+            &global_escrow,
+            accounts,
+            token_program,
+            investor_token_account,
+            token_vault_account,
+            investor,
+            tokens_to_return,
+            &[],
+        )?;
+    }
+
+    **sol_vault_account.try_borrow_mut_lamports()? -= sol_to_refund;
+    **investor.try_borrow_mut_lamports()? += sol_to_refund;
+
+    // Returned tokens go back to the unsold pool the recipient reclaims. total_sol_deposited
+    // only falls, so the sale stays failed.
+    global_escrow.tokens_sold = global_escrow
+        .tokens_sold
+        .checked_sub(tokens_unsold)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.round_tokens_sold = global_escrow.round_tokens_sold.saturating_sub(tokens_unsold);
+    global_escrow.vesting_tokens_outstanding = global_escrow
+        .vesting_tokens_outstanding
+        .saturating_sub(investor_data.unclaimed_vesting());
+    global_escrow.total_sol_deposited = global_escrow
+        .total_sol_deposited
+        .checked_sub(investor_data.sol_deposited)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_usd_value_cents = global_escrow
+        .total_usd_value_cents
+        .saturating_sub(usd_value_cents(investor_data.sol_deposited, investor_data.avg_sol_usd_price)?);
+    global_escrow.usd_valued_tokens = global_escrow.usd_valued_tokens.saturating_sub(tokens_unsold);
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    let sol_deposited = investor_data.sol_deposited;
+    investor_data.sol_deposited = 0;
+    investor_data.tokens_received = 0;
+    investor_data.tokens_vesting = 0;
+    investor_data.tokens_claimed = 0;
+    investor_data.held_immediate_sol = 0;
+    investor_data.status = InvestorStatus::Refunded;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Failed sale refund: {} of {} lamports and {} tokens returned for investor {}",
+        sol_to_refund,
+        sol_deposited,
+        tokens_to_return,
+        investor.key
+    );
+
+    Ok(())
+}
+
+pub fn process_refund_deposit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let investor_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    global_escrow.check_withdrawals_open()?;
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    // AUTHORIZATION: Only the investor can refund their own position
+    if investor_data.investor_pubkey != *investor.key {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if investor_data.status != InvestorStatus::Deposited {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp >= global_escrow.sale_end_timestamp {
+        msg!("Refunds close at sale end: {}", global_escrow.sale_end_timestamp);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Staked tokens never reached the investor, so they can't be handed back here
+    if global_escrow.stakes_on_deposit() {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(investor.key, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_sol_vault, _) = find_sol_vault_pda(investor.key, global_escrow_account.key, program_id);
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_token_vault, _) = find_token_vault_pda(global_escrow_account.key, program_id);
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Immediate SOL still held for the cancellation window goes back with the locked half
+    let sol_to_refund = investor_data
+        .get_locked_sol_amount()
+        .checked_add(investor_data.held_immediate_sol)
+        .ok_or(EscrowError::AmountOverflow)?;
+    // VESTING: only tokens in hand come back from the investor, the unclaimed vesting
+    // is forfeited in the vault. The whole purchase stops counting as sold.
+    let tokens_to_return = investor_data.tokens_in_hand();
+    let tokens_unsold = investor_data.tokens_received;
+
+    // SAFETY: Ensure SOL vault remains rent-exempt after refund
+    let vault_balance = sol_vault_account.lamports();
+    let min_rent_balance = Rent::get()?.minimum_balance(0);
+    if vault_balance < sol_to_refund || vault_balance - sol_to_refund < min_rent_balance {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    // Return tokens to the vault first; the investor signs for their own token account
+    transfer_escrow_tokens(
+        &global_escrow,
+        accounts,
+        token_program,
+        investor_token_account,
+        token_vault_account,
+        investor,
+        tokens_to_return,
+        &[],
+    )?;
+
+    **sol_vault_account.try_borrow_mut_lamports()? -= sol_to_refund;
+    **investor.try_borrow_mut_lamports()? += sol_to_refund;
+
+    // checked_sub: a second refund of the same position can never underflow the totals
+    global_escrow.tokens_sold = global_escrow
+        .tokens_sold
+        .checked_sub(tokens_unsold)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.round_tokens_sold = global_escrow.round_tokens_sold.saturating_sub(tokens_unsold);
+    global_escrow.vesting_tokens_outstanding = global_escrow
+        .vesting_tokens_outstanding
+        .saturating_sub(investor_data.unclaimed_vesting());
+    global_escrow.total_sol_deposited = global_escrow
+        .total_sol_deposited
+        .checked_sub(investor_data.sol_deposited)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_usd_value_cents = global_escrow
+        .total_usd_value_cents
+        .saturating_sub(usd_value_cents(investor_data.sol_deposited, investor_data.avg_sol_usd_price)?);
+    global_escrow.usd_valued_tokens = global_escrow.usd_valued_tokens.saturating_sub(tokens_unsold);
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    // The position is fully unwound, so a later deposit starts from zero
+    investor_data.sol_deposited = 0;
+    investor_data.tokens_received = 0;
+    investor_data.tokens_vesting = 0;
+    investor_data.tokens_claimed = 0;
+    investor_data.held_immediate_sol = 0;
+    investor_data.status = InvestorStatus::Refunded;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Deposit refunded: {} lamports and {} tokens returned for investor {}",
+        sol_to_refund,
+        tokens_to_return,
+        investor.key
+    );
+
+    Ok(())
+}
+
+pub fn process_cancel_recent_deposit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let investor_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    global_escrow.check_withdrawals_open()?;
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    // AUTHORIZATION: Only the investor can cancel their own position
+    if investor_data.investor_pubkey != *investor.key {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if investor_data.status != InvestorStatus::Deposited {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    // A cancellation after sale end would lower the raise the soft-cap outcome is read from
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    global_escrow.check_sale_open(current_timestamp)?;
+    if !investor_data.in_cancellation_window(global_escrow.cancellation_window, current_timestamp) {
+        msg!(
+            "Cancellation window closed at {}",
+            investor_data.deposit_timestamp.saturating_add(global_escrow.cancellation_window)
+        );
+        return Err(EscrowError::CancellationWindowClosed.into());
+    }
+
+    // Staked tokens never reached the investor, so they can't be handed back here
+    if global_escrow.stakes_on_deposit() {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(investor.key, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_sol_vault, _) = find_sol_vault_pda(investor.key, global_escrow_account.key, program_id);
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_token_vault, _) = find_token_vault_pda(global_escrow_account.key, program_id);
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let sol_to_refund = investor_data
+        .get_locked_sol_amount()
+        .checked_add(investor_data.held_immediate_sol)
+        .ok_or(EscrowError::AmountOverflow)?;
+    // VESTING: unclaimed vesting never left the vault and is forfeited with the rest
+    let tokens_to_return = investor_data.tokens_in_hand();
+    let tokens_unsold = investor_data.tokens_received;
+
+    // SAFETY: Ensure SOL vault remains rent-exempt after refund
+    let vault_balance = sol_vault_account.lamports();
+    let min_rent_balance = Rent::get()?.minimum_balance(0);
+    if vault_balance < sol_to_refund || vault_balance - sol_to_refund < min_rent_balance {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    // Return tokens to the vault first; the investor signs for their own token account
+    transfer_escrow_tokens(
+        &global_escrow,
+        accounts,
+        token_program,
+        investor_token_account,
+        token_vault_account,
+        investor,
+        tokens_to_return,
+        &[],
+    )?;
+
+    **sol_vault_account.try_borrow_mut_lamports()? -= sol_to_refund;
+    **investor.try_borrow_mut_lamports()? += sol_to_refund;
+
+    global_escrow.tokens_sold = global_escrow
+        .tokens_sold
+        .checked_sub(tokens_unsold)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.round_tokens_sold = global_escrow.round_tokens_sold.saturating_sub(tokens_unsold);
+    global_escrow.vesting_tokens_outstanding = global_escrow
+        .vesting_tokens_outstanding
+        .saturating_sub(investor_data.unclaimed_vesting());
+    global_escrow.total_sol_deposited = global_escrow
+        .total_sol_deposited
+        .checked_sub(investor_data.sol_deposited)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_usd_value_cents = global_escrow
+        .total_usd_value_cents
+        .saturating_sub(usd_value_cents(investor_data.sol_deposited, investor_data.avg_sol_usd_price)?);
+    global_escrow.usd_valued_tokens = global_escrow.usd_valued_tokens.saturating_sub(tokens_unsold);
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    let sol_deposited = investor_data.sol_deposited;
+    investor_data.sol_deposited = 0;
+    investor_data.tokens_received = 0;
+    investor_data.tokens_vesting = 0;
+    investor_data.tokens_claimed = 0;
+    investor_data.held_immediate_sol = 0;
+    investor_data.status = InvestorStatus::Refunded;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Deposit cancelled: {} of {} lamports and {} tokens returned for investor {}",
+        sol_to_refund,
+        sol_deposited,
+        tokens_to_return,
+        investor.key
+    );
+
+    Ok(())
+}
+
+pub fn process_register_refund(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    if !global_escrow.refund_queue_enabled {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // AUTHORIZATION: Only the investor can register their own claim
+    if investor_data.investor_pubkey != *investor.key {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(investor.key, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if !global_escrow.soft_cap_failed(current_timestamp) {
+        msg!("Refunds can only be queued for a failed sale");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    if investor_data.refund_registered {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    // Immediate half as split at deposit time, less the protocol fee carved out of it
+    // and anything still held in the SOL vault (that never reached the recipient)
+    let sol_immediate = investor_data.sol_deposited - investor_data.get_locked_sol_amount();
+    let fee = checked_mul_div(investor_data.sol_deposited, global_escrow.protocol_fee_bps as u64, BPS_DENOMINATOR)?;
+    let refund_owed = sol_immediate
+        .saturating_sub(fee)
+        .saturating_sub(investor_data.held_immediate_sol);
+    if refund_owed == 0 {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    investor_data.refund_registered = true;
+    investor_data.refund_owed = refund_owed;
+    investor_data.refund_queue_position = global_escrow.refund_queue_tail;
+
+    global_escrow.refund_queue_tail = global_escrow
+        .refund_queue_tail
+        .checked_add(refund_owed)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_refunds_owed = global_escrow
+        .total_refunds_owed
+        .checked_add(refund_owed)
+        .ok_or(EscrowError::AmountOverflow)?;
+
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Refund registered: {} lamports for investor {} at queue position {}",
+        refund_owed,
+        investor.key,
+        investor_data.refund_queue_position
+    );
+
+    Ok(())
+}
+
+pub fn process_fund_refund_queue(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    lamports: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let funder = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let refund_vault_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !funder.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.refund_queue_enabled {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let (expected_refund_vault, refund_vault_bump) = find_refund_vault_pda(global_escrow_account.key, program_id);
+    if refund_vault_account.key != &expected_refund_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Funding past the registered claims would strand SOL in the vault
+    let unfunded = global_escrow.refund_queue_tail.saturating_sub(global_escrow.refund_queue_funded);
+    if lamports == 0 || lamports > unfunded {
+        msg!("Refund funding must be between 1 and {} lamports", unfunded);
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    // Create refund vault if it doesn't exist
+    if refund_vault_account.owner != program_id {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                funder.key,
+                refund_vault_account.key,
+                rent.minimum_balance(0),
+                0, // No data, just stores SOL
+                program_id,
+            ),
+            &[
+                funder.clone(),
+                refund_vault_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"refund_vault",
+                global_escrow_account.key.as_ref(),
+                &[refund_vault_bump],
+            ]],
+        )?;
+    }
+
+    invoke(
+        &system_instruction::transfer(funder.key, refund_vault_account.key, lamports),
+        &[
+            funder.clone(),
+            refund_vault_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+
+    global_escrow.refund_queue_funded = global_escrow
+        .refund_queue_funded
+        .checked_add(lamports)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Refund queue funded: {} lamports ({} of {} registered)",
+        lamports,
+        global_escrow.refund_queue_funded,
+        global_escrow.refund_queue_tail
+    );
+
+    Ok(())
+}
+
+pub fn process_claim_refund_from_queue(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let investor = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let refund_vault_account = next_account_info(account_info_iter)?;
+
+    if !investor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id
+        || investor_account.owner != program_id
+        || refund_vault_account.owner != program_id
+    {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    global_escrow.check_withdrawals_open()?;
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    // AUTHORIZATION: Only the investor can claim their own refund
+    if investor_data.investor_pubkey != *investor.key {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(investor.key, global_escrow_account.key, program_id);
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_refund_vault, _) = find_refund_vault_pda(global_escrow_account.key, program_id);
+    if refund_vault_account.key != &expected_refund_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if investor_data.refund_owed == 0 {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    // Earlier claims are covered first; this one is payable only once funding reaches it
+    let claimable = investor_data.refund_claimable(global_escrow.refund_queue_funded);
+    if claimable == 0 {
+        msg!(
+            "Refund not funded yet: queue funded to {}, claim starts at {}",
+            global_escrow.refund_queue_funded,
+            investor_data.refund_queue_position
+        );
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    // SAFETY: Ensure refund vault remains rent-exempt after the payout
+    let vault_balance = refund_vault_account.lamports();
+    let min_rent_balance = Rent::get()?.minimum_balance(0);
+    if vault_balance < claimable || vault_balance - claimable < min_rent_balance {
+        return Err(EscrowError::NotRentExempt.into());
+    }
+
+    **refund_vault_account.try_borrow_mut_lamports()? -= claimable;
+    **investor.try_borrow_mut_lamports()? += claimable;
+
+    investor_data.refund_owed = investor_data
+        .refund_owed
+        .checked_sub(claimable)
+        .ok_or(EscrowError::AmountOverflow)?;
+    investor_data.refund_queue_position = investor_data
+        .refund_queue_position
+        .checked_add(claimable)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.total_refunds_owed = global_escrow
+        .total_refunds_owed
+        .checked_sub(claimable)
+        .ok_or(EscrowError::AmountOverflow)?;
+
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Queued refund paid: {} lamports to investor {}, {} still owed",
+        claimable,
+        investor.key,
+        investor_data.refund_owed
+    );
+
+    Ok(())
+}
@@ -0,0 +1,658 @@
+// Read-only instructions reporting state through logs and return data
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{set_return_data, MAX_RETURN_DATA},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+use borsh::BorshDeserialize;
+
+use crate::{
+    checked_mul_div, find_account_by_key, find_analytics_pda, find_audit_log_pda,
+    find_deposit_proof_pda, find_investor_pda, find_pending_config_pda, find_snapshot_pda,
+    get_oracle_price, is_token_program, normalize_price_decimals, read_chainlink_round,
+    read_mint_decimals, read_oracle_price, AssetQuote, AuditEntry, AuditLogHeader, AuditLogPage,
+    AverageSalePrice, BreakevenReport, DepositAnalytics, DepositHistoryPage, DepositProof,
+    EscrowError, EscrowEvent, GlobalEscrow, GlobalEscrowStatus, InvestorAccount, OracleKind,
+    PendingAction, StateSnapshot, BPS_DENOMINATOR, CHAINLINK_USD_DECIMALS, CURVE_PRICE_SCALE,
+    SOL_LAMPORTS, USD_CENTS_SCALE,
+};
+
+pub fn process_get_escrow_status(accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let _clock = next_account_info(account_info_iter)?;
+    let oracle_accounts = (account_info_iter.next(), account_info_iter.next());
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    
+    let tokens_remaining = global_escrow.total_tokens_available.saturating_sub(global_escrow.tokens_sold);
+    let progress_bps = if global_escrow.total_tokens_available > 0 {
+        checked_mul_div(global_escrow.tokens_sold, BPS_DENOMINATOR, global_escrow.total_tokens_available)?
+    } else {
+        0
+    };
+    let time_remaining = global_escrow.sale_end_timestamp.saturating_sub(current_timestamp).max(0);
+    
+    msg!("Escrow Status:");
+    msg!("  Initialized: {}", global_escrow.is_initialized);
+    msg!("  Total tokens: {}", global_escrow.total_tokens_available);
+    msg!("  Tokens sold: {}", global_escrow.tokens_sold);
+    msg!("  Tokens remaining: {}", tokens_remaining);
+    msg!("  Sale progress: {} bps", progress_bps);
+    msg!("  Total SOL deposited: {}", global_escrow.total_sol_deposited);
+    msg!("  Total SOL withdrawn: {}", global_escrow.total_sol_withdrawn);
+    msg!("  Lock duration: {}s", global_escrow.lock_duration);
+    msg!("  Sale ends: {} ({}s remaining)", global_escrow.sale_end_timestamp, time_remaining);
+    msg!("  Global unlock: {}", global_escrow.global_unlock_timestamp());
+    msg!("  Protocol fee: {} bps, total fees collected: {}", global_escrow.protocol_fee_bps, global_escrow.total_fees_collected);
+    
+    // Live price only when the caller passes the oracle accounts
+    let sol_usd_price = if let (Some(oracle_program), Some(price_feed)) = oracle_accounts {
+        let (sol_usd_price, price_timestamp) = get_oracle_price(price_feed, oracle_program, &global_escrow)?;
+        msg!("  SOL/USD price: {} (updated {})", sol_usd_price, price_timestamp);
+        Some(sol_usd_price)
+    } else {
+        None
+    };
+    
+    let status = GlobalEscrowStatus {
+        is_initialized: global_escrow.is_initialized,
+        total_tokens_available: global_escrow.total_tokens_available,
+        tokens_sold: global_escrow.tokens_sold,
+        tokens_remaining,
+        progress_bps,
+        total_sol_deposited: global_escrow.total_sol_deposited,
+        total_sol_withdrawn: global_escrow.total_sol_withdrawn,
+        lock_duration: global_escrow.lock_duration,
+        sale_end_timestamp: global_escrow.sale_end_timestamp,
+        time_remaining,
+        global_unlock_timestamp: global_escrow.global_unlock_timestamp(),
+        protocol_fee_bps: global_escrow.protocol_fee_bps,
+        total_fees_collected: global_escrow.total_fees_collected,
+        sol_usd_price,
+    };
+    set_return_data(&borsh::to_vec(&status)?);
+    
+    Ok(())
+}
+
+pub fn process_get_audit_log(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    max_entries: u8,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let audit_log_account = next_account_info(account_info_iter)?;
+
+    let (expected_audit_log, _) = find_audit_log_pda(global_escrow_account.key, program_id);
+    if audit_log_account.key != &expected_audit_log || audit_log_account.owner != program_id {
+        return Err(EscrowError::InvalidAuditLog.into());
+    }
+
+    let data = audit_log_account.data.borrow();
+    let header = AuditLogHeader::deserialize(&mut &data[..AuditLogHeader::LEN])?;
+    if !header.is_initialized {
+        return Err(EscrowError::InvalidAuditLog.into());
+    }
+
+    // Bound the page to what fits in the return data buffer
+    let max_fit = (MAX_RETURN_DATA - 8 - 4) / AuditEntry::LEN;
+    let stored = header.total_entries.min(header.capacity as u64) as usize;
+    let count = stored.min(max_entries as usize).min(max_fit);
+
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        // Walk backwards from the newest entry
+        let index = (header.total_entries - 1 - i as u64) % header.capacity as u64;
+        let offset = AuditLogHeader::LEN + index as usize * AuditEntry::LEN;
+        entries.push(AuditEntry::deserialize(&mut &data[offset..offset + AuditEntry::LEN])?);
+    }
+
+    msg!("Audit log: {} total entries, returning {}", header.total_entries, entries.len());
+
+    let page = AuditLogPage {
+        total_entries: header.total_entries,
+        entries,
+    };
+    set_return_data(&borsh::to_vec(&page)?);
+
+    Ok(())
+}
+
+pub fn process_get_deposit_proof(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let proof_account = next_account_info(account_info_iter)?;
+
+    if proof_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let proof = DepositProof::try_from_slice(&proof_account.data.borrow())?;
+    if !proof.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // SECURITY: Verify the proof lives at its canonical address
+    let (investor_account, _) = find_investor_pda(&proof.investor_pubkey, &proof.global_escrow_pubkey, program_id);
+    let (expected_proof, _) = find_deposit_proof_pda(&investor_account, proof.deposit_index, program_id);
+    if proof_account.key != &expected_proof {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    msg!("Deposit Proof #{}:", proof.deposit_index);
+    msg!("  Investor: {}", proof.investor_pubkey);
+    msg!("  SOL amount: {}", proof.sol_amount);
+    msg!("  Tokens received: {}", proof.tokens_received);
+    msg!("  SOL/USD price: {}", proof.sol_usd_price);
+    msg!("  Slot: {}, timestamp: {}", proof.slot, proof.timestamp);
+    msg!("  Hash valid: {}", proof.compute_hash() == proof.proof_hash);
+
+    set_return_data(&proof_account.data.borrow());
+
+    Ok(())
+}
+
+pub fn process_get_snapshot(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let snapshot_account = next_account_info(account_info_iter)?;
+
+    if snapshot_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let snapshot = StateSnapshot::try_from_slice(&snapshot_account.data.borrow())?;
+    if !snapshot.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // SECURITY: Verify the snapshot lives at its canonical address
+    let (expected_snapshot, _) = find_snapshot_pda(&snapshot.global_escrow_pubkey, snapshot.snapshot_id, program_id);
+    if snapshot_account.key != &expected_snapshot {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    msg!("Snapshot #{}:", snapshot.snapshot_id);
+    msg!("  Global escrow: {}", snapshot.global_escrow_pubkey);
+    msg!("  Creator: {}", snapshot.creator);
+    msg!("  Slot: {}, timestamp: {}", snapshot.slot, snapshot.timestamp);
+    if !snapshot.investor_data.is_empty() {
+        msg!("  Investor account: {}", snapshot.investor_account_pubkey);
+    }
+    msg!("  Hash valid: {}", snapshot.compute_hash() == snapshot.snapshot_hash);
+
+    let data = snapshot_account.data.borrow();
+    if data.len() > MAX_RETURN_DATA {
+        msg!("Snapshot exceeds return data limit, read the account directly");
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+    set_return_data(&data);
+
+    Ok(())
+}
+
+pub fn process_get_pending_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let pending_account = next_account_info(account_info_iter)?;
+
+    let (expected_pending, _) = find_pending_config_pda(global_escrow_account.key, program_id);
+    if pending_account.key != &expected_pending {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if pending_account.owner != program_id || pending_account.data_is_empty() {
+        msg!("No pending config change");
+        set_return_data(&[]);
+        return Ok(());
+    }
+
+    let pending = PendingAction::try_from_slice(&pending_account.data.borrow())?;
+    let now = Clock::get()?.unix_timestamp;
+
+    msg!("Pending config change: {:?}", pending.update);
+    msg!("  Queued at: {}", pending.queued_at);
+    msg!("  Active at: {} ({} seconds from now)", pending.activation_timestamp, (pending.activation_timestamp - now).max(0));
+
+    set_return_data(&pending_account.data.borrow());
+
+    Ok(())
+}
+
+pub fn process_get_analytics(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let analytics_account = next_account_info(account_info_iter)?;
+
+    let (expected_analytics, _) = find_analytics_pda(global_escrow_account.key, program_id);
+    if analytics_account.key != &expected_analytics || analytics_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let analytics = DepositAnalytics::try_from_slice(&analytics_account.data.borrow())?;
+    let average = analytics
+        .total_lamports
+        .checked_div(analytics.deposit_count)
+        .unwrap_or(0);
+
+    msg!("Deposit analytics:");
+    msg!("  Deposits: {}, total: {} lamports", analytics.deposit_count, analytics.total_lamports);
+    msg!("  Min: {}, max: {}, average: {}", analytics.min_deposit, analytics.max_deposit, average);
+    msg!("  Buckets: {:?}", analytics.buckets);
+
+    set_return_data(&analytics_account.data.borrow());
+
+    Ok(())
+}
+
+pub fn process_get_breakeven(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    let investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    let (expected_investor_pda, _) = find_investor_pda(
+        &investor_data.investor_pubkey,
+        global_escrow_account.key,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if investor_data.sol_deposited == 0 {
+        return Err(EscrowError::InsufficientSolDeposit.into());
+    }
+
+    let token_price = global_escrow.current_token_price(Clock::get()?.unix_timestamp)?;
+
+    // Value of the tokens in USD with CHAINLINK_USD_DECIMALS decimals
+    let token_value_usd = (investor_data.tokens_received as u128)
+        .checked_mul(token_price)
+        .and_then(|v| v.checked_mul(10_u128.pow(CHAINLINK_USD_DECIMALS as u32)))
+        .ok_or(EscrowError::AmountOverflow)?
+        / (CURVE_PRICE_SCALE as u128
+            * USD_CENTS_SCALE as u128
+            * 10_u128.pow(global_escrow.token_decimals as u32));
+
+    // sol_paid * breakeven / SOL_LAMPORTS = token_value_usd
+    let breakeven = token_value_usd
+        .checked_mul(SOL_LAMPORTS as u128)
+        .ok_or(EscrowError::AmountOverflow)?
+        / investor_data.sol_deposited as u128;
+
+    let report = BreakevenReport {
+        sol_paid: investor_data.sol_deposited,
+        tokens: investor_data.tokens_received,
+        token_price: u64::try_from(token_price).map_err(|_| EscrowError::AmountOverflow)?,
+        avg_sol_usd_price: investor_data.avg_sol_usd_price,
+        breakeven_sol_usd_price: u64::try_from(breakeven).map_err(|_| EscrowError::AmountOverflow)?,
+    };
+
+    msg!(
+        "Breakeven for {}: SOL/USD {} (entry {}), {} tokens for {} lamports",
+        investor_data.investor_pubkey,
+        report.breakeven_sol_usd_price,
+        report.avg_sol_usd_price,
+        report.tokens,
+        report.sol_paid
+    );
+
+    set_return_data(&borsh::to_vec(&report)?);
+
+    Ok(())
+}
+
+pub fn process_verify_investor_price(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let oracle_program = next_account_info(account_info_iter)?;
+    let price_feed = next_account_info(account_info_iter)?;
+
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    let investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    let (expected_investor_pda, _) = find_investor_pda(
+        &investor_data.investor_pubkey,
+        global_escrow_account.key,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Only the escrow's own Chainlink feed can vouch for the recorded price
+    if global_escrow.oracle_kind != OracleKind::Chainlink
+        || oracle_program.key != &global_escrow.oracle_program_id
+        || price_feed.key != &global_escrow.price_feed_pubkey
+        || price_feed.owner != oracle_program.key
+    {
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+
+    if investor_data.price_round_id == 0 {
+        msg!("No Chainlink round recorded for this investor");
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+
+    let (raw_answer, timestamp) = read_chainlink_round(&price_feed.data.borrow(), investor_data.price_round_id)
+        .ok_or_else(|| {
+            msg!("Round {} is no longer in the feed's live window", investor_data.price_round_id);
+            EscrowError::InvalidPriceFeed
+        })?;
+    // Recorded prices are normalized, the ring buffer holds the feed's raw answers
+    let answer = normalize_price_decimals(raw_answer, global_escrow.feed_decimals() as i32)?;
+
+    let matches = answer == investor_data.sol_usd_price as i128;
+    msg!(
+        "Round {} at {}: feed {}, recorded {} -> {}",
+        investor_data.price_round_id,
+        timestamp,
+        answer,
+        investor_data.sol_usd_price,
+        if matches { "match" } else { "MISMATCH" }
+    );
+
+    set_return_data(&borsh::to_vec(&matches)?);
+
+    Ok(())
+}
+
+pub fn process_get_investor_history_page(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    start: u64,
+    count: u8,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+    let (expected_investor_pda, _) = find_investor_pda(
+        &investor_data.investor_pubkey,
+        global_escrow_account.key,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let total_deposits = investor_data.deposit_count;
+    if start > total_deposits || (start == total_deposits && total_deposits > 0) {
+        msg!("History start {} is past the last deposit ({} deposits)", start, total_deposits);
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    // Bound the page to the stored deposits and to what fits in the return data buffer
+    let max_fit = (MAX_RETURN_DATA - 8 - 8 - 4) / DepositProof::LEN;
+    let count = (count as u64).min(total_deposits - start).min(max_fit as u64);
+
+    let mut entries = Vec::with_capacity(count as usize);
+    for deposit_index in start..start + count {
+        let (expected_proof, _) = find_deposit_proof_pda(investor_account.key, deposit_index, program_id);
+        let proof_account = find_account_by_key(accounts, &expected_proof).ok_or_else(|| {
+            msg!("Missing deposit proof account: {}", expected_proof);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+
+        // Deposits made while proofs were disabled have no record
+        if proof_account.owner != program_id {
+            continue;
+        }
+        entries.push(DepositProof::try_from_slice(&proof_account.data.borrow())?);
+    }
+
+    msg!(
+        "Deposit history: {} deposits, returning {} from index {}",
+        total_deposits,
+        entries.len(),
+        start
+    );
+
+    let page = DepositHistoryPage {
+        total_deposits,
+        start,
+        entries,
+    };
+    set_return_data(&borsh::to_vec(&page)?);
+
+    Ok(())
+}
+
+pub fn process_get_average_sale_price(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let average_price = if global_escrow.usd_valued_tokens == 0 {
+        0
+    } else {
+        let scaled = (global_escrow.total_usd_value_cents as u128)
+            .checked_mul(CURVE_PRICE_SCALE as u128)
+            .and_then(|v| v.checked_mul(10_u128.pow(global_escrow.token_decimals as u32)))
+            .ok_or(EscrowError::AmountOverflow)?
+            / global_escrow.usd_valued_tokens as u128;
+        u64::try_from(scaled).map_err(|_| EscrowError::AmountOverflow)?
+    };
+
+    let report = AverageSalePrice {
+        total_usd_value_cents: global_escrow.total_usd_value_cents,
+        usd_valued_tokens: global_escrow.usd_valued_tokens,
+        average_price,
+    };
+
+    msg!(
+        "Average sale price: {} ({} cents for {} tokens)",
+        report.average_price,
+        report.total_usd_value_cents,
+        report.usd_valued_tokens
+    );
+
+    set_return_data(&borsh::to_vec(&report)?);
+
+    Ok(())
+}
+
+pub fn process_get_quotes(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    usd_budget_cents: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let oracle_program = next_account_info(account_info_iter)?;
+    let asset_accounts = account_info_iter.as_slice();
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    if oracle_program.key != &global_escrow.oracle_program_id {
+        msg!("Invalid oracle program: {}", oracle_program.key);
+        return Err(EscrowError::InvalidPriceFeed.into());
+    }
+
+    if usd_budget_cents == 0 {
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+
+    let token_price = global_escrow.current_token_price(Clock::get()?.unix_timestamp)?;
+    if token_price == 0 {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Native SOL first, then every mapped mint (a native mint entry only overrides the SOL feed)
+    let native_mint = spl_token::native_mint::id();
+    let mut quote_mints = vec![native_mint];
+    quote_mints.extend(
+        global_escrow.quote_feeds[..global_escrow.quote_feed_count as usize]
+            .iter()
+            .map(|feed| feed.quote_mint)
+            .filter(|mint| mint != &native_mint),
+    );
+
+    let usd_scale = 10_u128.pow(CHAINLINK_USD_DECIMALS as u32);
+    let mut quotes = Vec::with_capacity(quote_mints.len());
+    for quote_mint in quote_mints {
+        let feed_key = global_escrow.price_feed_for(&quote_mint).ok_or(EscrowError::InvalidPriceFeed)?;
+        let price_feed = find_account_by_key(asset_accounts, &feed_key).ok_or_else(|| {
+            msg!("Missing price feed {} for quote asset {}", feed_key, quote_mint);
+            EscrowError::InvalidPriceFeed
+        })?;
+        let (price, _) = read_oracle_price(price_feed, oracle_program, &global_escrow)?;
+
+        let asset_decimals = if quote_mint == native_mint {
+            spl_token::native_mint::DECIMALS
+        } else {
+            let mint_account = find_account_by_key(asset_accounts, &quote_mint).ok_or_else(|| {
+                msg!("Missing mint account for quote asset {}", quote_mint);
+                EscrowError::InvalidTokenAccount
+            })?;
+            if !is_token_program(mint_account.owner) {
+                return Err(EscrowError::InvalidTokenAccount.into());
+            }
+            read_mint_decimals(mint_account)?
+        };
+        let asset_unit = 10_u128.pow(asset_decimals as u32);
+
+        // budget / price, in asset base units
+        let quote_amount = (usd_budget_cents as u128)
+            .checked_mul(usd_scale)
+            .and_then(|v| v.checked_mul(asset_unit))
+            .ok_or(EscrowError::AmountOverflow)?
+            / (price as u128 * USD_CENTS_SCALE as u128);
+
+        // Value of quote_amount in CURVE_PRICE_SCALE units, then tokens at the spot price
+        let value = quote_amount
+            .checked_mul(price as u128)
+            .and_then(|v| v.checked_mul(USD_CENTS_SCALE as u128 * CURVE_PRICE_SCALE as u128))
+            .ok_or(EscrowError::AmountOverflow)?
+            / (asset_unit * usd_scale);
+        let tokens = value
+            .checked_mul(10_u128.pow(global_escrow.token_decimals as u32))
+            .ok_or(EscrowError::AmountOverflow)?
+            / token_price;
+
+        let quote = AssetQuote {
+            quote_mint,
+            price,
+            quote_amount: u64::try_from(quote_amount).map_err(|_| EscrowError::AmountOverflow)?,
+            tokens: u64::try_from(tokens).map_err(|_| EscrowError::AmountOverflow)?,
+        };
+        msg!("Quote in {}: {} base units -> {} tokens (price {})", quote.quote_mint, quote.quote_amount, quote.tokens, quote.price);
+        quotes.push(quote);
+    }
+
+    set_return_data(&borsh::to_vec(&quotes)?);
+
+    Ok(())
+}
+
+pub fn process_get_investor_status(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let _clock = next_account_info(account_info_iter)?;
+
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    let investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    let (expected_investor_pda, _) = find_investor_pda(
+        &investor_data.investor_pubkey,
+        global_escrow_account.key,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let unlock_timestamp = global_escrow.investor_unlock_timestamp(&investor_data);
+
+    msg!("Investor Status:");
+    msg!("  Investor: {}", investor_data.investor_pubkey);
+    msg!("  SOL deposited: {}", investor_data.sol_deposited);
+    msg!("  Tokens received: {}", investor_data.tokens_received);
+    msg!("  Deposit timestamp: {}", investor_data.deposit_timestamp);
+    msg!("  Status: {:?}", investor_data.status);
+    msg!("  SOL/USD price: {}", investor_data.sol_usd_price);
+    msg!("  Unlock timestamp: {}", unlock_timestamp);
+
+    EscrowEvent::InvestorStatus {
+        global_escrow: *global_escrow_account.key,
+        investor: investor_data.investor_pubkey,
+        sol_deposited: investor_data.sol_deposited,
+        tokens_received: investor_data.tokens_received,
+        deposit_timestamp: investor_data.deposit_timestamp,
+        status: investor_data.status,
+        sol_usd_price: investor_data.sol_usd_price,
+        unlock_timestamp,
+        timestamp: Clock::get()?.unix_timestamp,
+    }
+    .emit()?;
+
+    Ok(())
+}
@@ -0,0 +1,732 @@
+// Moving raised SOL and unsold tokens out of the escrow
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use borsh::BorshSerialize;
+
+use crate::{
+    find_investor_pda, find_payment_vault_pda, find_recipient_vault_pda, find_sol_vault_pda,
+    find_token_vault_pda, is_token_program, read_mint_decimals, read_token_account,
+    record_audit_entry, transfer_escrow_tokens, transfer_tokens_checked, AmmInstruction,
+    AuditAction, EscrowError, EscrowEvent, GlobalEscrow, InvestorAccount, InvestorStatus,
+};
+
+pub fn process_withdraw_locked_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let withdrawer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+    let recipient_wallet = next_account_info(account_info_iter)?;
+    let _system_program = next_account_info(account_info_iter)?;
+    let _clock = next_account_info(account_info_iter)?;
+
+    if !withdrawer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // SECURITY: Validate investor account owner before deserializing
+    if investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Load accounts
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    global_escrow.check_withdrawals_open()?;
+    let investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    // SECURITY: Check if SOL has already been withdrawn (prevent double withdrawal)
+    if investor_data.status == InvestorStatus::SolWithdrawn {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+    
+    // SECURITY: Only recipient_wallet can withdraw locked SOL
+    if withdrawer.key != &global_escrow.recipient_wallet {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // SECURITY: Verify investor account PDA consistency  
+    let (expected_investor_pda, _) = find_investor_pda(
+        &investor_data.investor_pubkey,
+        &investor_data.global_escrow_pubkey,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_pda {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // SECURITY: Verify global escrow consistency
+    if investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Verify SOL vault PDA
+    let (expected_sol_vault, _sol_vault_bump) = find_sol_vault_pda(
+        &investor_data.investor_pubkey,
+        &investor_data.global_escrow_pubkey,
+        program_id,
+    );
+    
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Check if GLOBAL unlock condition is met (time lock and/or tokens_sold target per unlock_mode)
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    
+    // SOFT CAP: a failed sale's locked SOL belongs to the investors' refunds
+    if global_escrow.soft_cap_failed(current_timestamp) {
+        return Err(EscrowError::SoftCapNotMet.into());
+    }
+    
+    if !global_escrow.is_unlocked_for(&investor_data, current_timestamp) {
+        msg!(
+            "SOL still locked. Current: {}, Unlock at: {}, Tokens sold: {}/{}",
+            current_timestamp,
+            global_escrow.investor_unlock_timestamp(&investor_data),
+            global_escrow.tokens_sold,
+            global_escrow.unlock_tokens_sold_target
+        );
+        return Err(EscrowError::SolStillLocked.into());
+    }
+
+    // Calculate locked SOL amount (50% of deposited)
+    let sol_to_withdraw = investor_data.get_locked_sol_amount();
+    
+    // Check if SOL vault has enough balance
+    let vault_balance = sol_vault_account.lamports();
+    if vault_balance < sol_to_withdraw {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    // SAFETY: Ensure SOL vault remains rent-exempt after withdrawal
+    let rent = Rent::get()?;
+    let min_rent_balance = rent.minimum_balance(0);
+    let remaining_balance = vault_balance - sol_to_withdraw;
+    if remaining_balance < min_rent_balance {
+        return Err(EscrowError::NotRentExempt.into());
+    }
+
+    // Verify recipient wallet matches the one stored in global escrow
+    if recipient_wallet.key != &global_escrow.recipient_wallet {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // Transfer locked SOL from SOL vault to recipient wallet
+    **sol_vault_account.try_borrow_mut_lamports()? -= sol_to_withdraw;
+    **recipient_wallet.try_borrow_mut_lamports()? += sol_to_withdraw;
+
+    let mut updated_global_escrow = global_escrow;
+    updated_global_escrow.total_sol_withdrawn = updated_global_escrow
+        .total_sol_withdrawn
+        .checked_add(sol_to_withdraw)
+        .ok_or(EscrowError::AmountOverflow)?;
+    updated_global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &updated_global_escrow,
+        withdrawer,
+        AuditAction::WithdrawLockedSol,
+        &[investor_data.investor_pubkey.as_ref(), &sol_to_withdraw.to_le_bytes()],
+    )?;
+
+    // Update investor status to withdrawn
+    let mut updated_investor_data = investor_data;
+    updated_investor_data.status = InvestorStatus::SolWithdrawn;
+    updated_investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Locked SOL withdrawn: {} lamports to recipient wallet from SOL vault",
+        sol_to_withdraw
+    );
+    EscrowEvent::LockedSolWithdrawn {
+        global_escrow: *global_escrow_account.key,
+        investor: updated_investor_data.investor_pubkey,
+        recipient: *recipient_wallet.key,
+        sol_amount: sol_to_withdraw,
+        timestamp: current_timestamp,
+    }
+    .emit()?;
+    
+    Ok(())
+}
+
+pub fn process_close_sale(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let caller = next_account_info(account_info_iter)?; // recipient_wallet calling this
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let recipient_token_account = next_account_info(account_info_iter)?; // recipient's token account to receive unsold tokens
+    let token_program = next_account_info(account_info_iter)?;
+    let _clock = next_account_info(account_info_iter)?;
+
+    // Validate caller is signer
+    if !caller.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Validate token program
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Load global escrow data
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    global_escrow.check_withdrawals_open()?;
+
+    // Unsold tokens are reclaimed once; a refund after that doesn't reopen the count
+    if global_escrow.sale_closed {
+        msg!("Sale already closed");
+        return Err(EscrowError::SaleAlreadyClosed.into());
+    }
+
+    // AUTHORIZATION: Only recipient_wallet can close sale and reclaim unsold tokens
+    if caller.key != &global_escrow.recipient_wallet {
+        msg!("Only recipient wallet can close sale. Expected: {}, Found: {}", global_escrow.recipient_wallet, caller.key);
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // Check if sale has ended (using sale_end_timestamp from immutable config)
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp < global_escrow.sale_end_timestamp {
+        msg!("Sale has not ended yet. Current: {}, Sale ends: {}", current_timestamp, global_escrow.sale_end_timestamp);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // LOTTERY: winners' tokens are only set aside by the draw
+    if global_escrow.lottery_enabled
+        && !global_escrow.lottery_resolved
+        && !global_escrow.lottery_reveal_expired(current_timestamp)
+    {
+        msg!("Lottery has not been drawn yet");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // Calculate unsold tokens (the reserved allocation goes back to the recipient as well,
+    // unless it is earmarked for the AMM pool)
+    let returned_reserve = if global_escrow.provides_liquidity() { 0 } else { global_escrow.reserved_tokens };
+    let unsold_tokens = global_escrow
+        .total_tokens_available
+        .checked_sub(global_escrow.tokens_sold)
+        .and_then(|unsold| unsold.checked_add(returned_reserve))
+        .ok_or(EscrowError::AmountOverflow)?;
+
+    // Validate token vault PDA
+    let (expected_token_vault, _bump) = find_token_vault_pda(global_escrow_account.key, program_id);
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    // Never pay out tokens the vault holds for vesting claims, claim tickets or lottery winners
+    let vault_balance = read_token_account(token_vault_account)?.amount;
+    let unowed_balance = vault_balance.saturating_sub(global_escrow.tokens_owed_from_vault()?);
+    let unsold_tokens = unsold_tokens.min(unowed_balance);
+    
+    if unsold_tokens == 0 {
+        msg!("No unsold tokens to reclaim");
+        return Err(EscrowError::NotEnoughTokens.into());
+    }
+
+    // Unsold tokens may only land in the recipient wallet's own account of the sale mint
+    let recipient_token_data = read_token_account(recipient_token_account)?;
+    if recipient_token_data.mint != global_escrow.token_mint_pubkey
+        || recipient_token_data.owner != global_escrow.recipient_wallet
+    {
+        msg!(
+            "Recipient token account has mint {} and owner {}, expected {} and {}",
+            recipient_token_data.mint,
+            recipient_token_data.owner,
+            global_escrow.token_mint_pubkey,
+            global_escrow.recipient_wallet
+        );
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    // Transfer unsold tokens from token vault to recipient
+    transfer_escrow_tokens(
+        &global_escrow,
+        accounts,
+        token_program,
+        token_vault_account,
+        recipient_token_account,
+        global_escrow_account,
+        unsold_tokens,
+        &[&global_escrow.signer_seeds(&global_escrow.sale_id.to_le_bytes(), &[global_escrow.bump_seed])],
+    )?;
+
+    global_escrow.sale_closed = true;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        caller,
+        AuditAction::CloseSale,
+        &[recipient_token_account.key.as_ref(), &unsold_tokens.to_le_bytes()],
+    )?;
+
+    msg!(
+        "Sale closed: {} unsold tokens transferred to recipient wallet",
+        unsold_tokens
+    );
+    EscrowEvent::SaleClosed {
+        global_escrow: *global_escrow_account.key,
+        unsold_tokens,
+        tokens_sold: global_escrow.tokens_sold,
+        total_sol_deposited: global_escrow.total_sol_deposited,
+        timestamp: Clock::get()?.unix_timestamp,
+    }
+    .emit()?;
+    
+    Ok(())
+}
+
+pub fn process_claim_immediate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let recipient_vault = next_account_info(account_info_iter)?;
+    let destination = next_account_info(account_info_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    global_escrow.check_withdrawals_open()?;
+
+    // SECURITY: Only recipient_wallet can pull its immediate SOL
+    if recipient.key != &global_escrow.recipient_wallet {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_recipient_vault, _) = find_recipient_vault_pda(global_escrow_account.key, program_id);
+    if recipient_vault.key != &expected_recipient_vault || recipient_vault.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if destination.executable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // SAFETY: Ensure recipient vault remains rent-exempt after the claim
+    let rent = Rent::get()?;
+    let claimable = recipient_vault.lamports().saturating_sub(rent.minimum_balance(0));
+    let sol_to_claim = global_escrow.pending_immediate_sol.min(claimable);
+    if sol_to_claim == 0 {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    **recipient_vault.try_borrow_mut_lamports()? -= sol_to_claim;
+    **destination.try_borrow_mut_lamports()? += sol_to_claim;
+
+    global_escrow.pending_immediate_sol = global_escrow
+        .pending_immediate_sol
+        .checked_sub(sol_to_claim)
+        .ok_or(EscrowError::AmountOverflow)?;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        recipient,
+        AuditAction::ClaimImmediate,
+        &[destination.key.as_ref(), &sol_to_claim.to_le_bytes()],
+    )?;
+
+    msg!("Immediate SOL claimed: {} lamports to {}", sol_to_claim, destination.key);
+
+    Ok(())
+}
+
+pub fn process_sweep_stray_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let initializer = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let recipient_wallet = next_account_info(account_info_iter)?;
+
+    if !initializer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    global_escrow.check_withdrawals_open()?;
+
+    // SECURITY: Only initializer can sweep
+    if !global_escrow.is_authority(initializer.key) {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if recipient_wallet.key != &global_escrow.recipient_wallet {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    // Locked SOL lives in the per-investor SOL vaults, so anything the global escrow
+    // holds above its own rent-exempt minimum is stray
+    let rent = Rent::get()?;
+    let min_rent_balance = rent.minimum_balance(global_escrow_account.data_len());
+    let stray_lamports = global_escrow_account.lamports().saturating_sub(min_rent_balance);
+    if stray_lamports == 0 {
+        msg!("No stray SOL to sweep");
+        return Ok(());
+    }
+
+    **global_escrow_account.try_borrow_mut_lamports()? -= stray_lamports;
+    **recipient_wallet.try_borrow_mut_lamports()? += stray_lamports;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        initializer,
+        AuditAction::SweepStraySol,
+        &[&stray_lamports.to_le_bytes()],
+    )?;
+
+    msg!("Swept {} stray lamports to recipient wallet", stray_lamports);
+
+    Ok(())
+}
+
+pub fn process_add_liquidity(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let token_vault_account = next_account_info(account_info_iter)?;
+    let amm_token_account = next_account_info(account_info_iter)?;
+    let amm_pool = next_account_info(account_info_iter)?;
+    let amm_program = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_token_program(token_program.key) || system_program.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    if !global_escrow.is_initialized {
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    global_escrow.check_withdrawals_open()?;
+
+    // AUTHORIZATION: Only recipient_wallet can seed the pool with its SOL
+    if recipient.key != &global_escrow.recipient_wallet {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    if !global_escrow.provides_liquidity() || global_escrow.liquidity_added {
+        msg!("Liquidity provision not configured or already done");
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if current_timestamp < global_escrow.sale_end_timestamp {
+        msg!("Sale has not ended yet. Current: {}, Sale ends: {}", current_timestamp, global_escrow.sale_end_timestamp);
+        return Err(EscrowError::InvalidEscrowStatus.into());
+    }
+
+    // The pool accounts must be the configured ones
+    if amm_program.key != &global_escrow.amm_program
+        || amm_pool.key != &global_escrow.amm_pool
+        || amm_token_account.key != &global_escrow.amm_token_account
+    {
+        return Err(EscrowError::InvalidInstruction.into());
+    }
+    if !amm_program.executable {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let (expected_token_vault, _bump) = find_token_vault_pda(global_escrow_account.key, program_id);
+    if token_vault_account.key != &expected_token_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let token_amount = global_escrow.reserved_tokens;
+    if token_amount == 0 {
+        return Err(EscrowError::NotEnoughTokens.into());
+    }
+
+    let sol_amount = (global_escrow.total_sol_withdrawn as u128 * global_escrow.liquidity_sol_bps as u128 / 10000) as u64;
+    if sol_amount == 0 {
+        msg!("No SOL withdrawn yet to pair with the reserve");
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+    if recipient.lamports() < sol_amount {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let (sale_id_bytes, bump) = (global_escrow.sale_id.to_le_bytes(), [global_escrow.bump_seed]);
+    let global_escrow_seeds: &[&[u8]] = &global_escrow.signer_seeds(&sale_id_bytes, &bump);
+
+    // Move the reserve into the pool token account
+    transfer_escrow_tokens(
+        &global_escrow,
+        accounts,
+        token_program,
+        token_vault_account,
+        amm_token_account,
+        global_escrow_account,
+        token_amount,
+        &[global_escrow_seeds],
+    )?;
+
+    let seed_ix = Instruction {
+        program_id: *amm_program.key,
+        accounts: vec![
+            AccountMeta::new(*amm_pool.key, false),
+            AccountMeta::new(*amm_token_account.key, false),
+            AccountMeta::new(*recipient.key, true),
+            AccountMeta::new_readonly(*global_escrow_account.key, true),
+            AccountMeta::new_readonly(*token_program.key, false),
+            AccountMeta::new_readonly(*system_program.key, false),
+        ],
+        data: borsh::to_vec(&AmmInstruction::SeedPool { token_amount, sol_amount })?,
+    };
+
+    invoke_signed(
+        &seed_ix,
+        &[
+            amm_pool.clone(),
+            amm_token_account.clone(),
+            recipient.clone(),
+            global_escrow_account.clone(),
+            token_program.clone(),
+            system_program.clone(),
+            amm_program.clone(),
+        ],
+        &[global_escrow_seeds],
+    )?;
+
+    global_escrow.reserved_tokens = 0;
+    global_escrow.liquidity_added = true;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    record_audit_entry(
+        program_id,
+        accounts,
+        global_escrow_account,
+        &global_escrow,
+        recipient,
+        AuditAction::AddLiquidity,
+        &[amm_pool.key.as_ref(), &token_amount.to_le_bytes(), &sol_amount.to_le_bytes()],
+    )?;
+
+    msg!("Liquidity added: {} tokens and {} lamports to pool {}", token_amount, sol_amount, amm_pool.key);
+
+    Ok(())
+}
+
+pub fn process_release_held_sol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let investor_account = next_account_info(account_info_iter)?;
+    let sol_vault_account = next_account_info(account_info_iter)?;
+    let recipient_wallet = next_account_info(account_info_iter)?;
+
+    // SECURITY: Validate account owners before deserializing
+    if global_escrow_account.owner != program_id || investor_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    global_escrow.check_withdrawals_open()?;
+    let mut investor_data = InvestorAccount::load(&investor_account.data.borrow())?;
+
+    // The held SOL can only ever go to the stored recipient, so anyone may trigger this
+    if recipient_wallet.key != &global_escrow.recipient_wallet {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_investor_pda, _) = find_investor_pda(
+        &investor_data.investor_pubkey,
+        global_escrow_account.key,
+        program_id,
+    );
+    if investor_account.key != &expected_investor_pda || investor_data.global_escrow_pubkey != *global_escrow_account.key {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let (expected_sol_vault, _) = find_sol_vault_pda(&investor_data.investor_pubkey, global_escrow_account.key, program_id);
+    if sol_vault_account.key != &expected_sol_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if investor_data.in_cancellation_window(global_escrow.cancellation_window, current_timestamp) {
+        msg!("Deposit can still be cancelled, held SOL stays in the vault");
+        return Err(EscrowError::SolStillLocked.into());
+    }
+
+    // SOFT CAP: held SOL of a failed sale is refunded through RefundFailedSale
+    if global_escrow.soft_cap_failed(current_timestamp) {
+        return Err(EscrowError::SoftCapNotMet.into());
+    }
+
+    let sol_to_release = investor_data.held_immediate_sol;
+    if sol_to_release == 0 {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    // SAFETY: Ensure SOL vault remains rent-exempt after the release
+    let vault_balance = sol_vault_account.lamports();
+    let min_rent_balance = Rent::get()?.minimum_balance(0);
+    if vault_balance < sol_to_release || vault_balance - sol_to_release < min_rent_balance {
+        return Err(EscrowError::NotRentExempt.into());
+    }
+
+    **sol_vault_account.try_borrow_mut_lamports()? -= sol_to_release;
+    **recipient_wallet.try_borrow_mut_lamports()? += sol_to_release;
+
+    investor_data.held_immediate_sol = 0;
+    investor_data.serialize(&mut &mut investor_account.data.borrow_mut()[..])?;
+
+    msg!(
+        "Held SOL released: {} lamports to recipient wallet for investor {}",
+        sol_to_release,
+        investor_data.investor_pubkey
+    );
+
+    Ok(())
+}
+
+pub fn process_withdraw_locked_payment(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let recipient_wallet = next_account_info(account_info_iter)?;
+    let global_escrow_account = next_account_info(account_info_iter)?;
+    let payment_vault_account = next_account_info(account_info_iter)?;
+    let recipient_payment_account = next_account_info(account_info_iter)?;
+    let payment_mint = next_account_info(account_info_iter)?;
+    let payment_token_program = next_account_info(account_info_iter)?;
+    let _clock = next_account_info(account_info_iter)?;
+
+    if !recipient_wallet.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if global_escrow_account.owner != program_id {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    let mut global_escrow = GlobalEscrow::load(&global_escrow_account.data.borrow())?;
+    global_escrow.check_withdrawals_open()?;
+
+    if recipient_wallet.key != &global_escrow.recipient_wallet {
+        return Err(EscrowError::Unauthorized.into());
+    }
+
+    let (expected_payment_vault, _) = find_payment_vault_pda(global_escrow_account.key, program_id);
+    if payment_vault_account.key != &expected_payment_vault {
+        return Err(EscrowError::InvalidPDA.into());
+    }
+
+    if payment_mint.key != &global_escrow.payment_mint
+        || payment_vault_account.owner != payment_token_program.key
+        || !is_token_program(payment_token_program.key)
+    {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if !global_escrow.is_unlocked(current_timestamp) {
+        msg!(
+            "Payment still locked. Current: {}, Unlock at: {}",
+            current_timestamp,
+            global_escrow.global_unlock_timestamp()
+        );
+        return Err(EscrowError::SolStillLocked.into());
+    }
+
+    let amount = global_escrow
+        .total_payment_locked
+        .checked_sub(global_escrow.total_payment_withdrawn)
+        .ok_or(EscrowError::AmountOverflow)?;
+    if amount == 0 {
+        return Err(EscrowError::NoSolToWithdraw.into());
+    }
+
+    transfer_tokens_checked(
+        payment_token_program,
+        payment_vault_account,
+        payment_mint,
+        recipient_payment_account,
+        global_escrow_account,
+        amount,
+        read_mint_decimals(payment_mint)?,
+        &[&global_escrow.signer_seeds(&global_escrow.sale_id.to_le_bytes(), &[global_escrow.bump_seed])],
+    )?;
+
+    global_escrow.total_payment_withdrawn = global_escrow.total_payment_locked;
+    global_escrow.serialize(&mut &mut global_escrow_account.data.borrow_mut()[..])?;
+
+    msg!("Locked stablecoin withdrawn: {} units to recipient", amount);
+    Ok(())
+}
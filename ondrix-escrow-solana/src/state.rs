@@ -0,0 +1,1582 @@
+// Account layouts owned by the program and the helpers that read and update them
+
+use solana_program::{
+    entrypoint::ProgramResult,
+    msg,
+    hash::hashv,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::{
+    checked_mul_div, gcd, global_escrow_seeds, ConfigUpdate, EscrowError, ANALYTICS_BUCKETS,
+    ANALYTICS_FIRST_BUCKET_LAMPORTS, BPS_DENOMINATOR, CHAINLINK_USD_DECIMALS, CURVE_GROWTH_ONE,
+    CURVE_PRICE_SCALE, DEPOSIT_CPI_DEPTH, DEPOSIT_PERMIT_DOMAIN, GLOBAL_ESCROW_DISCRIMINATOR,
+    INVESTOR_ACCOUNT_DISCRIMINATOR, LOTTERY_REVEAL_WINDOW, MAX_INSTRUCTION_STACK_HEIGHT,
+    MAX_ORACLE_FEEDS, MAX_PRICE_TIERS, MAX_QUOTE_FEEDS, SYBIL_ATTESTATION_DOMAIN,
+    TOKEN_PRICE_USD_CENTS,
+};
+
+// Global escrow account - one per program/token mint combination
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    pub struct GlobalEscrow {
+        pub discriminator: [u8; 8],       // GLOBAL_ESCROW_DISCRIMINATOR
+        pub is_initialized: bool,
+        pub initializer_pubkey: Pubkey,
+        pub token_mint_pubkey: Pubkey,
+        pub recipient_wallet: Pubkey,     // Receives 50% of all SOL deposits
+        pub total_tokens_available: u64,
+        pub tokens_sold: u64,
+        pub total_sol_deposited: u64,
+        pub total_sol_withdrawn: u64,     // SOL withdrawn by initializer after lock periods
+        pub lock_duration: i64,           // Lock duration in seconds, counted from initialization_timestamp
+        pub bump_seed: u8,
+    
+        pub oracle_program_id: Pubkey,    // Chainlink or Pyth oracle program (see oracle_kind)
+        pub price_feed_pubkey: Pubkey,    // SOL/USD price feed
+    
+        pub min_sol_investment: u64,      // Minimum SOL investment
+        pub max_sol_investment: u64,      // Maximum SOL per address
+        pub price_staleness_threshold: u64, // Price staleness in seconds
+    
+        // SALE MANAGEMENT
+        pub sale_end_timestamp: i64,      // When sale ends (for unsold token reclaim)
+    
+        // GLOBAL TIMING
+        pub initialization_timestamp: i64, // When contract was initialized (for global unlock timing)
+    
+        // TRANSPARENCY
+        pub audit_log_enabled: bool,      // Once set, every privileged action must be written to the audit log
+    
+        // UNLOCK CONDITIONS
+        pub unlock_mode: UnlockMode,      // Which condition releases locked SOL
+        pub unlock_tokens_sold_target: u64, // tokens_sold milestone for target-based unlock
+    
+        // TIERED PRICING
+        pub price_tiers: [PriceTier; MAX_PRICE_TIERS], // Ascending tokens_sold thresholds
+        pub price_tier_count: u8,         // 0 = flat token price (flat_price_cents)
+    
+        // INVESTOR PROTECTION
+        pub inactivity_refund_delay: i64, // Seconds after global unlock before investors can self-refund (0 = disabled, older escrows)
+    
+        // PRICE SMOOTHING
+        pub use_ema: bool,                // Price deposits against the EMA instead of spot
+        pub ema_alpha_bps: u16,           // EMA weight of the newest price in basis points
+        pub ema_price: u64,               // Current EMA (8 decimals), 0 until seeded by the first deposit
+    
+        // DEPOSIT PROOFS
+        pub deposit_proofs_enabled: bool, // Persist a DepositProof PDA for every deposit
+    
+        // PROTOCOL FEE
+        pub fee_wallet: Pubkey,           // Receives the protocol fee
+        pub protocol_fee_bps: u16,        // Fee on each deposit in basis points (0 = disabled)
+        pub total_fees_collected: u64,    // Cumulative lamports sent to fee_wallet
+    
+        // DELEGATED DEPOSITS
+        pub allow_delegated_deposits: bool, // Allow multisig investors to deposit through an authorized executor
+    
+        // SALE ROUNDS (tokens_sold stays the lifetime counter across rounds)
+        pub current_round: u32,
+        pub round_tokens_cap: u64,        // Max tokens sellable in the current round (0 = no round cap)
+        pub round_tokens_sold: u64,       // Tokens sold in the current round, reset by AdvanceRound
+        pub max_total_tokens_sold: u64,   // Lifetime cap across all rounds (0 = vault supply only)
+    
+        // RESERVED ALLOCATION
+        pub reserved_tokens: u64,         // Held in the vault outside total_tokens_available
+    
+        // MULTI-ORACLE
+        pub use_multi_oracle: bool,       // Price against the median of oracle_feeds
+        pub oracle_feeds: [Pubkey; MAX_ORACLE_FEEDS], // Independent SOL/USD feeds on oracle_program_id
+        pub oracle_feed_count: u8,
+        pub max_feed_disagreement_bps: u16, // Max (highest - lowest) / median spread
+    
+        // DEPOSIT QUANTIZATION
+        pub deposit_quantum: u64,         // Lamports; 0 = any amount accepted
+        pub deposit_quantum_round_down: bool, // Round down and leave the remainder with the investor instead of rejecting
+    
+        // RECIPIENT FALLBACK
+        pub recipient_fallback_enabled: bool, // Route the immediate portion to the recipient vault when the recipient can't receive
+        pub pending_immediate_sol: u64,   // Lamports held in the recipient vault awaiting ClaimImmediate
+    
+        // RENT BUDGET
+        pub max_rent_per_investor: u64,   // Max lamports of account rent one deposit may charge; 0 = unbounded
+    
+        // PARTIAL FILLS
+        pub allow_partial_fill: bool,     // Fill what's left and leave the unfilled SOL with the investor
+    
+        // CPI DEPTH GUARD
+        pub max_caller_stack_height: u8,  // Highest stack height a deposit may be invoked at; 0 = runtime-safe default
+    
+        // HARD CAP
+        pub sol_hard_cap: u64,            // Max total_sol_deposited in lamports; 0 = no cap
+    
+        // SELF-DEALING
+        pub forbid_self_dealing: bool,    // Reject deposits from the initializer or recipient wallet
+    
+        // SOFT CAP
+        pub soft_cap_lamports: u64,       // Min total_sol_deposited by sale end for the sale to succeed; 0 = none
+    
+        // DEGRADED ORACLE MODE
+        pub degraded_mode_enabled: bool,  // Keep selling at reduced capacity on a stale feed
+        pub degraded_max_price_age: u64,  // Max age (seconds) of the cached price usable in degraded mode
+        pub degraded_max_deposit: u64,    // Per-transaction SOL limit while degraded
+        pub last_good_price: u64,         // Last fresh oracle price (8 decimals)
+        pub last_good_price_timestamp: i64, // Oracle timestamp of last_good_price
+    
+        // CUSTODIAN
+        pub custodian: Pubkey,            // Token accounts owned by this key may carry a delegate/close_authority; default = none
+    
+        // STAKING DELIVERY
+        pub staking_program: Pubkey,      // Stake purchased tokens through this program instead of delivering them; default = off
+        pub staking_pool: Pubkey,
+        pub staking_deposit_account: Pubkey, // Pool token account the purchased tokens are moved into
+    
+        // PAUSE / RAISE VELOCITY
+        pub is_paused: bool,              // Deposits rejected while set
+        pub velocity_window: i64,         // Seconds per evaluation window; 0 = velocity check off
+        pub min_raise_per_window: u64,    // Lamports a window must raise to avoid an auto-pause
+        pub velocity_window_start: i64,
+        pub velocity_window_raised: u64,  // Lamports raised in the current window
+    
+        // PERMIT DEPOSITS
+        pub allow_permit_deposits: bool,  // Accept relayed deposits authorized by an off-chain signed permit
+    
+        // QUOTE ASSET FEEDS
+        pub quote_feeds: [QuoteFeed; MAX_QUOTE_FEEDS], // quote_mint -> USD price feed; SOL falls back to price_feed_pubkey
+        pub quote_feed_count: u8,
+    
+        // LIQUIDITY PROVISION
+        pub amm_program: Pubkey,          // Seed a pool through this program after the sale; default = off
+        pub amm_pool: Pubkey,
+        pub amm_token_account: Pubkey,    // Pool token account the reserved tokens are moved into
+        pub liquidity_sol_bps: u16,       // Share of the withdrawn SOL the recipient adds to the pool
+        pub liquidity_added: bool,
+    
+        // CANCELLATION WINDOW
+        pub cancellation_window: i64,     // Seconds after a position's first deposit it may be cancelled; 0 = off
+    
+        // BONDING CURVE (replaces the price tiers while use_curve is set)
+        pub use_curve: bool,
+        pub curve_type: CurveType,
+        pub curve_base_price: u64,        // Price at tokens_sold = 0 (CURVE_PRICE_SCALE units)
+        pub curve_slope: u64,             // Linear: price added per whole token sold (CURVE_PRICE_SCALE units)
+        pub curve_growth_bps: u16,        // Exponential: price growth per step
+        pub curve_step: u64,              // Exponential: tokens (base units) per step
+    
+        // ORACLE KIND
+        pub oracle_kind: OracleKind,      // Which oracle oracle_program_id and the price feeds belong to
+    
+        // REFUND QUEUE (immediate SOL of a failed sale, repaid first-registered first-paid)
+        pub refund_queue_enabled: bool,
+        pub total_refunds_owed: u64,      // Registered refunds not yet paid out
+        pub refund_queue_tail: u64,       // Cumulative lamports ever registered; the next claim starts here
+        pub refund_queue_funded: u64,     // Cumulative lamports put into the refund vault
+    
+        // TOKEN DECIMALS
+        pub token_decimals: u8,           // Read from the token mint at initialization
+    
+        // DEPOSIT ANALYTICS
+        pub analytics_enabled: bool,      // Fold every deposit into the analytics PDA
+    
+        // TOKEN PRICE
+        pub token_price_cents: u64,       // Flat token price in USD cents (0 = TOKEN_PRICE_USD_CENTS)
+    
+        // LOTTERY
+        pub lottery_enabled: bool,        // Deposits become fixed-size entries drawn after sale end
+        pub lottery_entry_lamports: u64,  // SOL paid per entry
+        pub lottery_entry_tokens: u64,    // Tokens a winning entry receives
+        pub lottery_commitment: [u8; 32], // hash(secret) committed by the initializer before entries open
+        pub lottery_entry_count: u64,     // Entries recorded so far
+        pub lottery_resolved: bool,
+        pub lottery_seed: [u8; 32],       // hash(secret, slot hash, entry count), fixed at resolution
+        pub lottery_winner_count: u64,
+    
+        // DUTCH AUCTION
+        pub start_price_cents: u64,       // Price at initialization (0 = no auction)
+        pub end_price_cents: u64,         // Floor reached at sale end
+    
+        // VAULT SELF-CHECK
+        pub check_vault_on_deposit: bool, // Run the SelfAudit vault check before every deposit's token transfer
+    
+        // VESTING
+        pub vesting_enabled: bool,        // Deliver only part of each purchase at deposit, vest the rest
+        pub immediate_token_bps: u16,     // Share delivered at deposit while vesting is enabled
+    
+        // EARLY-BIRD BONUS
+        pub bonus_threshold_lamports: u64, // SOL raised below this earns the bonus
+        pub bonus_bps: u16,               // Extra tokens on the eligible part of a deposit (0 = off)
+    
+        // WHITELIST
+        pub whitelist_required: bool,     // Only investors with a whitelist entry PDA may deposit
+    
+        // EMERGENCY FREEZE
+        pub withdrawals_frozen: bool,     // Halts every SOL withdrawal and token claim; deposits unaffected
+    
+        // MERKLE WHITELIST
+        pub whitelist_root: [u8; 32],     // Root over keccak(investor) leaves; zero = no Merkle whitelist
+    
+        // VESTING MODE
+        pub vesting_mode: bool,           // Keep every purchased token in the vault until ClaimVestedTokens
+    
+        // CLAIM TICKETS
+        pub claim_ticket_mint: Pubkey,    // Receipt mint (authority: this PDA); default = tokens delivered at deposit
+        pub claim_tickets_outstanding: u64, // Tickets minted and not yet redeemed with ClaimTokens
+    
+        // VESTING CLIFF
+        pub cliff_duration: i64,          // Nothing vests before the vesting start + cliff_duration
+    
+        // DRIP SALE
+        pub tokens_per_bucket: u64,       // Tokens sellable per bucket (0 = no drip limit)
+        pub bucket_duration: i64,         // Bucket length in seconds, buckets aligned to initialization_timestamp
+        pub bucket_start: i64,            // Start of the bucket bucket_tokens_sold belongs to
+        pub bucket_tokens_sold: u64,
+    
+        // ADMIN AUTHORITY
+        pub authority: Pubkey,            // Current admin; default = initializer_pubkey, which stays as the PDA seed
+    
+        // INSTALLMENTS
+        pub installments_enabled: bool,   // Investors may open a Commitment and pay it in installments
+        pub installment_grace_period: i64, // Seconds an installment may be late before the commitment defaults
+        pub installment_penalty_bps: u16, // Collateral posted per commitment, forfeited to recipient on default
+    
+        // SALE ID
+        pub sale_id: u64,                 // Nonce in the PDA seeds, one escrow per (initializer, mint, sale_id)
+    
+        // SALE ECONOMICS
+        pub total_usd_value_cents: u64,   // USD value of the SOL paid for usd_valued_tokens, at each deposit's price
+        pub usd_valued_tokens: u64,       // Tokens sold through oracle-priced deposits (lottery awards excluded)
+    
+        // ESCROW CLOSURE
+        pub investor_accounts_tracked: bool, // open_investor_accounts counted since initialization (false for migrated escrows)
+        pub open_investor_accounts: u64,  // Investor PDAs created and not yet closed by BatchCloseInvestorAccounts
+    
+        // SYBIL DAMPENING
+        pub sybil_authority: Pubkey,      // Off-chain scoring service attesting wallet identities (zero = off)
+        pub max_sol_per_identity: u64,    // Lamports all wallets of one attested identity may deposit in total
+    
+        // DEFERRED PRICING
+        pub deferred_pricing: bool,       // Deposits go through CommitDeposit + RevealPrice only
+        pub reveal_window: i64,           // Seconds after a commit during which RevealPrice may price it
+    
+        // TOKEN PROGRAM
+        pub token_program_id: Pubkey,     // Program owning the sale mint and vault (zero = SPL Token, pre-Token-2022 escrows)
+    
+        // STABLECOIN DEPOSITS
+        pub payment_mint: Pubkey,         // Stablecoin accepted by DepositToken at 1 unit = 1 USD (zero = off)
+        pub total_payment_deposited: u64, // Stablecoin base units raised through DepositToken
+        pub total_payment_locked: u64,    // Locked half of those, held in the payment vault
+        pub total_payment_withdrawn: u64, // Locked stablecoin already released to the recipient
+    
+        // CPI DEPOSIT BLOCKING
+        pub disallow_cpi_deposits: bool,  // Deposits must be top-level instructions of the transaction
+    
+        // PRICE BAND
+        pub min_sol_usd_price: u64,       // SOL/USD prices (8 decimals) outside the band are rejected
+        pub max_sol_usd_price: u64,       // (both zero = unbounded, escrows created before the band)
+    
+        // FEED DECIMALS
+        pub price_feed_decimals: u8,      // Decimals of price_feed_pubkey read at init (zero = 8, older escrows)
+    
+        // DEPOSIT COOLDOWN
+        pub deposit_cooldown: i64,        // Seconds an investor waits between DepositSol calls (zero = no cooldown)
+    
+        // SALE CLOSE: CloseSale runs once and leaves every token still owed to investors
+        pub sale_closed: bool,
+        pub vesting_tokens_outstanding: u64, // Vesting tokens sold and not yet claimed or forfeited
+        pub lottery_tokens_outstanding: u64, // Winning entries' tokens not yet delivered by ClaimLottery
+    }
+}
+
+impl GlobalEscrow {
+    /// Decode global escrow data, rejecting data of any other account type
+    pub fn load(data: &[u8]) -> Result<Self, ProgramError> {
+        check_discriminator(data, &GLOBAL_ESCROW_DISCRIMINATOR)?;
+        Ok(Self::try_from_slice(data)?)
+    }
+    
+    /// End of the SOL time lock. Locks run from escrow initialization, not from each
+    /// deposit, so every investor's locked SOL unlocks at the same moment (a late
+    /// depositor is locked for less); only InvestorOverride's custom_lock_duration
+    /// shifts it for one investor, still counted from initialization
+    pub fn global_unlock_timestamp(&self) -> i64 {
+        self.initialization_timestamp + self.lock_duration
+    }
+    
+    /// Sold tokens the vault still holds for investors: unclaimed vesting, unredeemed
+    /// claim tickets and undelivered lottery wins
+    pub fn tokens_owed_from_vault(&self) -> Result<u64, ProgramError> {
+        self.vesting_tokens_outstanding
+            .checked_add(self.claim_tickets_outstanding)
+            .and_then(|owed| owed.checked_add(self.lottery_tokens_outstanding))
+            .ok_or_else(|| EscrowError::AmountOverflow.into())
+    }
+    
+    /// Fail every handler that moves SOL or tokens out while an emergency freeze is on
+    pub fn check_withdrawals_open(&self) -> ProgramResult {
+        if self.withdrawals_frozen {
+            msg!("Withdrawals are frozen");
+            return Err(EscrowError::WithdrawalsFrozen.into());
+        }
+        Ok(())
+    }
+    
+    /// Fail deposits and cancellations from sale end on, which fixes what the sale raised
+    pub fn check_sale_open(&self, now: i64) -> ProgramResult {
+        if now >= self.sale_end_timestamp {
+            msg!("Sale ended at {}", self.sale_end_timestamp);
+            return Err(EscrowError::SaleEnded.into());
+        }
+        Ok(())
+    }
+    
+    /// Whether locked SOL is withdrawable according to the configured unlock mode
+    pub fn is_unlocked(&self, current_timestamp: i64) -> bool {
+        let time_unlocked = current_timestamp >= self.global_unlock_timestamp();
+        let target_unlocked = self.unlock_tokens_sold_target > 0
+            && self.tokens_sold >= self.unlock_tokens_sold_target;
+        
+        match self.unlock_mode {
+            UnlockMode::Time => time_unlocked,
+            UnlockMode::TokensSoldTarget => target_unlocked,
+            UnlockMode::Either => time_unlocked || target_unlocked,
+        }
+    }
+    
+    /// Time-lock end for one investor: their override if set, else the global lock
+    pub fn investor_unlock_timestamp(&self, investor: &InvestorAccount) -> i64 {
+        if investor.custom_lock_duration > 0 {
+            self.initialization_timestamp + investor.custom_lock_duration
+        } else {
+            self.global_unlock_timestamp()
+        }
+    }
+    
+    /// Same as `is_unlocked`, but with the investor's lock override as the time component
+    pub fn is_unlocked_for(&self, investor: &InvestorAccount, current_timestamp: i64) -> bool {
+        let time_unlocked = current_timestamp >= self.investor_unlock_timestamp(investor);
+        let target_unlocked = self.unlock_tokens_sold_target > 0
+            && self.tokens_sold >= self.unlock_tokens_sold_target;
+        
+        match self.unlock_mode {
+            UnlockMode::Time => time_unlocked,
+            UnlockMode::TokensSoldTarget => target_unlocked,
+            UnlockMode::Either => time_unlocked || target_unlocked,
+        }
+    }
+    
+    /// Timestamp after which investors may reclaim their own locked SOL, if enabled
+    pub fn inactivity_deadline(&self) -> Option<i64> {
+        if self.inactivity_refund_delay > 0 {
+            Some(self.global_unlock_timestamp() + self.inactivity_refund_delay)
+        } else {
+            None
+        }
+    }
+    
+    /// Reject a sale that would exceed the current round cap or the lifetime cap
+    pub fn check_sale_caps(&self, tokens: u64) -> ProgramResult {
+        if self.round_tokens_cap > 0 {
+            let round_total = self.round_tokens_sold.checked_add(tokens).ok_or(EscrowError::AmountOverflow)?;
+            if round_total > self.round_tokens_cap {
+                msg!("Round {} cap exceeded: {} > {}", self.current_round, round_total, self.round_tokens_cap);
+                return Err(EscrowError::TokenCapExceeded.into());
+            }
+        }
+        
+        if self.max_total_tokens_sold > 0 {
+            let lifetime_total = self.tokens_sold.checked_add(tokens).ok_or(EscrowError::AmountOverflow)?;
+            if lifetime_total > self.max_total_tokens_sold {
+                msg!("Lifetime cap exceeded: {} > {}", lifetime_total, self.max_total_tokens_sold);
+                return Err(EscrowError::TokenCapExceeded.into());
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Tokens that can still be sold right now: remaining supply bounded by the
+    /// round and lifetime caps when they're set.
+    pub fn fillable_tokens(&self) -> u64 {
+        let mut fillable = self.total_tokens_available.saturating_sub(self.tokens_sold);
+        if self.round_tokens_cap > 0 {
+            fillable = fillable.min(self.round_tokens_cap.saturating_sub(self.round_tokens_sold));
+        }
+        if self.max_total_tokens_sold > 0 {
+            fillable = fillable.min(self.max_total_tokens_sold.saturating_sub(self.tokens_sold));
+        }
+        fillable
+    }
+    
+    /// Signer seeds (with bump) of this escrow's PDA, over buffers owned by the caller:
+    /// `sale_id_bytes` = sale_id.to_le_bytes(), `bump` = [bump_seed]
+    pub fn signer_seeds<'a>(&'a self, sale_id_bytes: &'a [u8; 8], bump: &'a [u8; 1]) -> Vec<&'a [u8]> {
+        let mut seeds = global_escrow_seeds(&self.initializer_pubkey, &self.token_mint_pubkey, sale_id_bytes);
+        seeds.push(bump);
+        seeds
+    }
+    
+    /// Key allowed to administer the escrow. Escrows created before TransferAuthority
+    /// carry a zeroed authority and stay administered by the initializer.
+    pub fn admin_authority(&self) -> Pubkey {
+        if self.authority == Pubkey::default() {
+            self.initializer_pubkey
+        } else {
+            self.authority
+        }
+    }
+    
+    pub fn is_authority(&self, key: &Pubkey) -> bool {
+        *key == self.admin_authority()
+    }
+    
+    /// Decimals the escrow's Chainlink SOL/USD feed reports with
+    pub fn feed_decimals(&self) -> u8 {
+        if self.price_feed_decimals == 0 {
+            CHAINLINK_USD_DECIMALS
+        } else {
+            self.price_feed_decimals
+        }
+    }
+    
+    /// Reject a SOL/USD price outside [min_sol_usd_price, max_sol_usd_price]
+    pub fn check_sol_usd_price(&self, price: u64) -> ProgramResult {
+        if self.max_sol_usd_price > 0 && !(self.min_sol_usd_price..=self.max_sol_usd_price).contains(&price) {
+            msg!(
+                "SOL/USD price {} outside {}..={}",
+                price,
+                self.min_sol_usd_price,
+                self.max_sol_usd_price
+            );
+            return Err(EscrowError::PriceOutOfBounds.into());
+        }
+        Ok(())
+    }
+    
+    /// Token program every token CPI of this escrow must go through
+    pub fn token_program_id(&self) -> Pubkey {
+        if self.token_program_id == Pubkey::default() {
+            spl_token::id()
+        } else {
+            self.token_program_id
+        }
+    }
+    
+    /// Start of the drip bucket containing `now`
+    pub fn current_bucket_start(&self, now: i64) -> i64 {
+        let elapsed = now.saturating_sub(self.initialization_timestamp).max(0);
+        self.initialization_timestamp + elapsed - elapsed % self.bucket_duration
+    }
+    
+    /// Tokens still sellable in the drip bucket containing `now`. A bucket that has
+    /// rolled over starts with its full allowance.
+    pub fn bucket_allowance(&self, now: i64) -> u64 {
+        if self.tokens_per_bucket == 0 {
+            return u64::MAX;
+        }
+        if self.bucket_start != self.current_bucket_start(now) {
+            return self.tokens_per_bucket;
+        }
+        self.tokens_per_bucket.saturating_sub(self.bucket_tokens_sold)
+    }
+    
+    /// Count a sale against the drip bucket containing `now`
+    pub fn record_bucket_sale(&mut self, tokens: u64, now: i64) -> ProgramResult {
+        if self.tokens_per_bucket == 0 {
+            return Ok(());
+        }
+        let bucket_start = self.current_bucket_start(now);
+        if self.bucket_start != bucket_start {
+            self.bucket_start = bucket_start;
+            self.bucket_tokens_sold = 0;
+        }
+        self.bucket_tokens_sold = self
+            .bucket_tokens_sold
+            .checked_add(tokens)
+            .ok_or(EscrowError::AmountOverflow)?;
+        Ok(())
+    }
+    
+    /// Whether lottery entry `index` (0-based) won. Entries are shuffled by the seeded
+    /// permutation `(stride * index + offset) mod entry_count` and the first
+    /// `lottery_winner_count` positions win, so exactly that many entries win and
+    /// anyone can recompute the draw from the stored seed.
+    pub fn lottery_entry_wins(&self, index: u64) -> bool {
+        let entry_count = self.lottery_entry_count;
+        if !self.lottery_resolved || index >= entry_count {
+            return false;
+        }
+        if self.lottery_winner_count >= entry_count {
+            return true;
+        }
+        
+        let seed_word = |i: usize| {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&self.lottery_seed[i * 8..(i + 1) * 8]);
+            u64::from_le_bytes(word)
+        };
+        
+        // Any stride coprime with entry_count makes the mapping a permutation;
+        // entry_count - 1 always is, which bounds the search
+        let mut stride = 1 + seed_word(0) % (entry_count - 1).max(1);
+        while gcd(stride, entry_count) != 1 {
+            stride += 1;
+        }
+        let offset = seed_word(1) % entry_count;
+        
+        let position = (stride as u128 * index as u128 + offset as u128) % entry_count as u128;
+        (position as u64) < self.lottery_winner_count
+    }
+    
+    /// Unresolved entries become refundable once the reveal window after sale end lapses
+    pub fn lottery_reveal_expired(&self, now: i64) -> bool {
+        !self.lottery_resolved && now > self.sale_end_timestamp.saturating_add(LOTTERY_REVEAL_WINDOW)
+    }
+    
+    /// A sale fails once it has ended below its soft cap. Only the locked half of each
+    /// deposit is refundable then; the immediate half has already reached the recipient.
+    /// Nothing adds to total_sol_deposited from sale end on, so the outcome can't change.
+    pub fn soft_cap_failed(&self, now: i64) -> bool {
+        self.soft_cap_lamports > 0
+            && now >= self.sale_end_timestamp
+            && self.total_sol_deposited < self.soft_cap_lamports
+    }
+    
+    /// Account a deposit against the raise-velocity window. When a window closes having
+    /// raised less than `min_raise_per_window`, the sale pauses itself after this deposit.
+    pub fn record_raise_velocity(&mut self, sol_amount: u64, now: i64) -> ProgramResult {
+        if self.velocity_window == 0 {
+            return Ok(());
+        }
+        
+        if self.velocity_window_start == 0 {
+            self.velocity_window_start = now;
+        }
+        
+        if now - self.velocity_window_start >= self.velocity_window {
+            if self.velocity_window_raised < self.min_raise_per_window {
+                msg!(
+                    "Raise velocity too low: {} < {} lamports per {}s, pausing deposits",
+                    self.velocity_window_raised,
+                    self.min_raise_per_window,
+                    self.velocity_window
+                );
+                self.is_paused = true;
+            }
+            self.velocity_window_start = now;
+            self.velocity_window_raised = sol_amount;
+        } else {
+            self.velocity_window_raised = self
+                .velocity_window_raised
+                .checked_add(sol_amount)
+                .ok_or(EscrowError::AmountOverflow)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Price feed for a deposit asset. Native SOL uses `price_feed_pubkey` unless the
+    /// map overrides it; other assets must be mapped explicitly.
+    pub fn price_feed_for(&self, quote_mint: &Pubkey) -> Option<Pubkey> {
+        self.quote_feeds[..self.quote_feed_count as usize]
+            .iter()
+            .find(|feed| &feed.quote_mint == quote_mint)
+            .map(|feed| feed.price_feed)
+            .or_else(|| (quote_mint == &spl_token::native_mint::id()).then_some(self.price_feed_pubkey))
+    }
+    
+    pub fn stakes_on_deposit(&self) -> bool {
+        self.staking_program != Pubkey::default()
+    }
+    
+    pub fn provides_liquidity(&self) -> bool {
+        self.amm_program != Pubkey::default()
+    }
+    
+    /// Highest stack height at which a deposit still has room for its own CPIs
+    pub fn deposit_stack_height_limit(&self) -> usize {
+        let safe_limit = MAX_INSTRUCTION_STACK_HEIGHT - DEPOSIT_CPI_DEPTH;
+        match self.max_caller_stack_height as usize {
+            0 => safe_limit,
+            configured => configured.min(safe_limit),
+        }
+    }
+    
+    /// Apply deposit quantization to a requested SOL amount. Misaligned amounts are
+    /// rejected, or rounded down to the quantum when round-down mode is set; the
+    /// remainder is simply never taken from the investor.
+    pub fn quantize_deposit(&self, sol_amount: u64) -> Result<u64, ProgramError> {
+        if self.deposit_quantum == 0 {
+            return Ok(sol_amount);
+        }
+        
+        let remainder = sol_amount % self.deposit_quantum;
+        if remainder == 0 {
+            return Ok(sol_amount);
+        }
+        
+        if !self.deposit_quantum_round_down {
+            msg!("Deposit {} is not a multiple of {} lamports", sol_amount, self.deposit_quantum);
+            return Err(EscrowError::DepositNotQuantized.into());
+        }
+        
+        let quantized = sol_amount - remainder;
+        msg!("Deposit rounded down to {} lamports ({} not taken)", quantized, remainder);
+        Ok(quantized)
+    }
+    
+    /// Stored flat price, or the 0.1 USD default for escrows created before it was configurable
+    pub fn flat_price_cents(&self) -> u64 {
+        if self.token_price_cents > 0 {
+            self.token_price_cents
+        } else {
+            TOKEN_PRICE_USD_CENTS
+        }
+    }
+    
+    pub fn issues_claim_tickets(&self) -> bool {
+        self.claim_ticket_mint != Pubkey::default()
+    }
+    
+    /// Split a purchase into (delivered now, vesting) per immediate_token_bps;
+    /// vesting_mode vests the whole purchase
+    pub fn split_vesting(&self, tokens: u64) -> Result<(u64, u64), ProgramError> {
+        if self.vesting_mode {
+            return Ok((0, tokens));
+        }
+        if !self.vesting_enabled {
+            return Ok((tokens, 0));
+        }
+        let immediate = checked_mul_div(tokens, self.immediate_token_bps as u64, BPS_DENOMINATOR)?;
+        Ok((immediate, tokens - immediate))
+    }
+    
+    /// Early-bird bonus on a deposit of `sol_amount` buying `tokens`: only the part of
+    /// the deposit raised below bonus_threshold_lamports earns it, capped so the purchase
+    /// stays within `fillable` (what is left to sell under every cap, drip bucket included)
+    pub fn early_bird_bonus(&self, sol_amount: u64, tokens: u64, fillable: u64) -> Result<u64, ProgramError> {
+        if self.bonus_bps == 0 || sol_amount == 0 || self.total_sol_deposited >= self.bonus_threshold_lamports {
+            return Ok(0);
+        }
+        let eligible_sol = (self.bonus_threshold_lamports - self.total_sol_deposited).min(sol_amount);
+        let eligible_tokens = checked_mul_div(tokens, eligible_sol, sol_amount)?;
+        let bonus = checked_mul_div(eligible_tokens, self.bonus_bps as u64, BPS_DENOMINATOR)?;
+        Ok(bonus.min(fillable.saturating_sub(tokens)))
+    }
+    
+    /// Price a deposit would pay right now, in CURVE_PRICE_SCALE units per whole token
+    pub fn current_token_price(&self, now: i64) -> Result<u128, ProgramError> {
+        if self.use_curve {
+            return self.curve_price_at(self.tokens_sold);
+        }
+        let price_cents = self
+            .dutch_auction_price_cents(now)
+            .unwrap_or_else(|| self.token_price_cents_at(self.tokens_sold));
+        Ok(price_cents as u128 * CURVE_PRICE_SCALE as u128)
+    }
+    
+    /// Dutch auction price at `now`: decays linearly from start_price_cents at
+    /// initialization to end_price_cents at sale end. None when no auction is set.
+    pub fn dutch_auction_price_cents(&self, now: i64) -> Option<u64> {
+        if self.start_price_cents == 0 {
+            return None;
+        }
+        
+        // A zero-length window has no time to decay over
+        let duration = self.sale_end_timestamp - self.initialization_timestamp;
+        if duration <= 0 {
+            return Some(self.end_price_cents);
+        }
+        
+        let elapsed = (now - self.initialization_timestamp).clamp(0, duration);
+        let decay = (self.start_price_cents - self.end_price_cents) as u128 * elapsed as u128 / duration as u128;
+        Some(self.start_price_cents - decay as u64)
+    }
+    
+    /// Token price in USD cents for the tier active at `tokens_sold`.
+    /// Past the last threshold the last tier's price keeps applying.
+    pub fn token_price_cents_at(&self, tokens_sold: u64) -> u64 {
+        let tiers = &self.price_tiers[..self.price_tier_count as usize];
+        match tiers.iter().find(|tier| tokens_sold < tier.tokens_sold_threshold) {
+            Some(tier) => tier.price_cents,
+            None => tiers.last().map_or(self.flat_price_cents(), |tier| tier.price_cents),
+        }
+    }
+    
+    /// Spot curve price at `tokens_sold` in CURVE_PRICE_SCALE units per whole token,
+    /// rounded up so integer pricing never undercharges
+    pub fn curve_price_at(&self, tokens_sold: u64) -> Result<u128, ProgramError> {
+        let unit = 10_u128.pow(self.token_decimals as u32);
+        let base = self.curve_base_price as u128;
+        match self.curve_type {
+            CurveType::Linear => {
+                let increase = (self.curve_slope as u128)
+                    .checked_mul(tokens_sold as u128)
+                    .ok_or(EscrowError::AmountOverflow)?
+                    .div_ceil(unit);
+                base.checked_add(increase).ok_or_else(|| EscrowError::AmountOverflow.into())
+            }
+            CurveType::Exponential => {
+                // base * (1 + growth)^steps by squaring, in CURVE_GROWTH_ONE fixed point
+                let mul_ceil = |a: u128, b: u128| -> Result<u128, ProgramError> {
+                    a.checked_mul(b)
+                        .map(|v| v.div_ceil(CURVE_GROWTH_ONE))
+                        .ok_or_else(|| EscrowError::AmountOverflow.into())
+                };
+                let mut factor = CURVE_GROWTH_ONE + CURVE_GROWTH_ONE / BPS_DENOMINATOR as u128 * self.curve_growth_bps as u128;
+                let mut growth = CURVE_GROWTH_ONE;
+                let mut steps = tokens_sold / self.curve_step.max(1);
+                while steps > 0 {
+                    if steps & 1 == 1 {
+                        growth = mul_ceil(growth, factor)?;
+                    }
+                    steps >>= 1;
+                    if steps > 0 {
+                        factor = mul_ceil(factor, factor)?;
+                    }
+                }
+                mul_ceil(base, growth)
+            }
+        }
+    }
+}
+
+// Price tier - applies while tokens_sold is below tokens_sold_threshold
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+    #[derive(Default)]
+    pub struct PriceTier {
+        pub tokens_sold_threshold: u64,
+        pub price_cents: u64,
+    }
+}
+
+// One entry of the quote_mint -> price_feed map
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+    #[derive(Default)]
+    pub struct QuoteFeed {
+        pub quote_mint: Pubkey,
+        pub price_feed: Pubkey,           // USD feed on oracle_program_id for quote_mint
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Default)]
+pub enum OracleKind {
+    #[default]
+    Chainlink,        // Chainlink OCR2 feed read through latest_round_data
+    Pyth,             // Pyth v2 price account owned by the Pyth program
+}
+
+// Bonding curve shape, priced per whole token against tokens_sold
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Default)]
+pub enum CurveType {
+    #[default]
+    Linear,           // base + slope * tokens_sold / 10^token_decimals
+    Exponential,      // base * (1 + growth_bps / 10000) ^ (tokens_sold / curve_step), flat within a step
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Default)]
+pub enum UnlockMode {
+    #[default]
+    Time,             // Global time lock only
+    TokensSoldTarget, // Only once tokens_sold reaches unlock_tokens_sold_target
+    Either,           // Whichever comes first
+}
+
+// Per-investor account - one per investor per global escrow
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+    pub struct InvestorAccount {
+        pub discriminator: [u8; 8],       // INVESTOR_ACCOUNT_DISCRIMINATOR
+        pub is_initialized: bool,
+        pub investor_pubkey: Pubkey,
+        pub global_escrow_pubkey: Pubkey,
+        pub sol_deposited: u64,           // Total SOL deposited by this investor
+        pub tokens_received: u64,         // All tokens bought (delivered immediately or vesting)
+        pub deposit_timestamp: i64,       // First deposit of the position (later deposits update last_deposit_timestamp)
+        pub sol_usd_price: u64,          // SOL price at deposit time (8 decimals)
+        pub status: InvestorStatus,
+        pub bump_seed: u8,
+        pub deposit_count: u64,           // Number of deposits made (index of the next deposit proof)
+        pub last_deposit_timestamp: i64,  // Most recent deposit (the cooldown runs from it)
+        pub held_immediate_sol: u64,      // Immediate portion kept in the SOL vault until the window passes
+        pub refund_registered: bool,      // Claim placed in the refund queue (once per position)
+        pub refund_owed: u64,             // Queued refund not yet paid
+        pub refund_queue_position: u64,   // Queue offset of the first unpaid lamport of this claim
+        pub custom_lock_duration: i64,    // Per-investor lock set by the initializer (0 = global lock)
+        pub lottery_entry: u64,           // 1-based lottery entry index (0 = no entry)
+        pub lottery_claimed: bool,        // Winning tokens delivered or losing entry refunded
+        pub tokens_vesting: u64,          // Part of tokens_received left in the vault to vest
+        pub tokens_claimed: u64,          // Vested tokens already claimed
+        pub avg_sol_usd_price: u64,       // SOL price weighted by lamports over all deposits (8 decimals)
+        pub price_round_id: u32,          // Chainlink round sol_usd_price was read from (0 = not a single round)
+        pub beneficiary: Pubkey,          // Recovery address allowed to claim after the inactivity deadline (zero = none)
+        pub payment_mint: Pubkey,         // Stablecoin paid through DepositToken (zero = SOL only)
+        pub payment_deposited: u64,       // Stablecoin base units paid through DepositToken
+        pub vesting_start: i64,           // Token-weighted purchase time of tokens_vesting (zero = deposit_timestamp)
+    }
+}
+
+impl InvestorAccount {
+    /// Decode investor account data, rejecting data of any other account type
+    pub fn load(data: &[u8]) -> Result<Self, ProgramError> {
+        check_discriminator(data, &INVESTOR_ACCOUNT_DISCRIMINATOR)?;
+        Ok(Self::try_from_slice(data)?)
+    }
+    
+    pub fn get_locked_sol_amount(&self) -> u64 {
+        self.sol_deposited / 2  // 50% of deposited SOL is locked
+    }
+    
+    /// Whether `signer` may claim for this position: the investor always, the
+    /// designated beneficiary only once `recovery_open` (inactivity deadline passed)
+    pub fn is_claimant(&self, signer: &Pubkey, recovery_open: bool) -> bool {
+        *signer == self.investor_pubkey
+            || (recovery_open && self.beneficiary != Pubkey::default() && *signer == self.beneficiary)
+    }
+    
+    /// Provisioned (e.g. by BatchInitInvestors) but never deposited into nor entered in the lottery
+    pub fn is_unused(&self) -> bool {
+        self.status == InvestorStatus::Uninitialized
+            && self.sol_deposited == 0
+            && self.payment_deposited == 0
+            && self.lottery_entry == 0
+    }
+    
+    /// Position fully settled: locked SOL gone and every token delivered
+    pub fn is_terminal(&self) -> bool {
+        matches!(self.status, InvestorStatus::SolWithdrawn | InvestorStatus::Refunded)
+            && self.tokens_claimed >= self.tokens_vesting
+    }
+    
+    /// Prepare the position for a deposit at `now`. Provisioned (BatchInitInvestors) and
+    /// fully refunded positions start over as a fresh deposit; a refunded position still
+    /// carrying amounts (inactivity refund, lost lottery entry) takes no new deposits.
+    pub fn reopen_for_deposit(&mut self, now: i64) -> ProgramResult {
+        match self.status {
+            InvestorStatus::Uninitialized => {}
+            InvestorStatus::Refunded
+                if self.sol_deposited == 0 && self.tokens_received == 0 && self.payment_deposited == 0 => {}
+            InvestorStatus::Refunded => {
+                msg!("Refunded position can't take new deposits");
+                return Err(EscrowError::InvalidEscrowStatus.into());
+            }
+            _ => return Ok(()),
+        }
+        self.deposit_timestamp = now;
+        self.status = InvestorStatus::Deposited;
+        Ok(())
+    }
+    
+    /// Vesting tokens still in the vault: owed to this position but not yet claimed
+    pub fn unclaimed_vesting(&self) -> u64 {
+        self.tokens_vesting.saturating_sub(self.tokens_claimed)
+    }
+    
+    /// Part of tokens_received that reached the investor, i.e. all but the unclaimed
+    /// vesting, which never left the vault
+    pub fn tokens_in_hand(&self) -> u64 {
+        self.tokens_received.saturating_sub(self.unclaimed_vesting())
+    }
+    
+    /// Start of the vesting schedule; positions from before vesting_start vest from the first deposit
+    pub fn vesting_start_timestamp(&self) -> i64 {
+        if self.vesting_start != 0 { self.vesting_start } else { self.deposit_timestamp }
+    }
+    
+    /// Add `tokens` bought at `now` to the vesting position. The schedule start moves to
+    /// the token-weighted average purchase time, so a top-up vests over the lock instead
+    /// of inheriting the first deposit's clock.
+    pub fn add_vesting(&mut self, tokens: u64, now: i64) -> ProgramResult {
+        if tokens == 0 {
+            return Ok(());
+        }
+        let total = self
+            .tokens_vesting
+            .checked_add(tokens)
+            .ok_or(EscrowError::AmountOverflow)?;
+        let weighted_start = (self.vesting_start_timestamp() as i128 * self.tokens_vesting as i128
+            + now as i128 * tokens as i128)
+            / total as i128;
+        self.vesting_start = i64::try_from(weighted_start).map_err(|_| EscrowError::AmountOverflow)?;
+        self.tokens_vesting = total;
+        Ok(())
+    }
+    
+    /// Vesting tokens released by `now`: nothing until the cliff, then linear up to
+    /// vesting start + `lock_duration`
+    pub fn vested_tokens(&self, lock_duration: i64, cliff_duration: i64, now: i64) -> u64 {
+        let elapsed = now.saturating_sub(self.vesting_start_timestamp());
+        if lock_duration <= 0 || elapsed >= lock_duration {
+            return self.tokens_vesting;
+        }
+        if elapsed <= cliff_duration {
+            return 0;
+        }
+        (self.tokens_vesting as u128 * (elapsed - cliff_duration) as u128 / (lock_duration - cliff_duration) as u128) as u64
+    }
+    
+    /// Whether the whole position may still be cancelled. The window runs from the first
+    /// deposit (deposit_timestamp), as a top-up must not reopen it for older deposits.
+    pub fn in_cancellation_window(&self, cancellation_window: i64, now: i64) -> bool {
+        cancellation_window > 0 && now < self.deposit_timestamp.saturating_add(cancellation_window)
+    }
+    
+    /// Part of the queued refund the refund vault has been funded for so far
+    pub fn refund_claimable(&self, refund_queue_funded: u64) -> u64 {
+        let claim_end = self.refund_queue_position.saturating_add(self.refund_owed);
+        refund_queue_funded.min(claim_end).saturating_sub(self.refund_queue_position)
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Default)]
+pub enum InvestorStatus {
+    #[default]
+    Uninitialized,
+    Deposited,        // SOL deposited, tokens received, SOL locked
+    SolWithdrawn,     // Locked SOL has been withdrawn by initializer
+    Refunded,         // Locked SOL has been returned to the investor
+}
+
+// Privileged actions recorded in the audit log
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AuditAction {
+    AuditLogEnabled,
+    WithdrawLockedSol,
+    CloseSale,
+    UpdateConfig,
+    AdvanceRound,
+    ClaimImmediate,
+    SweepStraySol,
+    QueueConfigUpdate,
+    SetPaused,
+    AddLiquidity,
+    InvestorOverride,
+    ResolveLottery,
+    SetWithdrawalsFrozen,
+    TransferAuthority,
+}
+
+// Single audit log entry: (action_code, actor, timestamp, param_hash)
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy)]
+    pub struct AuditEntry {
+        pub action: AuditAction,
+        pub actor: Pubkey,
+        pub timestamp: i64,
+        pub param_hash: [u8; 32],        // sha256 of the action parameters
+    }
+}
+
+// Audit log header - entries follow the header as a ring of AuditEntry::LEN slots
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct AuditLogHeader {
+        pub is_initialized: bool,
+        pub global_escrow_pubkey: Pubkey,
+        pub total_entries: u64,           // Entries ever written (index of next write = total_entries % capacity at cap)
+        pub capacity: u32,                // Entries currently allocated
+        pub bump_seed: u8,
+    }
+}
+
+impl AuditLogHeader {
+    pub fn account_size(capacity: usize) -> usize {
+        Self::LEN + capacity * AuditEntry::LEN
+    }
+}
+
+// Persisted, write-once record of a single deposit for off-chain verification
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct DepositProof {
+        pub is_initialized: bool,
+        pub investor_pubkey: Pubkey,
+        pub global_escrow_pubkey: Pubkey,
+        pub deposit_index: u64,           // Position in the investor's deposit sequence
+        pub sol_amount: u64,
+        pub tokens_received: u64,
+        pub sol_usd_price: u64,           // Price the deposit was executed at (8 decimals)
+        pub timestamp: i64,
+        pub slot: u64,
+        pub proof_hash: [u8; 32],         // sha256 over all fields above
+    }
+}
+
+impl DepositProof {
+    /// Hash of every economically relevant field, recomputable off-chain
+    pub fn compute_hash(&self) -> [u8; 32] {
+        hashv(&[
+            self.investor_pubkey.as_ref(),
+            self.global_escrow_pubkey.as_ref(),
+            &self.deposit_index.to_le_bytes(),
+            &self.sol_amount.to_le_bytes(),
+            &self.tokens_received.to_le_bytes(),
+            &self.sol_usd_price.to_le_bytes(),
+            &self.timestamp.to_le_bytes(),
+            &self.slot.to_le_bytes(),
+        ])
+        .to_bytes()
+    }
+}
+
+// Who the deposit is for and who signs/funds it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepositMode {
+    Direct,    // Investor signs and pays
+    Delegated, // Registered executor signs and pays for the investor identity
+    Permit,    // Relayer signs; SOL comes from the investor's permit vault (validated by the caller)
+    Deferred,  // Investor signs; SOL was committed earlier and is priced now (RevealPrice)
+}
+
+// Executor authorized by an investor identity (e.g. a Squads vault) to deposit on its behalf
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct DepositDelegate {
+        pub is_initialized: bool,
+        pub investor_pubkey: Pubkey,      // On-chain investor identity
+        pub global_escrow_pubkey: Pubkey,
+        pub executor: Pubkey,             // Signer allowed to deposit and pay for the identity
+        pub bump_seed: u8,
+    }
+}
+
+// Permissioned sales: an investor may deposit only while this PDA exists
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct WhitelistEntry {
+        pub is_initialized: bool,
+        pub investor_pubkey: Pubkey,
+        pub global_escrow_pubkey: Pubkey,
+        pub bump_seed: u8,
+    }
+}
+
+// Investor's promise to deposit total_lamports in equal installments, one per interval
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct Commitment {
+        pub is_initialized: bool,
+        pub investor_pubkey: Pubkey,
+        pub global_escrow_pubkey: Pubkey,
+        pub total_lamports: u64,
+        pub installment_lamports: u64,
+        pub installment_interval: i64,    // Seconds between due dates; the first is due at start_timestamp
+        pub start_timestamp: i64,
+        pub paid_lamports: u64,           // SOL actually taken by the deposits so far
+        pub installments_paid: u64,
+        pub penalty_lamports: u64,        // Collateral held on this account, forfeited on a missed installment
+        pub bump_seed: u8,
+    }
+}
+
+impl Commitment {
+    /// When the next unpaid installment is due
+    pub fn next_due_timestamp(&self) -> i64 {
+        self.start_timestamp
+            .saturating_add(self.installment_interval.saturating_mul(self.installments_paid as i64))
+    }
+    
+    pub fn is_complete(&self) -> bool {
+        self.paid_lamports >= self.total_lamports
+    }
+    
+    /// Next installment overdue past the grace period
+    pub fn is_missed(&self, grace_period: i64, now: i64) -> bool {
+        !self.is_complete() && now > self.next_due_timestamp().saturating_add(grace_period)
+    }
+}
+
+// SOL committed for a deposit that is priced later, at RevealPrice
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct DeferredDeposit {
+        pub is_initialized: bool,
+        pub investor_pubkey: Pubkey,
+        pub global_escrow_pubkey: Pubkey,
+        pub sol_amount: u64,              // Lamports held on the PDA on top of its rent
+        pub commit_slot: u64,             // Reveal must land in a later slot
+        pub commit_timestamp: i64,
+        pub reveal_deadline: i64,         // After this only ReclaimExpiredCommit applies
+        pub bump_seed: u8,
+    }
+}
+
+// Off-chain authorization for a relayed deposit, signed by the investor with Ed25519
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+    pub struct DepositPermit {
+        pub global_escrow: Pubkey,
+        pub investor: Pubkey,
+        pub sol_amount: u64,
+        pub nonce: u64,                   // Must equal the permit vault's next_nonce
+        pub expiry: i64,                  // Unix timestamp after which the permit is void
+    }
+}
+
+impl DepositPermit {
+    /// Exact bytes the investor signs: domain prefix followed by the borsh-encoded permit
+    pub fn signed_message(&self) -> Result<Vec<u8>, ProgramError> {
+        let mut message = DEPOSIT_PERMIT_DOMAIN.to_vec();
+        message.extend_from_slice(&borsh::to_vec(self)?);
+        Ok(message)
+    }
+}
+
+// Binding of a wallet to an identity scored off-chain, signed by the sybil authority with Ed25519
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+    pub struct SybilAttestation {
+        pub global_escrow: Pubkey,
+        pub wallet: Pubkey,               // Investor identity that deposits
+        pub identity: [u8; 32],           // Opaque id shared by every wallet of one person
+        pub expiry: i64,                  // Unix timestamp after which the attestation is void
+    }
+}
+
+impl SybilAttestation {
+    /// Exact bytes the sybil authority signs: domain prefix followed by the borsh-encoded attestation
+    pub fn signed_message(&self) -> Result<Vec<u8>, ProgramError> {
+        let mut message = SYBIL_ATTESTATION_DOMAIN.to_vec();
+        message.extend_from_slice(&borsh::to_vec(self)?);
+        Ok(message)
+    }
+}
+
+// Cumulative SOL deposited by all wallets attested to one identity
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct SybilIdentity {
+        pub is_initialized: bool,
+        pub global_escrow_pubkey: Pubkey,
+        pub identity: [u8; 32],
+        pub total_sol_deposited: u64,     // Never reduced by refunds
+        pub bump_seed: u8,
+    }
+}
+
+// Investor-funded SOL that permit deposits draw from, so relayers never front the SOL
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct PermitVault {
+        pub is_initialized: bool,
+        pub investor_pubkey: Pubkey,
+        pub global_escrow_pubkey: Pubkey,
+        pub next_nonce: u64,              // Replay protection: each permit consumes one nonce
+        pub bump_seed: u8,
+    }
+}
+
+// Config change queued behind the timelock, readable by clients before it takes effect
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct PendingAction {
+    pub is_initialized: bool,
+    pub global_escrow_pubkey: Pubkey,
+    pub update: ConfigUpdate,
+    pub queued_at: i64,
+    pub activation_timestamp: i64,    // Earliest time ExecuteConfigUpdate may apply it
+    pub bump_seed: u8,
+}
+
+// Write-once archive of the raw escrow (and optionally one investor) state at a given slot
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct StateSnapshot {
+    pub is_initialized: bool,
+    pub global_escrow_pubkey: Pubkey,
+    pub snapshot_id: u64,
+    pub creator: Pubkey,
+    pub slot: u64,
+    pub timestamp: i64,
+    pub global_escrow_data: Vec<u8>,  // Raw GlobalEscrow account bytes
+    pub investor_account_pubkey: Pubkey, // Default when no investor was captured
+    pub investor_data: Vec<u8>,       // Raw InvestorAccount bytes, empty when none
+    pub snapshot_hash: [u8; 32],      // sha256 over all fields above
+    pub bump_seed: u8,
+}
+
+impl StateSnapshot {
+    pub fn account_size(global_escrow_len: usize, investor_len: usize) -> usize {
+        1 + 32 + 8 + 32 + 8 + 8 + 4 + global_escrow_len + 32 + 4 + investor_len + 32 + 1
+    }
+
+    /// Hash of the captured state, recomputable off-chain
+    pub fn compute_hash(&self) -> [u8; 32] {
+        hashv(&[
+            self.global_escrow_pubkey.as_ref(),
+            &self.snapshot_id.to_le_bytes(),
+            self.creator.as_ref(),
+            &self.slot.to_le_bytes(),
+            &self.timestamp.to_le_bytes(),
+            &self.global_escrow_data,
+            self.investor_account_pubkey.as_ref(),
+            &self.investor_data,
+        ])
+        .to_bytes()
+    }
+}
+
+// Running deposit-size histogram and summary stats, one per global escrow
+fixed_len! {
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct DepositAnalytics {
+        pub is_initialized: bool,
+        pub global_escrow_pubkey: Pubkey,
+        pub buckets: [u64; ANALYTICS_BUCKETS], // Deposit counts per size bucket
+        pub deposit_count: u64,
+        pub total_lamports: u64,
+        pub min_deposit: u64,
+        pub max_deposit: u64,
+        pub bump_seed: u8,
+    }
+}
+
+impl DepositAnalytics {
+    /// Bucket i holds deposits below ANALYTICS_FIRST_BUCKET_LAMPORTS * 10^i; the last one is open-ended
+    pub fn bucket_for(sol_amount: u64) -> usize {
+        let mut upper_bound = ANALYTICS_FIRST_BUCKET_LAMPORTS;
+        let mut bucket = 0;
+        while bucket < ANALYTICS_BUCKETS - 1 && sol_amount >= upper_bound {
+            upper_bound = upper_bound.saturating_mul(10);
+            bucket += 1;
+        }
+        bucket
+    }
+    
+    pub fn record(&mut self, sol_amount: u64) -> ProgramResult {
+        let bucket = Self::bucket_for(sol_amount);
+        self.buckets[bucket] = self.buckets[bucket]
+            .checked_add(1)
+            .ok_or(EscrowError::AmountOverflow)?;
+        self.min_deposit = if self.deposit_count == 0 { sol_amount } else { self.min_deposit.min(sol_amount) };
+        self.max_deposit = self.max_deposit.max(sol_amount);
+        self.deposit_count = self
+            .deposit_count
+            .checked_add(1)
+            .ok_or(EscrowError::AmountOverflow)?;
+        self.total_lamports = self
+            .total_lamports
+            .checked_add(sol_amount)
+            .ok_or(EscrowError::AmountOverflow)?;
+        Ok(())
+    }
+}
+
+/// Fail unless `data` starts with the account type's `discriminator`
+pub fn check_discriminator(data: &[u8], discriminator: &[u8; 8]) -> ProgramResult {
+    if !data.starts_with(discriminator) {
+        msg!("Account discriminator mismatch");
+        return Err(EscrowError::AccountDiscriminatorMismatch.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DISCRIMINATOR_LEN, SOL_LAMPORTS};
+
+    fn escrow_error(err: EscrowError) -> ProgramError {
+        err.into()
+    }
+
+    fn vesting_position(tokens_vesting: u64, vesting_start: i64) -> InvestorAccount {
+        InvestorAccount {
+            status: InvestorStatus::Deposited,
+            tokens_received: tokens_vesting,
+            tokens_vesting,
+            deposit_timestamp: vesting_start,
+            vesting_start,
+            ..InvestorAccount::default()
+        }
+    }
+
+    #[test]
+    fn soft_cap_outcome_is_fixed_at_sale_end() {
+        let mut escrow = GlobalEscrow { sale_end_timestamp: 1_000, soft_cap_lamports: 100, ..GlobalEscrow::default() };
+        escrow.total_sol_deposited = 99;
+
+        assert_eq!(escrow.check_sale_open(999), Ok(()));
+        assert!(!escrow.soft_cap_failed(999));
+
+        // The second deposits close the sale stands failed, with no window in between
+        assert_eq!(escrow.check_sale_open(1_000), Err(escrow_error(EscrowError::SaleEnded)));
+        assert!(escrow.soft_cap_failed(1_000));
+
+        escrow.total_sol_deposited = 100;
+        assert!(!escrow.soft_cap_failed(1_000));
+    }
+
+    #[test]
+    fn unlock_modes_by_time_target_and_either() {
+        let mut escrow = GlobalEscrow {
+            initialization_timestamp: 1_000,
+            lock_duration: 100,
+            unlock_tokens_sold_target: 500,
+            ..GlobalEscrow::default()
+        };
+        let unlocked = |escrow: &mut GlobalEscrow, mode| {
+            escrow.unlock_mode = mode;
+            (escrow.is_unlocked(1_099), escrow.is_unlocked(1_100))
+        };
+
+        // Target not reached: only the time lock can release
+        escrow.tokens_sold = 499;
+        assert_eq!(unlocked(&mut escrow, UnlockMode::Time), (false, true));
+        assert_eq!(unlocked(&mut escrow, UnlockMode::TokensSoldTarget), (false, false));
+        assert_eq!(unlocked(&mut escrow, UnlockMode::Either), (false, true));
+
+        // Target reached before the lock ends
+        escrow.tokens_sold = 500;
+        assert_eq!(unlocked(&mut escrow, UnlockMode::Time), (false, true));
+        assert_eq!(unlocked(&mut escrow, UnlockMode::TokensSoldTarget), (true, true));
+        assert_eq!(unlocked(&mut escrow, UnlockMode::Either), (true, true));
+
+        // A zero target never unlocks
+        escrow.unlock_tokens_sold_target = 0;
+        assert_eq!(unlocked(&mut escrow, UnlockMode::TokensSoldTarget), (false, false));
+    }
+
+    #[test]
+    fn quantize_deposit_rejects_or_rounds_misaligned_amounts() {
+        let quantum = SOL_LAMPORTS / 10;
+        let mut escrow = GlobalEscrow { deposit_quantum: quantum, ..GlobalEscrow::default() };
+
+        // Reject mode
+        assert_eq!(escrow.quantize_deposit(3 * quantum), Ok(3 * quantum));
+        assert_eq!(escrow.quantize_deposit(3 * quantum + 1), Err(escrow_error(EscrowError::DepositNotQuantized)));
+
+        // Round-down mode only ever takes less than requested
+        escrow.deposit_quantum_round_down = true;
+        assert_eq!(escrow.quantize_deposit(3 * quantum), Ok(3 * quantum));
+        assert_eq!(escrow.quantize_deposit(4 * quantum - 1), Ok(3 * quantum));
+        assert_eq!(escrow.quantize_deposit(quantum - 1), Ok(0));
+
+        escrow.deposit_quantum = 0;
+        assert_eq!(escrow.quantize_deposit(3 * quantum + 1), Ok(3 * quantum + 1));
+    }
+
+    #[test]
+    fn refunded_position_reopens_only_when_empty() {
+        let mut investor = InvestorAccount { status: InvestorStatus::Refunded, deposit_timestamp: 1_000, ..InvestorAccount::default() };
+        investor.reopen_for_deposit(2_000).unwrap();
+        assert_eq!(investor.status, InvestorStatus::Deposited);
+        assert_eq!(investor.deposit_timestamp, 2_000);
+
+        // A top-up keeps the first deposit's timestamp
+        investor.reopen_for_deposit(3_000).unwrap();
+        assert_eq!(investor.deposit_timestamp, 2_000);
+
+        let mut leftover = InvestorAccount { status: InvestorStatus::Refunded, sol_deposited: SOL_LAMPORTS, ..InvestorAccount::default() };
+        assert_eq!(leftover.reopen_for_deposit(2_000), Err(escrow_error(EscrowError::InvalidEscrowStatus)));
+    }
+
+    #[test]
+    fn cancellation_window_runs_from_first_deposit() {
+        let investor = InvestorAccount { deposit_timestamp: 1_000, last_deposit_timestamp: 1_050, ..InvestorAccount::default() };
+
+        assert!(investor.in_cancellation_window(60, 1_059));
+        assert!(!investor.in_cancellation_window(60, 1_060));
+        assert!(!investor.in_cancellation_window(0, 1_000));
+    }
+
+    #[test]
+    fn vesting_starts_at_first_deposit_for_older_positions() {
+        let mut investor = vesting_position(1_000, 0);
+        investor.deposit_timestamp = 500;
+
+        assert_eq!(investor.vesting_start_timestamp(), 500);
+        assert_eq!(investor.vested_tokens(100, 0, 550), 500);
+    }
+
+    #[test]
+    fn top_up_re_anchors_vesting() {
+        let mut investor = vesting_position(100, 1_000);
+        investor.add_vesting(100, 2_000).unwrap();
+
+        assert_eq!(investor.tokens_vesting, 200);
+        assert_eq!(investor.vesting_start, 1_500);
+        assert_eq!(investor.vested_tokens(1_000, 0, 2_000), 100);
+
+        investor.add_vesting(0, 9_000).unwrap();
+        assert_eq!(investor.vesting_start, 1_500);
+    }
+
+    #[test]
+    fn tokens_in_hand_exclude_unclaimed_vesting() {
+        let mut investor = vesting_position(600, 1_000);
+        investor.tokens_received = 1_000;
+        investor.tokens_claimed = 200;
+
+        assert_eq!(investor.unclaimed_vesting(), 400);
+        assert_eq!(investor.tokens_in_hand(), 600);
+    }
+
+    #[test]
+    fn early_bird_bonus_only_below_threshold() {
+        let mut escrow = GlobalEscrow {
+            bonus_bps: 1_000,
+            bonus_threshold_lamports: 10 * SOL_LAMPORTS,
+            total_sol_deposited: 8 * SOL_LAMPORTS,
+            ..GlobalEscrow::default()
+        };
+
+        // Half of a 4 SOL deposit lands below the threshold
+        assert_eq!(escrow.early_bird_bonus(4 * SOL_LAMPORTS, 4_000, u64::MAX).unwrap(), 200);
+
+        escrow.total_sol_deposited = 10 * SOL_LAMPORTS;
+        assert_eq!(escrow.early_bird_bonus(4 * SOL_LAMPORTS, 4_000, u64::MAX).unwrap(), 0);
+
+        escrow.total_sol_deposited = 0;
+        escrow.bonus_bps = 0;
+        assert_eq!(escrow.early_bird_bonus(4 * SOL_LAMPORTS, 4_000, u64::MAX).unwrap(), 0);
+    }
+
+    #[test]
+    fn early_bird_bonus_capped_by_fillable() {
+        let escrow = GlobalEscrow { bonus_bps: 1_000, bonus_threshold_lamports: 10 * SOL_LAMPORTS, ..GlobalEscrow::default() };
+
+        assert_eq!(escrow.early_bird_bonus(SOL_LAMPORTS, 1_000, 1_050).unwrap(), 50);
+        assert_eq!(escrow.early_bird_bonus(SOL_LAMPORTS, 1_000, 1_000).unwrap(), 0);
+        assert_eq!(escrow.early_bird_bonus(SOL_LAMPORTS, 1_000, 900).unwrap(), 0);
+    }
+
+
+    #[test]
+    fn vested_tokens_at_cliff_and_full_vest() {
+        let investor = vesting_position(1_000, 1_000);
+        let (lock, cliff) = (100, 20);
+
+        assert_eq!(investor.vested_tokens(lock, cliff, 999), 0);
+        assert_eq!(investor.vested_tokens(lock, cliff, 1_020), 0);
+        assert_eq!(investor.vested_tokens(lock, cliff, 1_021), 12);
+        assert_eq!(investor.vested_tokens(lock, cliff, 1_099), 987);
+        assert_eq!(investor.vested_tokens(lock, cliff, 1_100), 1_000);
+        assert_eq!(investor.vested_tokens(lock, cliff, i64::MAX), 1_000);
+        assert_eq!(investor.vested_tokens(0, 0, 1_000), 1_000);
+    }
+
+
+    #[test]
+    fn bucket_allowance_resets_each_bucket() {
+        let mut escrow = GlobalEscrow {
+            initialization_timestamp: 1_000,
+            tokens_per_bucket: 100,
+            bucket_duration: 60,
+            ..GlobalEscrow::default()
+        };
+        assert_eq!(escrow.bucket_allowance(1_000), 100);
+
+        escrow.record_bucket_sale(70, 1_010).unwrap();
+        escrow.record_bucket_sale(30, 1_059).unwrap();
+        assert_eq!(escrow.bucket_allowance(1_059), 0);
+
+        // The next bucket starts with its full allowance, before and after its first sale
+        assert_eq!(escrow.bucket_allowance(1_060), 100);
+        escrow.record_bucket_sale(40, 1_119).unwrap();
+        assert_eq!((escrow.bucket_start, escrow.bucket_tokens_sold), (1_060, 40));
+        assert_eq!(escrow.bucket_allowance(1_119), 60);
+
+        // A skipped bucket doesn't carry anything over
+        assert_eq!(escrow.bucket_allowance(1_250), 100);
+
+        escrow.tokens_per_bucket = 0;
+        assert_eq!(escrow.bucket_allowance(1_119), u64::MAX);
+    }
+
+
+    #[test]
+    fn lock_is_global_at_the_unlock_boundary() {
+        let escrow = GlobalEscrow {
+            initialization_timestamp: 1_000,
+            lock_duration: 100,
+            unlock_mode: UnlockMode::Time,
+            ..GlobalEscrow::default()
+        };
+
+        assert!(!escrow.is_unlocked(1_099));
+        assert!(escrow.is_unlocked(1_100));
+
+        // A late depositor unlocks with everyone else, not lock_duration after their deposit
+        let mut late = InvestorAccount { deposit_timestamp: 1_090, ..InvestorAccount::default() };
+        assert_eq!(escrow.investor_unlock_timestamp(&late), 1_100);
+        assert!(!escrow.is_unlocked_for(&late, 1_099));
+        assert!(escrow.is_unlocked_for(&late, 1_100));
+
+        // An override is still counted from initialization
+        late.custom_lock_duration = 50;
+        assert_eq!(escrow.investor_unlock_timestamp(&late), 1_050);
+        assert!(!escrow.is_unlocked_for(&late, 1_049));
+        assert!(escrow.is_unlocked_for(&late, 1_050));
+    }
+
+
+    #[test]
+    fn sale_caps_near_u64_max_are_an_error() {
+        let mut escrow = GlobalEscrow {
+            tokens_sold: u64::MAX - 1,
+            max_total_tokens_sold: u64::MAX,
+            ..GlobalEscrow::default()
+        };
+        assert_eq!(escrow.check_sale_caps(1), Ok(()));
+        assert_eq!(escrow.check_sale_caps(2), Err(escrow_error(EscrowError::AmountOverflow)));
+
+        escrow.round_tokens_sold = u64::MAX;
+        escrow.round_tokens_cap = u64::MAX;
+        assert_eq!(escrow.check_sale_caps(1), Err(escrow_error(EscrowError::AmountOverflow)));
+
+        // Accounting drift past the supply leaves nothing fillable rather than wrapping
+        escrow.total_tokens_available = 1_000;
+        assert_eq!(escrow.fillable_tokens(), 0);
+    }
+
+
+    #[test]
+    fn sol_usd_price_band_is_inclusive() {
+        let mut escrow = GlobalEscrow::default();
+        assert_eq!(escrow.check_sol_usd_price(1), Ok(()));
+
+        escrow.min_sol_usd_price = 100;
+        escrow.max_sol_usd_price = 200;
+        assert_eq!(escrow.check_sol_usd_price(100), Ok(()));
+        assert_eq!(escrow.check_sol_usd_price(200), Ok(()));
+        assert_eq!(escrow.check_sol_usd_price(99), Err(escrow_error(EscrowError::PriceOutOfBounds)));
+        assert_eq!(escrow.check_sol_usd_price(201), Err(escrow_error(EscrowError::PriceOutOfBounds)));
+    }
+
+
+    #[test]
+    fn discriminators_are_sighash_prefixes() {
+        let global = solana_program::hash::hash(b"account:GlobalEscrow").to_bytes();
+        let investor = solana_program::hash::hash(b"account:InvestorAccount").to_bytes();
+        assert_eq!(global[..DISCRIMINATOR_LEN], GLOBAL_ESCROW_DISCRIMINATOR);
+        assert_eq!(investor[..DISCRIMINATOR_LEN], INVESTOR_ACCOUNT_DISCRIMINATOR);
+    }
+
+    #[test]
+    fn load_rejects_the_other_account_type() {
+        let investor = InvestorAccount { discriminator: INVESTOR_ACCOUNT_DISCRIMINATOR, ..InvestorAccount::default() };
+        let investor_data = borsh::to_vec(&investor).unwrap();
+        assert_eq!(
+            GlobalEscrow::load(&investor_data).unwrap_err(),
+            escrow_error(EscrowError::AccountDiscriminatorMismatch)
+        );
+
+        let escrow = GlobalEscrow { discriminator: GLOBAL_ESCROW_DISCRIMINATOR, ..GlobalEscrow::default() };
+        let escrow_data = borsh::to_vec(&escrow).unwrap();
+        assert_eq!(
+            InvestorAccount::load(&escrow_data).unwrap_err(),
+            escrow_error(EscrowError::AccountDiscriminatorMismatch)
+        );
+
+        assert_eq!(
+            GlobalEscrow::load(&[]).unwrap_err(),
+            escrow_error(EscrowError::AccountDiscriminatorMismatch)
+        );
+    }
+}
@@ -0,0 +1,233 @@
+// SPL Token and Token-2022 helpers: account checks, transfer fees and transfers
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    instruction::Instruction,
+    program::{get_return_data, invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_token::instruction as spl_instruction;
+
+use crate::{
+    find_account_by_key, EscrowError, GlobalEscrow, BPS_DENOMINATOR, TOKEN_2022_ACCOUNT_TYPE_MINT,
+    TOKEN_2022_ACCOUNT_TYPE_OFFSET, TOKEN_2022_PROGRAM_ID, TRANSFER_FEE_CONFIG_EXTENSION_TYPE,
+    TRANSFER_FEE_CONFIG_LEN,
+};
+
+/// Transfer fee (maximum_fee, basis_points) in effect at `epoch` for a Token-2022 mint
+/// with the transfer-fee extension. None for plain SPL mints and mints without the extension.
+pub fn read_transfer_fee(mint_data: &[u8], epoch: u64) -> Result<Option<(u64, u16)>, ProgramError> {
+    if mint_data.len() <= spl_token::state::Mint::LEN {
+        return Ok(None);
+    }
+    
+    if mint_data.get(TOKEN_2022_ACCOUNT_TYPE_OFFSET) != Some(&TOKEN_2022_ACCOUNT_TYPE_MINT) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    let read_u16 = |at: usize| -> Result<u16, ProgramError> {
+        let bytes = mint_data.get(at..at + 2).ok_or(ProgramError::InvalidAccountData)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    };
+    let read_u64 = |at: usize| -> Result<u64, ProgramError> {
+        let bytes = mint_data.get(at..at + 8).ok_or(ProgramError::InvalidAccountData)?;
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(buf))
+    };
+    
+    // Walk the TLV entries: [type: u16][length: u16][value]
+    let mut offset = TOKEN_2022_ACCOUNT_TYPE_OFFSET + 1;
+    while offset + 4 <= mint_data.len() {
+        let extension_type = read_u16(offset)?;
+        let length = read_u16(offset + 2)? as usize;
+        let value_start = offset + 4;
+        if extension_type == 0 {
+            break; // Uninitialized: end of the extension area
+        }
+        
+        if extension_type == TRANSFER_FEE_CONFIG_EXTENSION_TYPE {
+            if length != TRANSFER_FEE_CONFIG_LEN {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            // Skip both authorities and withheld_amount, then older/newer TransferFee
+            // entries of (epoch: u64, maximum_fee: u64, transfer_fee_basis_points: u16)
+            let older = value_start + 32 + 32 + 8;
+            let newer = older + 18;
+            let fee_at = if epoch >= read_u64(newer)? { newer } else { older };
+            return Ok(Some((read_u64(fee_at + 8)?, read_u16(fee_at + 16)?)));
+        }
+        
+        offset = value_start + length;
+    }
+    
+    Ok(None)
+}
+
+/// Decimals of an SPL mint, from its base layout (extension data, if any, is ignored)
+pub fn read_mint_decimals(mint_account: &AccountInfo) -> Result<u8, ProgramError> {
+    let mint_data = mint_account.data.borrow();
+    let base = mint_data
+        .get(..spl_token::state::Mint::LEN)
+        .ok_or(EscrowError::InvalidTokenAccount)?;
+    Ok(spl_token::state::Mint::unpack(base)?.decimals)
+}
+
+/// Tokens that land in the destination when `amount` is sent, after any transfer fee
+/// the mint withholds. Mirrors Token-2022's fee rounding (ceil, capped at maximum_fee).
+pub fn net_tokens_after_transfer_fee(mint_account: &AccountInfo, amount: u64, epoch: u64) -> Result<u64, ProgramError> {
+    let Some((maximum_fee, basis_points)) = read_transfer_fee(&mint_account.data.borrow(), epoch)? else {
+        return Ok(amount);
+    };
+    
+    if basis_points == 0 || amount == 0 {
+        return Ok(amount);
+    }
+    
+    let raw_fee = ((amount as u128) * (basis_points as u128)).div_ceil(BPS_DENOMINATOR as u128);
+    let fee = u64::try_from(raw_fee).map_err(|_| EscrowError::AmountOverflow)?.min(maximum_fee);
+    
+    Ok(amount - fee)
+}
+
+/// SPL Token or Token-2022, the two token programs a sale mint may live under
+pub fn is_token_program(key: &Pubkey) -> bool {
+    *key == spl_token::id() || *key == TOKEN_2022_PROGRAM_ID
+}
+
+/// The spl_token builders only accept the SPL Token program id. Token-2022 keeps the
+/// same wire format for the base instructions, so build with spl_token::id() and
+/// retarget the instruction at the escrow's token program.
+pub fn for_token_program(mut instruction: Instruction, token_program: &Pubkey) -> Instruction {
+    instruction.program_id = *token_program;
+    instruction
+}
+
+/// Whether `account` is an initialized-size token account of `token_program`.
+/// Token-2022 accounts may carry extensions past the base layout.
+pub fn is_token_account(account: &AccountInfo, token_program: &Pubkey) -> bool {
+    account.owner == token_program && account.data_len() >= spl_token::state::Account::LEN
+}
+
+/// Token account from its base layout (extension data, if any, is ignored)
+pub fn read_token_account(token_account: &AccountInfo) -> Result<spl_token::state::Account, ProgramError> {
+    let data = token_account.data.borrow();
+    let base = data
+        .get(..spl_token::state::Account::LEN)
+        .ok_or(EscrowError::InvalidTokenAccount)?;
+    spl_token::state::Account::unpack(base)
+}
+
+/// Bytes a token account of `mint` needs. Token-2022 mints can require account
+/// extensions (e.g. TransferFeeAmount), so ask the program instead of assuming 165.
+pub fn token_account_size<'a>(token_program: &AccountInfo<'a>, mint: &AccountInfo<'a>) -> Result<usize, ProgramError> {
+    if token_program.key == &spl_token::id() {
+        return Ok(spl_token::state::Account::LEN);
+    }
+    
+    invoke(
+        &for_token_program(
+            spl_instruction::get_account_data_size(&spl_token::id(), mint.key)?,
+            token_program.key,
+        ),
+        &[mint.clone(), token_program.clone()],
+    )?;
+    
+    let (returned_by, data) = get_return_data().ok_or(EscrowError::InvalidTokenAccount)?;
+    if returned_by != *token_program.key || data.len() != 8 {
+        return Err(EscrowError::InvalidTokenAccount.into());
+    }
+    let mut size = [0u8; 8];
+    size.copy_from_slice(&data);
+    usize::try_from(u64::from_le_bytes(size)).map_err(|_| EscrowError::AmountOverflow.into())
+}
+
+/// transfer_checked through either token program
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_tokens_checked<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    invoke_signed(
+        &for_token_program(
+            spl_instruction::transfer_checked(
+                &spl_token::id(),
+                source.key,
+                mint.key,
+                destination.key,
+                authority.key,
+                &[],
+                amount,
+                decimals,
+            )?,
+            token_program.key,
+        ),
+        &[
+            source.clone(),
+            mint.clone(),
+            destination.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+/// Move escrow tokens through the escrow's token program. SPL Token escrows use a
+/// plain transfer; Token-2022 escrows use transfer_checked (required once the mint has
+/// extensions such as transfer fees), so the sale mint must be among `accounts`.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_escrow_tokens<'a>(
+    global_escrow: &GlobalEscrow,
+    accounts: &[AccountInfo<'a>],
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    if *token_program.key != global_escrow.token_program_id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    if token_program.key == &spl_token::id() {
+        return invoke_signed(
+            &spl_instruction::transfer(
+                token_program.key,
+                source.key,
+                destination.key,
+                authority.key,
+                &[],
+                amount,
+            )?,
+            &[source.clone(), destination.clone(), authority.clone(), token_program.clone()],
+            signer_seeds,
+        );
+    }
+    
+    let token_mint = find_account_by_key(accounts, &global_escrow.token_mint_pubkey).ok_or_else(|| {
+        msg!("Missing token mint {} for Token-2022 transfer", global_escrow.token_mint_pubkey);
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    transfer_tokens_checked(
+        token_program,
+        source,
+        token_mint,
+        destination,
+        authority,
+        amount,
+        global_escrow.token_decimals,
+        signer_seeds,
+    )
+}
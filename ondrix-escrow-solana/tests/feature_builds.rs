@@ -0,0 +1,56 @@
+// Compile checks for the supported feature sets, run by plain `cargo test` rather than
+// a CI matrix. Each set is checked in its own target directory so the nested cargo
+// doesn't wait on the lock held by the outer `cargo test`.
+
+use std::process::Command;
+
+use ondrix_escrow_solana::process_instruction;
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+fn cargo_check(features: &[&str]) -> bool {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let name = if features.is_empty() { "none".to_string() } else { features.join("+") };
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo
+        .args(["check", "--lib", "--quiet", "--no-default-features"])
+        .args(["--manifest-path", &format!("{}/Cargo.toml", manifest_dir)])
+        .env("CARGO_TARGET_DIR", format!("{}/target/feature-builds/{}", manifest_dir, name));
+    if !features.is_empty() {
+        cargo.args(["--features", &features.join(",")]);
+    }
+    cargo.status().expect("failed to run cargo").success()
+}
+
+#[test]
+fn default_build() {
+    assert!(cargo_check(&["devnet"]));
+}
+
+#[test]
+fn no_entrypoint_build() {
+    assert!(cargo_check(&["devnet", "no-entrypoint"]));
+    assert!(cargo_check(&["mainnet", "no-entrypoint"]));
+}
+
+#[test]
+fn mainnet_build() {
+    assert!(cargo_check(&["mainnet"]));
+    // Enabled on top of the default devnet, as `--features mainnet` and `--all-features` do
+    assert!(cargo_check(&["devnet", "mainnet"]));
+}
+
+#[test]
+fn client_build() {
+    assert!(cargo_check(&["devnet", "client", "no-entrypoint"]));
+}
+
+#[test]
+fn build_without_network_fails() {
+    assert!(!cargo_check(&[]));
+}
+
+#[test]
+fn process_instruction_stays_public() {
+    // CPI consumers built with no-entrypoint call the processor directly
+    let _: fn(&Pubkey, &[AccountInfo], &[u8]) -> ProgramResult = process_instruction;
+}
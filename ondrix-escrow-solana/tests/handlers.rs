@@ -0,0 +1,1129 @@
+// Handler tests on solana-program-test's in-process bank. Escrow and investor accounts
+// are preloaded in the state a deposit leaves them in, so no oracle feed is needed.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use ondrix_escrow_solana::{
+    find_audit_log_pda, find_global_escrow_pda, find_investor_pda, find_pending_config_pda, find_snapshot_pda, find_sol_vault_pda, find_token_vault_pda,
+    find_whitelist_pda,
+    process_instruction, sol_for_tokens, AuditAction, AuditEntry, AuditLogHeader, ConfigUpdate, EscrowError, EscrowInstruction, GlobalEscrow,
+    InitializeEscrowParams, InvestorAccount, InvestorStatus, OracleKind, GLOBAL_ESCROW_DISCRIMINATOR,
+    INVESTOR_ACCOUNT_DISCRIMINATOR, MIN_LOCK_DURATION, PYTH_ACCOUNT_TYPE_PRICE, PYTH_MAGIC, PYTH_PRICE_ACCOUNT_MIN_LEN,
+    PYTH_PROGRAM_ID, PYTH_SOL_USD_FEED, PYTH_STATUS_TRADING, PYTH_VERSION, StateSnapshot,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::{Transaction, TransactionError},
+};
+
+const SOL: u64 = 1_000_000_000;
+const TOKENS: u64 = 1_000;
+const VESTING_START: i64 = 1_000;
+const LOCK_DURATION: i64 = 100;
+const CLIFF_DURATION: i64 = 20;
+const SALE_END: i64 = VESTING_START + LOCK_DURATION / 2;
+const SOL_USD_100: i64 = 100 * 100_000_000; // $100.00 with 8 decimals
+const TOKENS_PER_SOL: u64 = 400; // At $100 per SOL and $0.25 per token
+
+struct Fixture {
+    program_id: Pubkey,
+    investor: Keypair,
+    initializer: Keypair,
+    recipient: Keypair,
+    global_escrow: Pubkey,
+    investor_pda: Pubkey,
+    sol_vault: Pubkey,
+    token_vault: Pubkey,
+    investor_tokens: Pubkey,
+    recipient_tokens: Pubkey,
+    token_mint: Pubkey,
+    oracle_program: Pubkey,
+    price_feed: Pubkey,
+}
+
+fn program_account(data: &impl BorshSerialize, owner: Pubkey) -> Account {
+    let data = borsh::to_vec(data).unwrap();
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account::pack(
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        &mut data,
+    )
+    .unwrap();
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: spl_token::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn mint_account() -> Account {
+    let mut data = vec![0u8; spl_token::state::Mint::LEN];
+    spl_token::state::Mint::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 10 * TOKENS,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        &mut data,
+    )
+    .unwrap();
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: spl_token::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Pyth v2 price account trading at `price` (8 decimals), published at VESTING_START
+fn pyth_price_account(oracle_program: Pubkey, price: i64) -> Account {
+    let mut data = vec![0u8; PYTH_PRICE_ACCOUNT_MIN_LEN];
+    data[0..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+    data[4..8].copy_from_slice(&PYTH_VERSION.to_le_bytes());
+    data[8..12].copy_from_slice(&PYTH_ACCOUNT_TYPE_PRICE.to_le_bytes());
+    data[20..24].copy_from_slice(&(-8i32).to_le_bytes());
+    data[96..104].copy_from_slice(&VESTING_START.to_le_bytes());
+    data[208..216].copy_from_slice(&price.to_le_bytes());
+    data[224..228].copy_from_slice(&PYTH_STATUS_TRADING.to_le_bytes());
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: oracle_program,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// A sale ending at SALE_END with one investor holding a 2 SOL position that bought
+/// TOKENS, of which `tokens_vesting` are still in the vault next to the unsold supply.
+/// Deposits are priced by a Pyth feed at $100 per SOL, against $0.25 per token.
+fn setup(tokens_vesting: u64) -> (ProgramTest, Fixture) {
+    setup_with(tokens_vesting, |_| {})
+}
+
+/// `setup` with the escrow adjusted by `configure` before it is stored
+fn setup_with(tokens_vesting: u64, configure: impl FnOnce(&mut GlobalEscrow)) -> (ProgramTest, Fixture) {
+    let program_id = Pubkey::new_unique();
+    let mut test = ProgramTest::new("ondrix_escrow_solana", program_id, processor!(process_instruction));
+
+    let investor = Keypair::new();
+    let recipient = Keypair::new();
+    let initializer = Keypair::new();
+    let token_mint = Pubkey::new_unique();
+    let (global_escrow, bump_seed) = find_global_escrow_pda(&initializer.pubkey(), &token_mint, 0, &program_id);
+    let (investor_pda, investor_bump) = find_investor_pda(&investor.pubkey(), &global_escrow, &program_id);
+    let (sol_vault, _) = find_sol_vault_pda(&investor.pubkey(), &global_escrow, &program_id);
+    let (token_vault, _) = find_token_vault_pda(&global_escrow, &program_id);
+    let investor_tokens = Pubkey::new_unique();
+    let recipient_tokens = Pubkey::new_unique();
+    let oracle_program = Pubkey::new_unique();
+    let price_feed = Pubkey::new_unique();
+
+    let mut escrow = GlobalEscrow {
+        discriminator: GLOBAL_ESCROW_DISCRIMINATOR,
+        is_initialized: true,
+        initializer_pubkey: initializer.pubkey(),
+        token_mint_pubkey: token_mint,
+        recipient_wallet: recipient.pubkey(),
+        bump_seed,
+        lock_duration: LOCK_DURATION,
+        cliff_duration: CLIFF_DURATION,
+        sale_end_timestamp: SALE_END,
+        total_tokens_available: 10 * TOKENS,
+        tokens_sold: TOKENS,
+        total_sol_deposited: 2 * SOL,
+        vesting_tokens_outstanding: tokens_vesting,
+        min_sol_investment: SOL / 10,
+        max_sol_investment: 10 * SOL,
+        oracle_kind: OracleKind::Pyth,
+        oracle_program_id: oracle_program,
+        price_feed_pubkey: price_feed,
+        price_staleness_threshold: 3_600,
+        token_price_cents: 25,
+        min_sol_usd_price: 1,
+        max_sol_usd_price: u64::MAX,
+        ..GlobalEscrow::default()
+    };
+    configure(&mut escrow);
+
+    let position = InvestorAccount {
+        discriminator: INVESTOR_ACCOUNT_DISCRIMINATOR,
+        is_initialized: true,
+        investor_pubkey: investor.pubkey(),
+        global_escrow_pubkey: global_escrow,
+        bump_seed: investor_bump,
+        status: InvestorStatus::Deposited,
+        sol_deposited: 2 * SOL,
+        tokens_received: TOKENS,
+        tokens_vesting,
+        deposit_timestamp: VESTING_START,
+        last_deposit_timestamp: VESTING_START,
+        vesting_start: VESTING_START,
+        deposit_count: 1,
+        ..InvestorAccount::default()
+    };
+
+    let vault_tokens = escrow.total_tokens_available - escrow.tokens_sold + tokens_vesting;
+    for wallet in [investor.pubkey(), initializer.pubkey()] {
+        test.add_account(wallet, Account { lamports: 10 * SOL, owner: solana_sdk::system_program::id(), ..Account::default() });
+    }
+    test.add_account(global_escrow, program_account(&escrow, program_id));
+    test.add_account(investor_pda, program_account(&position, program_id));
+    test.add_account(
+        sol_vault,
+        Account { lamports: SOL + Rent::default().minimum_balance(0), owner: program_id, ..Account::default() },
+    );
+    test.add_account(token_vault, token_account(token_mint, global_escrow, vault_tokens));
+    test.add_account(investor_tokens, token_account(token_mint, investor.pubkey(), TOKENS - tokens_vesting));
+    test.add_account(recipient_tokens, token_account(token_mint, recipient.pubkey(), 0));
+    test.add_account(token_mint, mint_account());
+    test.add_account(price_feed, pyth_price_account(oracle_program, SOL_USD_100));
+
+    let fixture = Fixture {
+        program_id,
+        investor,
+        initializer,
+        recipient,
+        global_escrow,
+        investor_pda,
+        sol_vault,
+        token_vault,
+        investor_tokens,
+        recipient_tokens,
+        token_mint,
+        oracle_program,
+        price_feed,
+    };
+    (test, fixture)
+}
+
+/// `setup` pricing deposits at the median of three Pyth feeds quoting `prices`,
+/// which may spread at most 1% around the median
+fn multi_oracle_setup(prices: [i64; 3]) -> (ProgramTest, Fixture, [Pubkey; 3]) {
+    let feeds = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let (mut test, fixture) = setup_with(0, |escrow| {
+        escrow.use_multi_oracle = true;
+        escrow.oracle_feeds[..3].copy_from_slice(&feeds);
+        escrow.oracle_feed_count = 3;
+        escrow.max_feed_disagreement_bps = 100;
+    });
+    for (feed, price) in feeds.into_iter().zip(prices) {
+        test.add_account(feed, pyth_price_account(fixture.oracle_program, price));
+    }
+    (test, fixture, feeds)
+}
+
+struct InitFixture {
+    program_id: Pubkey,
+    initializer: Keypair,
+    global_escrow: Pubkey,
+    token_mint: Pubkey,
+    token_vault: Pubkey,
+    token_source: Pubkey,
+    recipient: Pubkey,
+}
+
+/// An initializer holding `source_tokens` of a fresh mint, with no escrow accounts yet.
+/// The clock starts at VESTING_START.
+fn init_setup(source_tokens: u64) -> (ProgramTest, InitFixture) {
+    let program_id = Pubkey::new_unique();
+    let mut test = ProgramTest::new("ondrix_escrow_solana", program_id, processor!(process_instruction));
+
+    let initializer = Keypair::new();
+    let token_mint = Pubkey::new_unique();
+    let (global_escrow, _) = find_global_escrow_pda(&initializer.pubkey(), &token_mint, 0, &program_id);
+    let (token_vault, _) = find_token_vault_pda(&global_escrow, &program_id);
+    let token_source = Pubkey::new_unique();
+
+    test.add_account(
+        initializer.pubkey(),
+        Account { lamports: 10 * SOL, owner: solana_sdk::system_program::id(), ..Account::default() },
+    );
+    test.add_account(token_mint, mint_account());
+    test.add_account(token_source, token_account(token_mint, initializer.pubkey(), source_tokens));
+    test.add_account(PYTH_SOL_USD_FEED, pyth_price_account(PYTH_PROGRAM_ID, SOL_USD_100));
+
+    let fixture = InitFixture {
+        program_id,
+        initializer,
+        global_escrow,
+        token_mint,
+        token_vault,
+        token_source,
+        recipient: Pubkey::new_unique(),
+    };
+    (test, fixture)
+}
+
+/// Sale of TOKENS at $0.25 ending at SALE_END, priced by the network's Pyth feed
+fn init_params() -> InitializeEscrowParams {
+    InitializeEscrowParams {
+        token_amount: TOKENS,
+        lock_duration: MIN_LOCK_DURATION,
+        sale_end_timestamp: SALE_END,
+        min_sol_investment: SOL / 10,
+        max_sol_investment: 10 * SOL,
+        price_staleness_threshold: 3_600,
+        reserved_tokens: 0,
+        oracle_kind: OracleKind::Pyth,
+        token_price_cents: 25,
+        cliff_duration: 0,
+        sale_id: 0,
+        min_sol_usd_price: 1,
+        max_sol_usd_price: u64::MAX,
+        sol_hard_cap: 0,
+    }
+}
+
+fn initialize_escrow_ix(fixture: &InitFixture, params: InitializeEscrowParams) -> Instruction {
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::InitializeEscrow(params)).unwrap(),
+        vec![
+            AccountMeta::new(fixture.initializer.pubkey(), true),
+            AccountMeta::new(fixture.global_escrow, false),
+            AccountMeta::new_readonly(fixture.token_mint, false),
+            AccountMeta::new(fixture.token_vault, false),
+            AccountMeta::new(fixture.token_source, false),
+            AccountMeta::new_readonly(fixture.recipient, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+            AccountMeta::new_readonly(PYTH_PROGRAM_ID, false),
+            AccountMeta::new_readonly(PYTH_SOL_USD_FEED, false),
+        ],
+    )
+}
+
+fn refund_deposit_ix(fixture: &Fixture, global_escrow: Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::RefundDeposit).unwrap(),
+        vec![
+            AccountMeta::new(fixture.investor.pubkey(), true),
+            AccountMeta::new(global_escrow, false),
+            AccountMeta::new(fixture.investor_pda, false),
+            AccountMeta::new(fixture.sol_vault, false),
+            AccountMeta::new(fixture.token_vault, false),
+            AccountMeta::new(fixture.investor_tokens, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+fn refund_failed_sale_ix(fixture: &Fixture) -> Instruction {
+    let mut ix = refund_deposit_ix(fixture, fixture.global_escrow);
+    ix.data = borsh::to_vec(&EscrowInstruction::RefundFailedSale).unwrap();
+    ix
+}
+
+fn withdraw_locked_sol_ix(fixture: &Fixture) -> Instruction {
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::WithdrawLockedSol).unwrap(),
+        vec![
+            AccountMeta::new(fixture.recipient.pubkey(), true),
+            AccountMeta::new(fixture.global_escrow, false),
+            AccountMeta::new(fixture.investor_pda, false),
+            AccountMeta::new(fixture.sol_vault, false),
+            AccountMeta::new(fixture.recipient.pubkey(), false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+    )
+}
+
+fn claim_vested_tokens_ix(fixture: &Fixture) -> Instruction {
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::ClaimVestedTokens).unwrap(),
+        vec![
+            AccountMeta::new(fixture.investor.pubkey(), true),
+            AccountMeta::new(fixture.global_escrow, false),
+            AccountMeta::new(fixture.investor_pda, false),
+            AccountMeta::new(fixture.token_vault, false),
+            AccountMeta::new(fixture.investor_tokens, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+fn deposit_sol_ix(fixture: &Fixture, sol_amount: u64, fill_to_cap: bool) -> Instruction {
+    let deposit = EscrowInstruction::DepositSol { sol_amount, min_tokens_out: 0, deadline: 0, fill_to_cap, whitelist_proof: vec![] };
+    deposit_ix(fixture, deposit)
+}
+
+/// Any of the deposit instructions taking DepositSol's accounts
+fn deposit_ix(fixture: &Fixture, deposit: EscrowInstruction) -> Instruction {
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&deposit).unwrap(),
+        vec![
+            AccountMeta::new(fixture.investor.pubkey(), true),
+            AccountMeta::new(fixture.global_escrow, false),
+            AccountMeta::new(fixture.investor_pda, false),
+            AccountMeta::new(fixture.sol_vault, false),
+            AccountMeta::new(fixture.token_vault, false),
+            AccountMeta::new(fixture.investor_tokens, false),
+            AccountMeta::new(fixture.recipient.pubkey(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(fixture.oracle_program, false),
+            AccountMeta::new_readonly(fixture.price_feed, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(fixture.token_mint, false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+    )
+}
+
+fn close_sale_ix(fixture: &Fixture) -> Instruction {
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::CloseSale).unwrap(),
+        vec![
+            AccountMeta::new_readonly(fixture.recipient.pubkey(), true),
+            AccountMeta::new(fixture.global_escrow, false),
+            AccountMeta::new(fixture.token_vault, false),
+            AccountMeta::new(fixture.recipient_tokens, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+    )
+}
+
+fn queue_config_update_ix(fixture: &Fixture, update: ConfigUpdate) -> Instruction {
+    let (pending_config, _) = find_pending_config_pda(&fixture.global_escrow, &fixture.program_id);
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::QueueConfigUpdate { update }).unwrap(),
+        vec![
+            AccountMeta::new(fixture.initializer.pubkey(), true),
+            AccountMeta::new_readonly(fixture.global_escrow, false),
+            AccountMeta::new(pending_config, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+        ],
+    )
+}
+
+fn initialize_audit_log_ix(fixture: &Fixture) -> Instruction {
+    let (audit_log, _) = find_audit_log_pda(&fixture.global_escrow, &fixture.program_id);
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::InitializeAuditLog).unwrap(),
+        vec![
+            AccountMeta::new(fixture.initializer.pubkey(), true),
+            AccountMeta::new(fixture.global_escrow, false),
+            AccountMeta::new(audit_log, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+        ],
+    )
+}
+
+fn set_paused_ix(fixture: &Fixture, paused: bool) -> Instruction {
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::SetPaused { paused }).unwrap(),
+        vec![
+            AccountMeta::new_readonly(fixture.initializer.pubkey(), true),
+            AccountMeta::new(fixture.global_escrow, false),
+        ],
+    )
+}
+
+fn snapshot_state_ix(fixture: &Fixture, snapshot_id: u64) -> Instruction {
+    let (snapshot, _) = find_snapshot_pda(&fixture.global_escrow, snapshot_id, &fixture.program_id);
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::SnapshotState { snapshot_id }).unwrap(),
+        vec![
+            AccountMeta::new(fixture.investor.pubkey(), true),
+            AccountMeta::new_readonly(fixture.global_escrow, false),
+            AccountMeta::new(snapshot, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+            AccountMeta::new_readonly(fixture.investor_pda, false),
+        ],
+    )
+}
+
+fn add_to_whitelist_ix(fixture: &Fixture) -> Instruction {
+    let investor = fixture.investor.pubkey();
+    let (entry, _) = find_whitelist_pda(&investor, &fixture.global_escrow, &fixture.program_id);
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::AddToWhitelist { investor }).unwrap(),
+        vec![
+            AccountMeta::new(fixture.initializer.pubkey(), true),
+            AccountMeta::new_readonly(fixture.global_escrow, false),
+            AccountMeta::new(entry, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+        ],
+    )
+}
+
+fn set_withdrawals_frozen_ix(fixture: &Fixture, frozen: bool) -> Instruction {
+    Instruction::new_with_bytes(
+        fixture.program_id,
+        &borsh::to_vec(&EscrowInstruction::SetWithdrawalsFrozen { frozen }).unwrap(),
+        vec![
+            AccountMeta::new_readonly(fixture.initializer.pubkey(), true),
+            AccountMeta::new(fixture.global_escrow, false),
+        ],
+    )
+}
+
+/// `ix` with the audit log appended, as privileged handlers expect once logging is on
+fn with_audit_log(fixture: &Fixture, mut ix: Instruction) -> Instruction {
+    let (audit_log, _) = find_audit_log_pda(&fixture.global_escrow, &fixture.program_id);
+    ix.accounts.push(AccountMeta::new(audit_log, false));
+    ix
+}
+
+async fn send(context: &mut ProgramTestContext, signer: &Keypair, ix: Instruction) -> Result<(), BanksClientError> {
+    // A fresh blockhash keeps repeated identical instructions distinct transactions
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&context.payer.pubkey()), &[&context.payer, signer], blockhash);
+    context.banks_client.process_transaction(tx).await
+}
+
+async fn set_time(context: &mut ProgramTestContext, unix_timestamp: i64) {
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    context.set_sysvar(&Clock { unix_timestamp, ..clock });
+}
+
+async fn token_balance(context: &mut ProgramTestContext, account: Pubkey) -> u64 {
+    let account = context.banks_client.get_account(account).await.unwrap().unwrap();
+    spl_token::state::Account::unpack(&account.data).unwrap().amount
+}
+
+async fn load_investor(context: &mut ProgramTestContext, fixture: &Fixture) -> InvestorAccount {
+    let account = context.banks_client.get_account(fixture.investor_pda).await.unwrap().unwrap();
+    InvestorAccount::load(&account.data).unwrap()
+}
+
+async fn load_escrow(context: &mut ProgramTestContext, fixture: &Fixture) -> GlobalEscrow {
+    let account = context.banks_client.get_account(fixture.global_escrow).await.unwrap().unwrap();
+    GlobalEscrow::load(&account.data).unwrap()
+}
+
+async fn audit_entries(context: &mut ProgramTestContext, fixture: &Fixture) -> Vec<AuditEntry> {
+    let (audit_log, _) = find_audit_log_pda(&fixture.global_escrow, &fixture.program_id);
+    let data = context.banks_client.get_account(audit_log).await.unwrap().unwrap().data;
+    let header = AuditLogHeader::deserialize(&mut &data[..AuditLogHeader::LEN]).unwrap();
+    (0..header.total_entries as usize)
+        .map(|slot| {
+            let offset = AuditLogHeader::LEN + slot * AuditEntry::LEN;
+            AuditEntry::deserialize(&mut &data[offset..offset + AuditEntry::LEN]).unwrap()
+        })
+        .collect()
+}
+
+/// Lamports and tokens held by the sale's parties, which no instruction may create
+/// or destroy (fees come from the context payer)
+async fn holdings(context: &mut ProgramTestContext, fixture: &Fixture) -> (u64, u64) {
+    let mut lamports = 0;
+    for account in [fixture.investor.pubkey(), fixture.recipient.pubkey(), fixture.sol_vault, fixture.investor_pda, fixture.global_escrow] {
+        lamports += context.banks_client.get_balance(account).await.unwrap();
+    }
+    let mut tokens = 0;
+    for account in [fixture.token_vault, fixture.investor_tokens, fixture.recipient_tokens] {
+        tokens += token_balance(context, account).await;
+    }
+    (lamports, tokens)
+}
+
+fn assert_escrow_error(result: Result<(), BanksClientError>, expected: EscrowError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(expected as u32))
+    );
+}
+
+#[tokio::test]
+async fn close_sale_twice_leaves_vesting_claimable() {
+    let (test, fixture) = setup(TOKENS);
+    let mut context = test.start_with_context().await;
+
+    set_time(&mut context, SALE_END).await;
+    send(&mut context, &fixture.recipient, close_sale_ix(&fixture)).await.unwrap();
+    assert_eq!(token_balance(&mut context, fixture.recipient_tokens).await, 9 * TOKENS);
+    assert_eq!(token_balance(&mut context, fixture.token_vault).await, TOKENS);
+    assert!(load_escrow(&mut context, &fixture).await.sale_closed);
+
+    let result = send(&mut context, &fixture.recipient, close_sale_ix(&fixture)).await;
+    assert_escrow_error(result, EscrowError::SaleAlreadyClosed);
+
+    // The vesting investor's tokens were never part of the reclaim
+    set_time(&mut context, VESTING_START + LOCK_DURATION).await;
+    send(&mut context, &fixture.investor, claim_vested_tokens_ix(&fixture)).await.unwrap();
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS);
+    assert_eq!(token_balance(&mut context, fixture.token_vault).await, 0);
+    assert_eq!(load_escrow(&mut context, &fixture).await.vesting_tokens_outstanding, 0);
+}
+
+#[tokio::test]
+async fn refunded_position_cannot_claim_vesting() {
+    let (test, fixture) = setup(TOKENS);
+    let mut context = test.start_with_context().await;
+
+    set_time(&mut context, VESTING_START).await;
+    send(&mut context, &fixture.investor, refund_deposit_ix(&fixture, fixture.global_escrow)).await.unwrap();
+    assert_eq!(load_escrow(&mut context, &fixture).await.vesting_tokens_outstanding, 0);
+
+    set_time(&mut context, VESTING_START + LOCK_DURATION).await;
+    let result = send(&mut context, &fixture.investor, claim_vested_tokens_ix(&fixture)).await;
+    assert_escrow_error(result, EscrowError::InvalidEscrowStatus);
+}
+
+#[tokio::test]
+async fn deposit_at_sale_end_fails() {
+    let (test, fixture) = setup(0);
+    let mut context = test.start_with_context().await;
+
+    set_time(&mut context, SALE_END).await;
+    let ix = deposit_sol_ix(&fixture, SOL, false);
+    let result = send(&mut context, &fixture.investor, ix).await;
+    assert_escrow_error(result, EscrowError::SaleEnded);
+}
+
+#[tokio::test]
+async fn queue_config_update_after_deposits_follows_allow_list() {
+    let (test, fixture) = setup(0);
+    let mut context = test.start_with_context().await;
+
+    let ix = queue_config_update_ix(&fixture, ConfigUpdate::VestingMode { enabled: true });
+    let result = send(&mut context, &fixture.initializer, ix).await;
+    assert_escrow_error(result, EscrowError::InvalidEscrowStatus);
+
+    let ix = queue_config_update_ix(&fixture, ConfigUpdate::DepositCooldown { cooldown: 60 });
+    send(&mut context, &fixture.initializer, ix).await.unwrap();
+    let (pending_config, _) = find_pending_config_pda(&fixture.global_escrow, &fixture.program_id);
+    assert!(context.banks_client.get_account(pending_config).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn deposit_fills_the_hard_cap_exactly() {
+    let (test, fixture) = setup_with(0, |escrow| escrow.sol_hard_cap = 4 * SOL);
+    let mut context = test.start_with_context().await;
+
+    set_time(&mut context, VESTING_START + 1).await;
+    send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, 2 * SOL, false)).await.unwrap();
+    assert_eq!(load_escrow(&mut context, &fixture).await.total_sol_deposited, 4 * SOL);
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS + 2 * TOKENS_PER_SOL);
+
+    // Cap reached: nothing more is taken, with or without fill_to_cap
+    let result = send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await;
+    assert_escrow_error(result, EscrowError::HardCapExceeded);
+    let result = send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, true)).await;
+    assert_escrow_error(result, EscrowError::HardCapExceeded);
+}
+
+#[tokio::test]
+async fn over_cap_deposit_fills_only_when_asked() {
+    let (test, fixture) = setup_with(0, |escrow| escrow.sol_hard_cap = 4 * SOL);
+    let mut context = test.start_with_context().await;
+
+    set_time(&mut context, VESTING_START + 1).await;
+    let result = send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, 3 * SOL, false)).await;
+    assert_escrow_error(result, EscrowError::HardCapExceeded);
+    assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 2 * SOL);
+
+    // Only the 2 SOL left to the cap leave the investor's wallet
+    let balance_before = context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap();
+    send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, 3 * SOL, true)).await.unwrap();
+    let balance_after = context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap();
+    assert_eq!(balance_before - balance_after, 2 * SOL);
+    assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 4 * SOL);
+    assert_eq!(load_escrow(&mut context, &fixture).await.total_sol_deposited, 4 * SOL);
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS + 2 * TOKENS_PER_SOL);
+}
+
+#[tokio::test]
+async fn missed_soft_cap_refunds_investors_and_blocks_withdrawal() {
+    let (test, fixture) = setup_with(0, |escrow| escrow.soft_cap_lamports = 3 * SOL);
+    let mut context = test.start_with_context().await;
+
+    set_time(&mut context, SALE_END).await;
+    let result = send(&mut context, &fixture.recipient, withdraw_locked_sol_ix(&fixture)).await;
+    assert_escrow_error(result, EscrowError::SoftCapNotMet);
+
+    let balance_before = context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap();
+    send(&mut context, &fixture.investor, refund_failed_sale_ix(&fixture)).await.unwrap();
+    let balance_after = context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap();
+    // The locked half; the immediate half already went to the recipient
+    assert_eq!(balance_after - balance_before, SOL);
+    assert_eq!(load_investor(&mut context, &fixture).await.status, InvestorStatus::Refunded);
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, 0);
+}
+
+#[tokio::test]
+async fn met_soft_cap_releases_locked_sol_to_the_recipient() {
+    let (test, fixture) = setup_with(0, |escrow| escrow.soft_cap_lamports = 2 * SOL);
+    let mut context = test.start_with_context().await;
+
+    set_time(&mut context, SALE_END).await;
+    let result = send(&mut context, &fixture.investor, refund_failed_sale_ix(&fixture)).await;
+    assert_escrow_error(result, EscrowError::InvalidEscrowStatus);
+
+    send(&mut context, &fixture.recipient, withdraw_locked_sol_ix(&fixture)).await.unwrap();
+    assert_eq!(context.banks_client.get_balance(fixture.recipient.pubkey()).await.unwrap(), SOL);
+    assert_eq!(load_investor(&mut context, &fixture).await.status, InvestorStatus::SolWithdrawn);
+}
+
+#[tokio::test]
+async fn top_up_restarts_the_deposit_cooldown() {
+    let (test, fixture) = setup_with(0, |escrow| escrow.deposit_cooldown = 10);
+    let mut context = test.start_with_context().await;
+
+    // The fixture's last deposit was at VESTING_START
+    set_time(&mut context, VESTING_START + 5).await;
+    let result = send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await;
+    assert_escrow_error(result, EscrowError::CooldownActive);
+    assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 2 * SOL);
+
+    set_time(&mut context, VESTING_START + 10).await;
+    send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await.unwrap();
+
+    // Counted from the top-up, not the first deposit
+    set_time(&mut context, VESTING_START + 15).await;
+    let result = send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await;
+    assert_escrow_error(result, EscrowError::CooldownActive);
+
+    set_time(&mut context, VESTING_START + 20).await;
+    send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await.unwrap();
+    let position = load_investor(&mut context, &fixture).await;
+    assert_eq!(position.sol_deposited, 4 * SOL);
+    assert_eq!((position.deposit_timestamp, position.last_deposit_timestamp), (VESTING_START, VESTING_START + 20));
+}
+
+#[tokio::test]
+async fn privileged_actions_are_recorded_in_the_audit_log() {
+    let (test, fixture) = setup(0);
+    let mut context = test.start_with_context().await;
+
+    send(&mut context, &fixture.initializer, initialize_audit_log_ix(&fixture)).await.unwrap();
+    send(&mut context, &fixture.initializer, with_audit_log(&fixture, set_paused_ix(&fixture, true))).await.unwrap();
+    let queue = queue_config_update_ix(&fixture, ConfigUpdate::DepositCooldown { cooldown: 60 });
+    send(&mut context, &fixture.initializer, with_audit_log(&fixture, queue)).await.unwrap();
+
+    let entries = audit_entries(&mut context, &fixture).await;
+    let actions: Vec<_> = entries.iter().map(|entry| entry.action).collect();
+    assert_eq!(actions, [AuditAction::AuditLogEnabled, AuditAction::SetPaused, AuditAction::QueueConfigUpdate]);
+    assert!(entries.iter().all(|entry| entry.actor == fixture.initializer.pubkey()));
+    assert_ne!(entries[1].param_hash, entries[2].param_hash);
+
+    // Once logging is on, a privileged action can't skip the log
+    let result = send(&mut context, &fixture.initializer, set_paused_ix(&fixture, false)).await;
+    assert_escrow_error(result, EscrowError::InvalidAuditLog);
+    assert!(load_escrow(&mut context, &fixture).await.is_paused);
+}
+
+#[tokio::test]
+async fn under_funded_source_creates_no_accounts() {
+    let (test, fixture) = init_setup(TOKENS - 1);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START).await;
+
+    let result = send(&mut context, &fixture.initializer, initialize_escrow_ix(&fixture, init_params())).await;
+    assert_escrow_error(result, EscrowError::NotEnoughTokens);
+    assert!(context.banks_client.get_account(fixture.global_escrow).await.unwrap().is_none());
+    assert!(context.banks_client.get_account(fixture.token_vault).await.unwrap().is_none());
+    assert_eq!(token_balance(&mut context, fixture.token_source).await, TOKENS - 1);
+
+    // Exactly token_amount is enough
+    let (test, fixture) = init_setup(TOKENS);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START).await;
+    send(&mut context, &fixture.initializer, initialize_escrow_ix(&fixture, init_params())).await.unwrap();
+    assert_eq!(token_balance(&mut context, fixture.token_vault).await, TOKENS);
+    assert_eq!(token_balance(&mut context, fixture.token_source).await, 0);
+}
+
+#[tokio::test]
+async fn mismatched_existing_vault_is_rejected() {
+    for wrong_mint in [false, true] {
+        let (mut test, fixture) = init_setup(TOKENS);
+        let (mint, owner) = if wrong_mint {
+            (Pubkey::new_unique(), fixture.global_escrow)
+        } else {
+            (fixture.token_mint, fixture.initializer.pubkey())
+        };
+        test.add_account(fixture.token_vault, token_account(mint, owner, 0));
+        let mut context = test.start_with_context().await;
+        set_time(&mut context, VESTING_START).await;
+
+        let result = send(&mut context, &fixture.initializer, initialize_escrow_ix(&fixture, init_params())).await;
+        assert_escrow_error(result, EscrowError::InvalidTokenAccount);
+        assert!(context.banks_client.get_account(fixture.global_escrow).await.unwrap().is_none());
+        assert_eq!(token_balance(&mut context, fixture.token_source).await, TOKENS);
+    }
+}
+
+#[tokio::test]
+async fn agreeing_feeds_price_at_their_median() {
+    let (test, fixture, feeds) = multi_oracle_setup([SOL_USD_100, SOL_USD_100 / 1_000 * 1_008, SOL_USD_100 / 1_000 * 1_004]);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START + 1).await;
+
+    let mut ix = deposit_sol_ix(&fixture, SOL, false);
+    ix.accounts.extend(feeds.map(|feed| AccountMeta::new_readonly(feed, false)));
+    send(&mut context, &fixture.investor, ix).await.unwrap();
+
+    // $100.40 per SOL buys 401.6 tokens at $0.25
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS + 401);
+}
+
+#[tokio::test]
+async fn outlier_feed_rejects_the_deposit() {
+    let (test, fixture, feeds) = multi_oracle_setup([SOL_USD_100, SOL_USD_100, SOL_USD_100 / 2 * 3]);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START + 1).await;
+
+    let mut ix = deposit_sol_ix(&fixture, SOL, false);
+    ix.accounts.extend(feeds.map(|feed| AccountMeta::new_readonly(feed, false)));
+    let result = send(&mut context, &fixture.investor, ix).await;
+    assert_escrow_error(result, EscrowError::PriceFeedDisagreement);
+    assert_eq!(load_escrow(&mut context, &fixture).await.total_sol_deposited, 2 * SOL);
+}
+
+#[tokio::test]
+async fn snapshot_archive_is_write_once() {
+    let (test, fixture) = setup(0);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START + 1).await;
+    let (snapshot_address, _) = find_snapshot_pda(&fixture.global_escrow, 7, &fixture.program_id);
+
+    send(&mut context, &fixture.investor, snapshot_state_ix(&fixture, 7)).await.unwrap();
+    let archived = context.banks_client.get_account(snapshot_address).await.unwrap().unwrap();
+    let snapshot = StateSnapshot::try_from_slice(&archived.data).unwrap();
+    let escrow_data = context.banks_client.get_account(fixture.global_escrow).await.unwrap().unwrap().data;
+    let investor_data = context.banks_client.get_account(fixture.investor_pda).await.unwrap().unwrap().data;
+    assert_eq!((snapshot.global_escrow_data, snapshot.investor_data), (escrow_data, investor_data));
+    assert_eq!(snapshot.investor_account_pubkey, fixture.investor_pda);
+
+    // The state moves on, but the archive can't be rewritten
+    send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await.unwrap();
+    let result = send(&mut context, &fixture.investor, snapshot_state_ix(&fixture, 7)).await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::AccountAlreadyInitialized)
+    );
+    let unchanged = context.banks_client.get_account(snapshot_address).await.unwrap().unwrap();
+    assert_eq!(unchanged.data, archived.data);
+}
+
+#[tokio::test]
+async fn deposit_refund_and_close_conserve_sol_and_tokens() {
+    for sol_amount in [SOL / 10, SOL + 7, 3 * SOL, 8 * SOL - 1] {
+        let (test, fixture) = setup(0);
+        let mut context = test.start_with_context().await;
+        set_time(&mut context, VESTING_START + 1).await;
+        let start = holdings(&mut context, &fixture).await;
+
+        send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, sol_amount, false)).await.unwrap();
+        assert_eq!(holdings(&mut context, &fixture).await, start, "deposit of {}", sol_amount);
+
+        send(&mut context, &fixture.investor, refund_deposit_ix(&fixture, fixture.global_escrow)).await.unwrap();
+        assert_eq!(holdings(&mut context, &fixture).await, start, "refund after {}", sol_amount);
+
+        set_time(&mut context, SALE_END).await;
+        send(&mut context, &fixture.recipient, close_sale_ix(&fixture)).await.unwrap();
+        assert_eq!(holdings(&mut context, &fixture).await, start, "close after {}", sol_amount);
+    }
+}
+
+#[tokio::test]
+async fn partial_fill_takes_only_the_cost_of_the_tokens_left() {
+    for tokens_left in [1, 137, TOKENS_PER_SOL - 1] {
+        let (test, fixture) = setup_with(0, |escrow| {
+            escrow.allow_partial_fill = true;
+            escrow.total_tokens_available = TOKENS + tokens_left;
+        });
+        let mut context = test.start_with_context().await;
+        set_time(&mut context, VESTING_START + 1).await;
+
+        let balance_before = context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap();
+        send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await.unwrap();
+        let delivered_sol = balance_before - context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap();
+
+        // delivered + refunded == sol_amount, with the refund never exceeding the unfilled part
+        let refunded_sol = SOL - delivered_sol;
+        assert_eq!(delivered_sol, sol_for_tokens(tokens_left, SOL_USD_100 as u64, 25, 0).unwrap());
+        assert_eq!(delivered_sol + refunded_sol, SOL);
+        assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS + tokens_left);
+        assert_eq!(load_escrow(&mut context, &fixture).await.total_sol_deposited, 2 * SOL + delivered_sol);
+    }
+}
+
+#[tokio::test]
+async fn deposit_fill_cap_takes_exactly_what_is_left() {
+    let cap = 3 * SOL + 123;
+    let (test, fixture) = setup_with(0, |escrow| escrow.sol_hard_cap = cap);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START + 1).await;
+    let fill_cap = deposit_ix(&fixture, EscrowInstruction::DepositFillCap);
+
+    // One lamport over the cap is rejected outright
+    let result = send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL + 124, false)).await;
+    assert_escrow_error(result, EscrowError::HardCapExceeded);
+
+    let balance_before = context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap();
+    send(&mut context, &fixture.investor, fill_cap.clone()).await.unwrap();
+    let balance_after = context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap();
+    assert_eq!(balance_before - balance_after, SOL + 123);
+    assert_eq!(load_escrow(&mut context, &fixture).await.total_sol_deposited, cap);
+
+    let result = send(&mut context, &fixture.investor, fill_cap).await;
+    assert_escrow_error(result, EscrowError::HardCapExceeded);
+}
+
+#[tokio::test]
+async fn refund_deposit_twice_fails() {
+    let (test, fixture) = setup(0);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START).await;
+
+    send(&mut context, &fixture.investor, refund_deposit_ix(&fixture, fixture.global_escrow)).await.unwrap();
+
+    let position = load_investor(&mut context, &fixture).await;
+    assert_eq!(position.status, InvestorStatus::Refunded);
+    assert_eq!((position.sol_deposited, position.tokens_received, position.held_immediate_sol), (0, 0, 0));
+    assert_eq!(token_balance(&mut context, fixture.token_vault).await, 10 * TOKENS);
+    let escrow = load_escrow(&mut context, &fixture).await;
+    assert_eq!((escrow.tokens_sold, escrow.total_sol_deposited), (0, 0));
+
+    let result = send(&mut context, &fixture.investor, refund_deposit_ix(&fixture, fixture.global_escrow)).await;
+    assert_escrow_error(result, EscrowError::NoSolToWithdraw);
+}
+
+#[tokio::test]
+async fn unreachable_min_tokens_out_changes_nothing() {
+    let (test, fixture) = setup(0);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START + 1).await;
+    let deposit = |min_tokens_out| EscrowInstruction::DepositSol {
+        sol_amount: SOL,
+        min_tokens_out,
+        deadline: 0,
+        fill_to_cap: false,
+        whitelist_proof: vec![],
+    };
+
+    let escrow_before = context.banks_client.get_account(fixture.global_escrow).await.unwrap().unwrap();
+    let position_before = context.banks_client.get_account(fixture.investor_pda).await.unwrap().unwrap();
+    let holdings_before = holdings(&mut context, &fixture).await;
+    let balance_before = context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap();
+
+    let result = send(&mut context, &fixture.investor, deposit_ix(&fixture, deposit(TOKENS_PER_SOL + 1))).await;
+    assert_escrow_error(result, EscrowError::SlippageExceeded);
+    assert_eq!(context.banks_client.get_account(fixture.global_escrow).await.unwrap().unwrap(), escrow_before);
+    assert_eq!(context.banks_client.get_account(fixture.investor_pda).await.unwrap().unwrap(), position_before);
+    assert_eq!(holdings(&mut context, &fixture).await, holdings_before);
+    assert_eq!(context.banks_client.get_balance(fixture.investor.pubkey()).await.unwrap(), balance_before);
+
+    // The exact quote is still good
+    send(&mut context, &fixture.investor, deposit_ix(&fixture, deposit(TOKENS_PER_SOL))).await.unwrap();
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS + TOKENS_PER_SOL);
+}
+
+#[tokio::test]
+async fn deposits_are_priced_at_the_configured_flat_price() {
+    for (token_price_cents, tokens_per_sol) in [(25, TOKENS_PER_SOL), (100, TOKENS_PER_SOL / 4)] {
+        let (test, fixture) = setup_with(0, |escrow| escrow.token_price_cents = token_price_cents);
+        let mut context = test.start_with_context().await;
+        set_time(&mut context, VESTING_START + 1).await;
+
+        send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await.unwrap();
+        assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS + tokens_per_sol);
+    }
+}
+
+#[tokio::test]
+async fn whitelist_gates_deposits_to_listed_investors() {
+    let (test, fixture) = setup_with(0, |escrow| escrow.whitelist_required = true);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START + 1).await;
+    let (entry, _) = find_whitelist_pda(&fixture.investor.pubkey(), &fixture.global_escrow, &fixture.program_id);
+    let mut deposit = deposit_sol_ix(&fixture, SOL, false);
+    deposit.accounts.push(AccountMeta::new_readonly(entry, false));
+
+    let result = send(&mut context, &fixture.investor, deposit.clone()).await;
+    assert_escrow_error(result, EscrowError::Unauthorized);
+    assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 2 * SOL);
+
+    send(&mut context, &fixture.initializer, add_to_whitelist_ix(&fixture)).await.unwrap();
+    send(&mut context, &fixture.investor, deposit).await.unwrap();
+    assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 3 * SOL);
+}
+
+#[tokio::test]
+async fn frozen_withdrawals_leave_deposits_open() {
+    let (test, fixture) = setup(TOKENS);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START + CLIFF_DURATION + 10).await;
+
+    send(&mut context, &fixture.initializer, set_withdrawals_frozen_ix(&fixture, true)).await.unwrap();
+    let result = send(&mut context, &fixture.investor, claim_vested_tokens_ix(&fixture)).await;
+    assert_escrow_error(result, EscrowError::WithdrawalsFrozen);
+    send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await.unwrap();
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS_PER_SOL);
+
+    // Unfrozen, the claim releases what vested in the meantime
+    send(&mut context, &fixture.initializer, set_withdrawals_frozen_ix(&fixture, false)).await.unwrap();
+    send(&mut context, &fixture.investor, claim_vested_tokens_ix(&fixture)).await.unwrap();
+    let vested = TOKENS * 10 / (LOCK_DURATION - CLIFF_DURATION) as u64;
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS_PER_SOL + vested);
+}
+
+#[tokio::test]
+async fn claim_vested_tokens_at_cliff_and_full_vest() {
+    let (test, fixture) = setup(TOKENS);
+    let mut context = test.start_with_context().await;
+
+    // Exactly at the cliff nothing has vested yet
+    set_time(&mut context, VESTING_START + CLIFF_DURATION).await;
+    send(&mut context, &fixture.investor, claim_vested_tokens_ix(&fixture)).await.unwrap();
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, 0);
+
+    // One second later the first slice of the linear schedule is released
+    set_time(&mut context, VESTING_START + CLIFF_DURATION + 1).await;
+    send(&mut context, &fixture.investor, claim_vested_tokens_ix(&fixture)).await.unwrap();
+    let first_slice = TOKENS / (LOCK_DURATION - CLIFF_DURATION) as u64;
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, first_slice);
+
+    // Exactly at full vest the rest is claimable, and nothing more after that
+    set_time(&mut context, VESTING_START + LOCK_DURATION).await;
+    send(&mut context, &fixture.investor, claim_vested_tokens_ix(&fixture)).await.unwrap();
+    assert_eq!(token_balance(&mut context, fixture.investor_tokens).await, TOKENS);
+    assert_eq!(load_investor(&mut context, &fixture).await.tokens_claimed, TOKENS);
+
+    // Only the unsold tokens are left in the vault
+    send(&mut context, &fixture.investor, claim_vested_tokens_ix(&fixture)).await.unwrap();
+    assert_eq!(token_balance(&mut context, fixture.token_vault).await, 9 * TOKENS);
+}
+
+#[tokio::test]
+async fn close_sale_rejects_a_destination_of_another_mint() {
+    let (mut test, fixture) = setup(0);
+    let wrong_mint_tokens = Pubkey::new_unique();
+    test.add_account(wrong_mint_tokens, token_account(Pubkey::new_unique(), fixture.recipient.pubkey(), 0));
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, SALE_END).await;
+
+    let mut close = close_sale_ix(&fixture);
+    close.accounts[3] = AccountMeta::new(wrong_mint_tokens, false);
+    let result = send(&mut context, &fixture.recipient, close).await;
+    assert_escrow_error(result, EscrowError::InvalidTokenAccount);
+    assert_eq!(token_balance(&mut context, fixture.token_vault).await, 9 * TOKENS);
+    assert!(!load_escrow(&mut context, &fixture).await.sale_closed);
+
+    send(&mut context, &fixture.recipient, close_sale_ix(&fixture)).await.unwrap();
+    assert_eq!(token_balance(&mut context, fixture.recipient_tokens).await, 9 * TOKENS);
+}
+
+#[tokio::test]
+async fn deposit_deadline_is_inclusive() {
+    let (test, fixture) = setup(0);
+    let mut context = test.start_with_context().await;
+    let now = VESTING_START + 10;
+    set_time(&mut context, now).await;
+    let deposit = |deadline| {
+        let deposit = EscrowInstruction::DepositSol { sol_amount: SOL, min_tokens_out: 0, deadline, fill_to_cap: false, whitelist_proof: vec![] };
+        deposit_ix(&fixture, deposit)
+    };
+
+    let result = send(&mut context, &fixture.investor, deposit(now - 1)).await;
+    assert_escrow_error(result, EscrowError::DeadlineExceeded);
+    assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 2 * SOL);
+
+    send(&mut context, &fixture.investor, deposit(now)).await.unwrap();
+    send(&mut context, &fixture.investor, deposit(now + 1)).await.unwrap();
+    assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 4 * SOL);
+}
+
+#[tokio::test]
+async fn deposit_outside_the_price_band_fails() {
+    let price = SOL_USD_100 as u64;
+    for (min, max) in [(price + 1, u64::MAX), (1, price - 1)] {
+        let (test, fixture) = setup_with(0, |escrow| {
+            escrow.min_sol_usd_price = min;
+            escrow.max_sol_usd_price = max;
+        });
+        let mut context = test.start_with_context().await;
+        set_time(&mut context, VESTING_START + 1).await;
+
+        let result = send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await;
+        assert_escrow_error(result, EscrowError::PriceOutOfBounds);
+        assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 2 * SOL);
+    }
+
+    // Both ends of the band are inside it
+    let (test, fixture) = setup_with(0, |escrow| {
+        escrow.min_sol_usd_price = price;
+        escrow.max_sol_usd_price = price;
+    });
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START + 1).await;
+    send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, SOL, false)).await.unwrap();
+}
+
+#[tokio::test]
+async fn investor_account_is_rejected_as_global_escrow() {
+    let (test, fixture) = setup(0);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START).await;
+
+    let result = send(&mut context, &fixture.investor, refund_deposit_ix(&fixture, fixture.investor_pda)).await;
+    assert_escrow_error(result, EscrowError::AccountDiscriminatorMismatch);
+}
+
+#[tokio::test]
+async fn top_up_past_the_maximum_changes_nothing() {
+    let (test, fixture) = setup(0);
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, VESTING_START + 1).await;
+    let before = holdings(&mut context, &fixture).await;
+
+    // The position already holds 2 SOL of the 10 SOL maximum
+    let result = send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, 8 * SOL + 1, false)).await;
+    assert_escrow_error(result, EscrowError::InvestmentExceedsMaximum);
+    assert_eq!(holdings(&mut context, &fixture).await, before);
+    assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 2 * SOL);
+    assert_eq!(load_escrow(&mut context, &fixture).await.total_sol_deposited, 2 * SOL);
+
+    send(&mut context, &fixture.investor, deposit_sol_ix(&fixture, 8 * SOL, false)).await.unwrap();
+    assert_eq!(load_investor(&mut context, &fixture).await.sol_deposited, 10 * SOL);
+}